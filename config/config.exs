@@ -367,6 +367,60 @@ config :mydia, :features,
 # Can be overridden via P2P_BIND_PORT environment variable
 config :mydia, :p2p_bind_port, nil
 
+# Explicit UDP socket addresses to bind, at most one IPv4 and one IPv6
+# e.g. ["0.0.0.0:4001", "[::]:4001"] to pin both ports on dual-stack hosts
+# Can be overridden via P2P_BIND_ADDRS environment variable (comma-separated)
+config :mydia, :p2p_bind_addrs, []
+
+# Only bind the addresses of this network interface (e.g. "eth0"), on p2p_bind_port
+# Cannot be combined with p2p_bind_addrs
+# Can be overridden via P2P_BIND_INTERFACE environment variable
+config :mydia, :p2p_bind_interface, nil
+
+# Which relay servers to use for NAT traversal
+# :custom_with_defaults - IROH_RELAY_URL, falling back to iroh's public relays
+# :custom - only IROH_RELAY_URL
+# :default - only iroh's public relays
+# :disabled - no relays, direct/LAN connections only
+# Can be overridden via P2P_RELAY_MODE environment variable
+config :mydia, :p2p_relay_mode, :custom_with_defaults
+
+# mDNS discovery on the local network, so players on the same LAN connect directly
+# :announce_and_discover - make this server findable and find other nodes
+# :discover - only find other nodes
+# :disabled - no mDNS traffic
+# Can be overridden via P2P_LOCAL_DISCOVERY environment variable
+config :mydia, :p2p_local_discovery, :announce_and_discover
+
+# Service to publish this node's addresses to, so paired players can dial it by node id
+# :n0 - iroh's public DNS/pkarr servers
+# {:pkarr, "https://pkarr.example.com/pkarr"} - a self-hosted pkarr relay
+# :disabled - don't publish (players then need the full address or the LAN)
+# Can be overridden via P2P_LOOKUP_SERVICE environment variable ("n0", "disabled" or a URL)
+config :mydia, :p2p_lookup_service, :n0
+
+# Which devices get full access over P2P
+# :open - every peer (authorization is left to the request handlers)
+# {:allowlist, node_ids} - only these node ids and ones added with Mydia.P2p.Server.allow_peer/1;
+#   other peers may only ping and pair, a few requests at a time
+# Can be overridden via P2P_CONNECTION_POLICY environment variable ("open" or "allowlist")
+config :mydia, :p2p_connection_policy, :open
+
+# Caps on the requests each P2P peer may make; requests over them are refused
+# Keys: :max_concurrent_streams, :requests_per_second, :burst and :max_pending_responses
+# (requests waiting on this server from all peers). Unset keys keep the defaults.
+config :mydia, :p2p_request_limits, []
+
+# Caps on what unpaired P2P peers may send under the allowlist policy
+# Keys: :max_open_streams, :max_requests (per :window_ms) and :max_message_size (bytes).
+# Unset keys keep the defaults.
+config :mydia, :p2p_unpaired_limits, []
+
+# Only honor access tokens issued at P2P pairing on connections from the node id they were
+# issued to, so a leaked token can't be used from another device
+# Can be overridden via P2P_BIND_DEVICE_TOKENS environment variable ("true" or "false")
+config :mydia, :p2p_bind_device_tokens, true
+
 # Path to store the P2P keypair for persistent node identity
 # REQUIRED: Without this, the node ID changes on restart and paired devices can't reconnect
 # Can be overridden via P2P_KEYPAIR_PATH environment variable
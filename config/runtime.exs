@@ -444,6 +444,79 @@ if p2p_bind_port do
   config :mydia, :p2p_bind_port, p2p_bind_port
 end
 
+# P2P bind addresses and interface (all environments)
+case System.get_env("P2P_BIND_ADDRS") do
+  nil ->
+    :ok
+
+  "" ->
+    :ok
+
+  value ->
+    config :mydia,
+           :p2p_bind_addrs,
+           value |> String.split(",", trim: true) |> Enum.map(&String.trim/1)
+end
+
+case System.get_env("P2P_BIND_INTERFACE") do
+  nil -> :ok
+  "" -> :ok
+  value -> config :mydia, :p2p_bind_interface, value
+end
+
+# P2P relay mode configuration (all environments)
+p2p_relay_mode =
+  case System.get_env("P2P_RELAY_MODE") do
+    nil -> nil
+    "" -> nil
+    "default" -> :default
+    "custom" -> :custom
+    "custom_with_defaults" -> :custom_with_defaults
+    "disabled" -> :disabled
+    value -> raise "Invalid P2P_RELAY_MODE #{inspect(value)}"
+  end
+
+if p2p_relay_mode do
+  config :mydia, :p2p_relay_mode, p2p_relay_mode
+end
+
+# P2P local network discovery (all environments)
+case System.get_env("P2P_LOCAL_DISCOVERY") do
+  nil -> :ok
+  "" -> :ok
+  "announce_and_discover" -> config :mydia, :p2p_local_discovery, :announce_and_discover
+  "discover" -> config :mydia, :p2p_local_discovery, :discover
+  "disabled" -> config :mydia, :p2p_local_discovery, :disabled
+  value -> raise "Invalid P2P_LOCAL_DISCOVERY #{inspect(value)}"
+end
+
+# P2P lookup service (all environments)
+case System.get_env("P2P_LOOKUP_SERVICE") do
+  nil -> :ok
+  "" -> :ok
+  "n0" -> config :mydia, :p2p_lookup_service, :n0
+  "disabled" -> config :mydia, :p2p_lookup_service, :disabled
+  url -> config :mydia, :p2p_lookup_service, {:pkarr, url}
+end
+
+# P2P connection policy (all environments)
+case System.get_env("P2P_CONNECTION_POLICY") do
+  nil -> :ok
+  "" -> :ok
+  "open" -> config :mydia, :p2p_connection_policy, :open
+  "allowlist" -> config :mydia, :p2p_connection_policy, {:allowlist, []}
+  value -> raise "Invalid P2P_CONNECTION_POLICY #{inspect(value)}"
+end
+
+# P2P device token binding (all environments)
+case System.get_env("P2P_BIND_DEVICE_TOKENS") do
+  nil -> :ok
+  "" -> :ok
+  "true" -> config :mydia, :p2p_bind_device_tokens, true
+  "false" -> config :mydia, :p2p_bind_device_tokens, false
+  value -> raise "Invalid P2P_BIND_DEVICE_TOKENS #{inspect(value)}"
+end
+
 # P2P keypair path configuration (all environments)
 # REQUIRED for persistent node identity - paired devices need this to reconnect
 p2p_keypair_path =
@@ -53,6 +53,23 @@ defmodule Mydia.Auth.Guardian do
     end
   end
 
+  @doc """
+  Verifies a token presented over a P2P connection from `node_id`, returning
+  the user. A token bound to a node id at pairing is rejected from any other node.
+  """
+  def verify_token_for_node(token, node_id) do
+    case decode_and_verify(token) do
+      {:ok, %{"node_id" => bound_node_id}} when bound_node_id != node_id ->
+        {:error, :node_mismatch}
+
+      {:ok, claims} ->
+        resource_from_claims(claims)
+
+      {:error, reason} ->
+        {:error, reason}
+    end
+  end
+
   @doc """
   Revokes a token (for logout).
   """
@@ -12,23 +12,109 @@ defmodule Mydia.P2p do
   Start the p2p host with configuration.
 
   ## Options
-    * `:relay_url` - Custom relay URL for NAT traversal (uses iroh default relays if nil).
+    * `:relay_policy` - Relay servers for NAT traversal: `:default` (iroh's relays),
+      `{:custom, urls}`, `{:custom_with_defaults, urls}` or `:disabled` (direct/LAN only).
     * `:bind_port` - UDP port for direct connections (enables hole punching in Docker).
       If nil or 0, a random port is used.
     * `:keypair_path` - Path to store/load the node's keypair for persistent identity.
       If nil, a new random keypair is generated on each start.
+    * `:bind_addrs` - Explicit socket addresses to bind, at most one IPv4 and one IPv6
+      (e.g. `["0.0.0.0:4001", "[::]:4001"]`). An IPv4 address overrides `:bind_port`.
+    * `:bind_interface` - Only bind the addresses of this network interface (e.g. `"eth0"`),
+      on `:bind_port`. Cannot be combined with `:bind_addrs`.
+    * `:local_discovery` - mDNS on the local network: `:disabled`, `:discover`
+      (find other nodes) or `:announce_and_discover` (also make this node findable).
+      Found nodes arrive as `{:ok, "peer_discovered", node_id, addrs}` events.
+    * `:lookup` - Service this node publishes its addresses to and looks up peers
+      dialed by node id in: `:n0` (iroh's public DNS), `{:pkarr, url}` (a self-hosted
+      pkarr relay) or `:disabled`.
+    * `:connection_policy` - `:open` (every peer gets full access) or
+      `{:allowlist, node_ids}`, where peers not on the list may only ping and pair,
+      a few requests at a time. Manage the list with `allow_peer/2` and `revoke_peer/2`.
+    * `:request_limits` - `%Mydia.P2p.RequestLimits{}` capping every peer's request
+      rate and concurrency, or nil for the defaults. Requests over a limit are
+      answered with a 429 or 503 error and counted in `rejected_requests`.
+    * `:unpaired_limits` - `%Mydia.P2p.UnpairedLimits{}` capping what peers off the
+      allowlist may send, or nil for the defaults.
 
-  Returns `{:ok, {resource, node_id}}` on success.
+  Waits for the endpoint to bind. Returns `{:ok, {resource, node_id}}` on success,
+  or `{:error, %Mydia.P2p.Error{}}` if the port is in use, the relay URL is
+  malformed or the keypair cannot be read (`:keypair`) or holds no valid key
+  (`:keypair_corrupt`). An existing keypair file is never overwritten.
   """
-  def start_host(_relay_url \\ nil, _bind_port \\ nil, _keypair_path \\ nil),
-    do: :erlang.nif_error(:nif_not_loaded)
+  def start_host(
+        _relay_policy \\ :default,
+        _bind_port \\ nil,
+        _keypair_path \\ nil,
+        _bind_addrs \\ [],
+        _bind_interface \\ nil,
+        _local_discovery \\ :disabled,
+        _lookup \\ :n0,
+        _connection_policy \\ :open,
+        _request_limits \\ nil,
+        _unpaired_limits \\ nil
+      ),
+      do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Stop the p2p host. In-flight requests and HLS streams get up to `grace_ms`
+  to finish before connections are closed. The bind port is free again once
+  this returns. Also happens automatically when the resource is garbage collected.
+  """
+  def stop_host(_resource, _grace_ms \\ 5_000), do: :erlang.nif_error(:nif_not_loaded)
 
   @doc """
   Dial a peer using their EndpointAddr JSON.
-  The endpoint_addr_json should be a JSON-serialized EndpointAddr.
+  The endpoint_addr_json should be a JSON-serialized EndpointAddr, or a bare
+  node id whose addresses are in the address book, found by local discovery
+  or published to the lookup service.
   """
   def dial(_resource, _endpoint_addr_json), do: :erlang.nif_error(:nif_not_loaded)
 
+  @doc """
+  Add a peer's EndpointAddr JSON to the address book, so it can be dialed by
+  node id. Peers dialed by EndpointAddr are added automatically.
+  """
+  def add_peer_addr(_resource, _endpoint_addr_json), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Give a paired device's node id full access under the `{:allowlist, node_ids}`
+  connection policy.
+  """
+  def allow_peer(_resource, _node_id), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Take full access away from a device's node id. Its open connection is kept,
+  but is limited like any unpaired peer's.
+  """
+  def revoke_peer(_resource, _node_id), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  End the authenticated session of the connection from a node id by closing
+  the connection. Returns `{:ok, true}` if there was one.
+  """
+  def revoke_session(_resource, _node_id), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Close the connection from a node id. The peer receives `reason` (`:disconnected`,
+  `:revoked`, `:banned` or `:session_revoked`) and does not redial.
+  Returns `{:ok, true}` if there was a connection to close.
+  """
+  def disconnect_peer(_resource, _node_id, _reason \\ :disconnected),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Refuse connections from a node id for `duration_ms`, or until `unban_peer/2`
+  if nil, and close its current connection with reason `:banned`.
+  Bans are kept in memory and end when the host stops.
+  """
+  def ban_peer(_resource, _node_id, _duration_ms \\ nil), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Lift a ban. Returns `{:ok, true}` if the node id was banned.
+  """
+  def unban_peer(_resource, _node_id), do: :erlang.nif_error(:nif_not_loaded)
+
   @doc """
   Get this node's EndpointAddr as JSON for sharing with other peers.
   """
@@ -36,7 +122,14 @@ defmodule Mydia.P2p do
 
   @doc """
   Start listening for p2p events.
-  Events will be sent as messages to the given process.
+  Events will be sent as messages to the given process. Request events carry
+  the node id of the peer that sent them, as authenticated by the connection
+  handshake, and the `%Mydia.P2p.Session{}` its connection authenticated as
+  (or nil): `{:ok, "request_received", kind, request_id, peer, session, request}`
+  and `{:ok, "hls_stream", stream_id, peer, session, request}`.
+
+  Answer an `"authenticate"` request, whose request is the token, with
+  `{:authenticated, %Mydia.P2p.Session{}}` to set the connection's session.
   """
   def start_listening(_resource, _pid), do: :erlang.nif_error(:nif_not_loaded)
 
@@ -57,6 +150,17 @@ defmodule Mydia.P2p do
   """
   def get_network_stats(_resource), do: :erlang.nif_error(:nif_not_loaded)
 
+  @doc """
+  List every connected peer as `Mydia.P2p.PeerInfo` structs.
+  """
+  def list_peers(_resource), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc """
+  Get the latest latency probe results for every relay as `Mydia.P2p.RelayHealth`
+  structs, lowest latency first.
+  """
+  def get_relay_health(_resource), do: :erlang.nif_error(:nif_not_loaded)
+
   @doc """
   Send an HLS response header for a streaming request.
   Must be called before any send_hls_chunk calls.
@@ -84,6 +188,41 @@ defmodule Mydia.P2p do
     do: :erlang.nif_error(:nif_not_loaded)
 end
 
+defmodule Mydia.P2p.Error do
+  @moduledoc """
+  Error returned by p2p NIF calls as `{:error, %Mydia.P2p.Error{}}`.
+
+  `kind` is one of `:not_connected`, `:connect`, `:invalid_address`,
+  `:stream_not_found`, `:timeout`, `:protocol`, `:io`, `:remote`, `:cancelled`,
+  `:shutdown`, `:bind`, `:invalid_config`, `:keypair`, `:keypair_corrupt` or
+  `:refused` (the peer closed the connection on purpose, e.g. it banned this node).
+  `code` is only set for `:remote` errors.
+  """
+  defstruct [:kind, :message, :code, retryable: false]
+
+  @type t :: %__MODULE__{
+          kind: atom(),
+          message: String.t(),
+          code: non_neg_integer() | nil,
+          retryable: boolean()
+        }
+end
+
+defmodule Mydia.P2p.WireError do
+  @moduledoc """
+  A structured error response to send back to a player, sent as
+  `{:failure, %Mydia.P2p.WireError{}}`. Codes follow HTTP status semantics.
+  Players that predate structured errors receive only the message.
+  """
+  defstruct [:code, :message, retryable: false]
+
+  @type t :: %__MODULE__{
+          code: non_neg_integer(),
+          message: String.t(),
+          retryable: boolean()
+        }
+end
+
 defmodule Mydia.P2p.PairingRequest do
   @moduledoc """
   A pairing request received from a player.
@@ -131,13 +270,78 @@ defmodule Mydia.P2p.NetworkStats do
   @moduledoc """
   Network statistics from the p2p host.
   """
-  defstruct [:connected_peers, :relay_connected, :relay_url, :peer_connection_type]
+  defstruct [
+    :connected_peers,
+    :relay_connected,
+    :relay_url,
+    :relay_policy,
+    :peer_connection_type,
+    bound_addrs: [],
+    rejected_requests: 0
+  ]
 
   @type t :: %__MODULE__{
           connected_peers: non_neg_integer(),
           relay_connected: boolean(),
           relay_url: String.t() | nil,
-          peer_connection_type: String.t()
+          relay_policy: String.t(),
+          bound_addrs: [String.t()],
+          peer_connection_type: String.t(),
+          rejected_requests: non_neg_integer()
+        }
+end
+
+defmodule Mydia.P2p.PeerInfo do
+  @moduledoc """
+  Details about a single peer connection.
+
+  `connection_type` is "direct", "relay", "mixed" or "none". `connected_since`
+  is a unix timestamp in seconds and `open_streams` counts in-flight requests
+  and HLS streams. `rejected_requests` counts requests from the peer refused by
+  the connection policy or request limits.
+  """
+  defstruct [
+    :node_id,
+    :connection_type,
+    :rtt_ms,
+    :connected_since,
+    remote_addrs: [],
+    bytes_sent: 0,
+    bytes_received: 0,
+    open_streams: 0,
+    rejected_requests: 0
+  ]
+
+  @type t :: %__MODULE__{
+          node_id: String.t(),
+          connection_type: String.t(),
+          remote_addrs: [String.t()],
+          rtt_ms: non_neg_integer() | nil,
+          bytes_sent: non_neg_integer(),
+          bytes_received: non_neg_integer(),
+          connected_since: non_neg_integer(),
+          open_streams: non_neg_integer(),
+          rejected_requests: non_neg_integer()
+        }
+end
+
+defmodule Mydia.P2p.RelayHealth do
+  @moduledoc """
+  Health of a single relay server.
+
+  Every relay's HTTPS probe endpoint is requested at startup and about every
+  30 seconds. `last_error` is why the last failed probe failed, and is kept
+  after the relay recovers. This is a report only: iroh picks the `home`
+  relay by itself.
+  """
+  defstruct [:url, :latency_ms, :last_error, home: false, reachable: false]
+
+  @type t :: %__MODULE__{
+          url: String.t(),
+          home: boolean(),
+          reachable: boolean(),
+          latency_ms: non_neg_integer() | nil,
+          last_error: String.t() | nil
         }
 end
 
@@ -182,6 +386,62 @@ defmodule Mydia.P2p.HlsRequest do
         }
 end
 
+defmodule Mydia.P2p.RequestLimits do
+  @moduledoc """
+  Caps on the requests every peer may make, paired or not.
+
+  A peer may have `max_concurrent_streams` requests and HLS streams in flight,
+  and make `requests_per_second` requests on average with bursts of up to
+  `burst`. At most `max_pending_responses` requests from all peers wait on
+  this node at once.
+  """
+  defstruct max_concurrent_streams: 32,
+            requests_per_second: 50,
+            burst: 100,
+            max_pending_responses: 512
+
+  @type t :: %__MODULE__{
+          max_concurrent_streams: pos_integer(),
+          requests_per_second: non_neg_integer(),
+          burst: pos_integer(),
+          max_pending_responses: pos_integer()
+        }
+end
+
+defmodule Mydia.P2p.UnpairedLimits do
+  @moduledoc """
+  Caps on the requests of peers not on the `{:allowlist, node_ids}` list.
+
+  Such a peer may have `max_open_streams` requests in flight and make
+  `max_requests` requests per `window_ms`. Requests bigger than
+  `max_message_size` bytes are dropped before they are read.
+  """
+  defstruct max_open_streams: 2,
+            max_requests: 10,
+            window_ms: 60_000,
+            max_message_size: 4096
+
+  @type t :: %__MODULE__{
+          max_open_streams: pos_integer(),
+          max_requests: pos_integer(),
+          window_ms: pos_integer(),
+          max_message_size: pos_integer()
+        }
+end
+
+defmodule Mydia.P2p.Session do
+  @moduledoc """
+  Who a P2P connection authenticated as. Set once per connection by answering
+  its authenticate request, then attached to every later request event.
+  """
+  defstruct [:principal, scopes: []]
+
+  @type t :: %__MODULE__{
+          principal: String.t(),
+          scopes: [String.t()]
+        }
+end
+
 defmodule Mydia.P2p.HlsResponseHeader do
   @moduledoc """
   An HLS response header to send back to a player over P2P.
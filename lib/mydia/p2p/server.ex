@@ -9,11 +9,19 @@ defmodule Mydia.P2p.Server do
   require Logger
 
   alias Mydia.P2p
+  alias Mydia.RemoteAccess
   alias Mydia.RemoteAccess.DirectUrls
   alias Mydia.RemoteAccess.Pairing
   alias Mydia.Streaming.HlsSession
   alias MydiaWeb.Schema.Middleware.Logging, as: GraphQLLogging
 
+  # What a connection that authenticated with an access token may do
+  @session_scopes ["graphql", "hls"]
+
+  # Grace period for in-flight streams when stopping, well inside the
+  # supervisor's default 5s shutdown so the host closes and frees its port
+  @stop_grace_ms 2_000
+
   @doc """
   Status information about the p2p host.
   """
@@ -25,7 +33,9 @@ defmodule Mydia.P2p.Server do
       :connected_peers,
       :relay_connected,
       :relay_url,
-      :peer_connection_type
+      :relay_policy,
+      :peer_connection_type,
+      relay_health: []
     ]
 
     @type t :: %__MODULE__{
@@ -35,7 +45,9 @@ defmodule Mydia.P2p.Server do
             connected_peers: non_neg_integer(),
             relay_connected: boolean(),
             relay_url: String.t() | nil,
-            peer_connection_type: String.t() | nil
+            relay_policy: String.t(),
+            peer_connection_type: String.t() | nil,
+            relay_health: [P2p.RelayHealth.t()]
           }
   end
 
@@ -44,12 +56,48 @@ defmodule Mydia.P2p.Server do
   end
 
   def init(_) do
-    # Default to our own relay; override via IROH_RELAY_URL env var
-    relay_url = System.get_env("IROH_RELAY_URL", "https://cae1-1.relay.mydia.dev")
+    # Trap exits so terminate/2 runs on shutdown and releases the bind port
+    Process.flag(:trap_exit, true)
+
+    # Default to our own relay; override via IROH_RELAY_URL env var (comma-separated for several)
+    relay_urls =
+      "IROH_RELAY_URL"
+      |> System.get_env("https://cae1-1.relay.mydia.dev")
+      |> String.split(",", trim: true)
+      |> Enum.map(&String.trim/1)
+
+    # Which relays to use (our relay plus iroh's by default, see config :p2p_relay_mode)
+    relay_policy =
+      relay_policy(Application.get_env(:mydia, :p2p_relay_mode, :custom_with_defaults), relay_urls)
 
     # Get bind_port from config (required for hole punching in Docker)
     bind_port = Application.get_env(:mydia, :p2p_bind_port)
 
+    # Optional explicit socket addresses or a single interface to bind (multi-homed/IPv6 hosts)
+    bind_addrs = Application.get_env(:mydia, :p2p_bind_addrs, [])
+    bind_interface = Application.get_env(:mydia, :p2p_bind_interface)
+
+    # Let players on the same LAN find this server without a relay
+    local_discovery = Application.get_env(:mydia, :p2p_local_discovery, :announce_and_discover)
+
+    # Where to publish our addresses so paired players can dial us by node id
+    lookup = Application.get_env(:mydia, :p2p_lookup_service, :n0)
+
+    # Whether unpaired devices are limited to pinging and pairing
+    connection_policy =
+      case Application.get_env(:mydia, :p2p_connection_policy, :open) do
+        {:allowlist, node_ids} -> {:allowlist, node_ids ++ RemoteAccess.list_active_node_ids()}
+        policy -> policy
+      end
+
+    # Caps on each peer's request rate and concurrency, so one device can't flood us
+    request_limits =
+      struct!(P2p.RequestLimits, Application.get_env(:mydia, :p2p_request_limits, []))
+
+    # What devices that haven't paired yet may send under the allowlist policy
+    unpaired_limits =
+      struct!(P2p.UnpairedLimits, Application.get_env(:mydia, :p2p_unpaired_limits, []))
+
     # Get keypair_path from config for persistent node identity
     # This is REQUIRED - without it, the node ID changes on restart and paired devices can't reconnect
     keypair_path =
@@ -67,18 +115,45 @@ defmodule Mydia.P2p.Server do
         Or set the P2P_KEYPAIR_PATH environment variable.
         """
 
-    # Start the host - NIF returns {resource, node_id} directly (raises on error)
-    {resource, node_id} = P2p.start_host(relay_url, bind_port, keypair_path)
+    case P2p.start_host(
+           relay_policy,
+           bind_port,
+           keypair_path,
+           bind_addrs,
+           bind_interface,
+           local_discovery,
+           lookup,
+           connection_policy,
+           request_limits,
+           unpaired_limits
+         ) do
+      {:ok, {resource, node_id}} ->
+        start_listening(resource, node_id, relay_policy, keypair_path)
+
+      {:error, %P2p.Error{kind: :keypair_corrupt} = error} ->
+        Logger.error("""
+        P2P Host failed to start: #{error.message}
+
+        The keypair at #{keypair_path} was left untouched, as a new identity would
+        unpair every device. Restore it from a backup, or move it away to start over.
+        """)
+
+        {:stop, error}
+
+      {:error, %P2p.Error{} = error} ->
+        Logger.error("P2P Host failed to start: #{error.message}")
+        {:stop, error}
+    end
+  end
 
-    Logger.info("P2P Host started with NodeID: #{node_id}, relay: #{relay_url}")
+  defp start_listening(resource, node_id, relay_policy, keypair_path) do
+    Logger.info("P2P Host started with NodeID: #{node_id}, relays: #{inspect(relay_policy)}")
 
     Logger.info("P2P Host using persistent keypair at #{keypair_path}")
 
-    if bind_port do
-      Logger.info("P2P Host using UDP port #{bind_port}")
-    else
-      Logger.info("P2P Host using random port")
-    end
+    # Report the actual sockets so operators know which UDP ports to open
+    %P2p.NetworkStats{bound_addrs: bound_addrs} = P2p.get_network_stats(resource)
+    Logger.info("P2P Host bound to UDP #{Enum.join(bound_addrs, ", ")}")
 
     # Start listening for events, sending them to self()
     # NIF returns "ok" directly (raises on error)
@@ -90,19 +165,71 @@ defmodule Mydia.P2p.Server do
       node_addr: nil,
       relay_connected: false,
       # Track connected peers (Map of peer_id => connection_type)
-      connected_peers: %{}
+      connected_peers: %{},
+      # Running HLS stream handlers (Map of stream_id => pid)
+      hls_tasks: %{},
+      # Running GraphQL requests (Map of request_id => pid)
+      graphql_tasks: %{}
     }
 
     {:ok, state}
   end
 
+  defp relay_policy(mode, _relay_urls) when mode in [:default, :disabled], do: mode
+
+  defp relay_policy(mode, relay_urls) when mode in [:custom, :custom_with_defaults],
+    do: {mode, relay_urls}
+
   @doc """
-  Dial a peer using their EndpointAddr JSON.
+  Dial a peer using their EndpointAddr JSON or node id.
   """
   def dial(endpoint_addr_json) do
     GenServer.call(__MODULE__, {:dial, endpoint_addr_json})
   end
 
+  @doc """
+  Give a paired device's node id full access under the allowlist connection policy.
+  """
+  def allow_peer(node_id) do
+    GenServer.call(__MODULE__, {:allow_peer, node_id})
+  end
+
+  @doc """
+  Take full access away from a revoked device's node id.
+  """
+  def revoke_peer(node_id) do
+    GenServer.call(__MODULE__, {:revoke_peer, node_id})
+  end
+
+  @doc """
+  End the authenticated session of the connection from `node_id` by closing it.
+  """
+  def revoke_session(node_id) do
+    GenServer.call(__MODULE__, {:revoke_session, node_id})
+  end
+
+  @doc """
+  Close the connection from `node_id`, telling the device why.
+  """
+  def disconnect_peer(node_id, reason \\ :disconnected) do
+    GenServer.call(__MODULE__, {:disconnect_peer, node_id, reason})
+  end
+
+  @doc """
+  Refuse connections from `node_id` for `duration_ms`, or until unbanned if nil.
+  Bans are not persisted and end when the server restarts.
+  """
+  def ban_peer(node_id, duration_ms \\ nil) do
+    GenServer.call(__MODULE__, {:ban_peer, node_id, duration_ms})
+  end
+
+  @doc """
+  Lift a ban on `node_id`.
+  """
+  def unban_peer(node_id) do
+    GenServer.call(__MODULE__, {:unban_peer, node_id})
+  end
+
   @doc """
   Get the node ID (PublicKey) of this p2p host.
   """
@@ -133,6 +260,22 @@ defmodule Mydia.P2p.Server do
     GenServer.call(__MODULE__, :network_stats)
   end
 
+  @doc """
+  List every connected peer with its connection details.
+  """
+  @spec list_peers() :: [P2p.PeerInfo.t()]
+  def list_peers do
+    GenServer.call(__MODULE__, :list_peers)
+  end
+
+  @doc """
+  Get the latest latency probe results for every relay, lowest latency first.
+  """
+  @spec relay_health() :: [P2p.RelayHealth.t()]
+  def relay_health do
+    GenServer.call(__MODULE__, :relay_health)
+  end
+
   # GenServer callbacks
 
   def handle_call({:dial, endpoint_addr_json}, _from, state) do
@@ -140,6 +283,36 @@ defmodule Mydia.P2p.Server do
     {:reply, result, state}
   end
 
+  def handle_call({:allow_peer, node_id}, _from, state) do
+    result = P2p.allow_peer(state.resource, node_id)
+    {:reply, result, state}
+  end
+
+  def handle_call({:revoke_peer, node_id}, _from, state) do
+    result = P2p.revoke_peer(state.resource, node_id)
+    {:reply, result, state}
+  end
+
+  def handle_call({:revoke_session, node_id}, _from, state) do
+    result = P2p.revoke_session(state.resource, node_id)
+    {:reply, result, state}
+  end
+
+  def handle_call({:disconnect_peer, node_id, reason}, _from, state) do
+    result = P2p.disconnect_peer(state.resource, node_id, reason)
+    {:reply, result, state}
+  end
+
+  def handle_call({:ban_peer, node_id, duration_ms}, _from, state) do
+    result = P2p.ban_peer(state.resource, node_id, duration_ms)
+    {:reply, result, state}
+  end
+
+  def handle_call({:unban_peer, node_id}, _from, state) do
+    result = P2p.unban_peer(state.resource, node_id)
+    {:reply, result, state}
+  end
+
   def handle_call(:node_id, _from, state) do
     {:reply, state.node_id, state}
   end
@@ -169,7 +342,9 @@ defmodule Mydia.P2p.Server do
       connected_peers: map_size(state.connected_peers),
       relay_connected: network_stats.relay_connected,
       relay_url: relay_url,
-      peer_connection_type: peer_connection_type
+      relay_policy: network_stats.relay_policy,
+      peer_connection_type: peer_connection_type,
+      relay_health: P2p.get_relay_health(state.resource)
     }
 
     {:reply, status, state}
@@ -180,10 +355,28 @@ defmodule Mydia.P2p.Server do
     {:reply, stats, state}
   end
 
+  def handle_call(:list_peers, _from, state) do
+    {:reply, P2p.list_peers(state.resource), state}
+  end
+
+  def handle_call(:relay_health, _from, state) do
+    {:reply, P2p.get_relay_health(state.resource), state}
+  end
+
+  def terminate(_reason, state) do
+    P2p.stop_host(state.resource, @stop_grace_ms)
+  end
+
   # Handle events from Rust NIF
 
-  def handle_info({:ok, "peer_connected", peer_id, connection_type}, state) do
-    Logger.info("P2P Event: Peer Connected #{peer_id} (#{connection_type})")
+  def handle_info(
+        {:ok, "peer_connected", peer_id, connection_type, protocol_version, capabilities},
+        state
+      ) do
+    Logger.info(
+      "P2P Event: Peer Connected #{peer_id} (#{connection_type}, protocol #{protocol_version}, capabilities: #{inspect(capabilities)})"
+    )
+
     state = %{state | connected_peers: Map.put(state.connected_peers, peer_id, connection_type)}
     {:noreply, state}
   end
@@ -194,6 +387,31 @@ defmodule Mydia.P2p.Server do
     {:noreply, state}
   end
 
+  def handle_info({:ok, "peer_path_changed", peer_id, remote_addr}, state) do
+    Logger.debug("P2P Event: Selected path for #{peer_id} is now #{remote_addr}")
+    {:noreply, state}
+  end
+
+  def handle_info({:ok, "peer_reconnecting", peer_id, attempt}, state) do
+    Logger.info("P2P Event: Reconnecting to #{peer_id} (attempt #{attempt})")
+    {:noreply, state}
+  end
+
+  def handle_info({:ok, "peer_reconnected", peer_id, attempts}, state) do
+    Logger.info("P2P Event: Reconnected to #{peer_id} after #{attempts} attempt(s)")
+    {:noreply, state}
+  end
+
+  def handle_info({:ok, "peer_closed", peer_id, reason}, state) do
+    Logger.info("P2P Event: #{peer_id} closed the connection (#{reason})")
+    {:noreply, state}
+  end
+
+  def handle_info({:ok, "peer_discovered", node_id, addrs}, state) do
+    Logger.debug("P2P Event: Discovered #{node_id} on the local network at #{inspect(addrs)}")
+    {:noreply, state}
+  end
+
   def handle_info({:ok, "peer_disconnected", peer_id}, state) do
     Logger.info("P2P Event: Peer Disconnected #{peer_id}")
     state = %{state | connected_peers: Map.delete(state.connected_peers, peer_id)}
@@ -206,6 +424,12 @@ defmodule Mydia.P2p.Server do
     {:noreply, state}
   end
 
+  def handle_info({:ok, "relay_disconnected"}, state) do
+    Logger.warning("P2P Event: Relay Disconnected")
+    state = %{state | relay_connected: false}
+    {:noreply, state}
+  end
+
   def handle_info({:ok, "ready", node_addr}, state) do
     Logger.info("P2P Event: Ready with address")
     enriched = enrich_node_addr(node_addr)
@@ -214,12 +438,13 @@ defmodule Mydia.P2p.Server do
     {:noreply, state}
   end
 
-  def handle_info({:ok, "request_received", "pairing", request_id, req}, state) do
-    Logger.info("P2P Request: Pairing from #{req.device_name}")
+  def handle_info({:ok, "request_received", "pairing", request_id, peer, _session, req}, state) do
+    Logger.info("P2P Request: Pairing from #{req.device_name} (#{peer})")
 
     device_attrs = %{
       device_name: req.device_name,
-      platform: req.device_type || req.device_os || "unknown"
+      platform: req.device_type || req.device_os || "unknown",
+      node_id: peer
     }
 
     # Get direct URLs from config for the client to use
@@ -232,6 +457,9 @@ defmodule Mydia.P2p.Server do
     response =
       case Pairing.complete_pairing(req.claim_code, device_attrs) do
         {:ok, _device, media_token, access_token, device_token} ->
+          # Give the device full access under the allowlist connection policy
+          P2p.allow_peer(state.resource, peer)
+
           %P2p.PairingResponse{
             success: true,
             media_token: media_token,
@@ -257,12 +485,34 @@ defmodule Mydia.P2p.Server do
     {:noreply, state}
   end
 
-  def handle_info({:ok, "request_received", "ping", _request_id}, state) do
+  def handle_info({:ok, "request_received", "ping", _request_id, _peer, _session}, state) do
     Logger.debug("P2P Ping Request received")
     {:noreply, state}
   end
 
-  def handle_info({:ok, "request_received", "read_media", request_id, req}, state) do
+  def handle_info(
+        {:ok, "request_received", "authenticate", request_id, peer, _session, token},
+        state
+      ) do
+    response =
+      case verify_p2p_token(token, peer) do
+        {:ok, user} ->
+          Logger.info("P2P: #{peer} authenticated as user #{user.id}")
+          {:authenticated, %P2p.Session{principal: to_string(user.id), scopes: @session_scopes}}
+
+        {:error, reason} ->
+          Logger.warning("P2P: Authentication failed for #{peer}: #{inspect(reason)}")
+          {:failure, %P2p.WireError{code: 401, message: "Unauthorized"}}
+      end
+
+    P2p.send_response(state.resource, request_id, response)
+    {:noreply, state}
+  end
+
+  def handle_info(
+        {:ok, "request_received", "read_media", request_id, _peer, _session, req},
+        state
+      ) do
     # Validate file path exists
     # SECURITY: In production, verify path is within allowed directories!
     if File.exists?(req.file_path) do
@@ -276,67 +526,32 @@ defmodule Mydia.P2p.Server do
       )
     else
       Logger.warning("Requested file not found: #{req.file_path}")
-      P2p.send_response(state.resource, request_id, {:error, "File not found"})
+      P2p.send_response(
+        state.resource,
+        request_id,
+        {:failure, %P2p.WireError{code: 404, message: "File not found"}}
+      )
     end
 
     {:noreply, state}
   end
 
-  def handle_info({:ok, "request_received", "graphql", request_id, req}, state) do
-    Logger.debug("P2P Request: GraphQL query")
+  def handle_info({:ok, "request_received", "graphql", request_id, peer, session, req}, state) do
+    Logger.debug("P2P Request: GraphQL query from #{peer}")
 
-    # Parse variables from JSON
-    variables = parse_graphql_variables(req.variables)
-
-    # Build context from auth token, marking source as p2p
+    resource = state.resource
     # Include peer connection type so resolvers can enforce relay caps
-    peer_connection_type = infer_peer_connection_type(state.connected_peers)
-    context = build_graphql_context(req.auth_token, :p2p, peer_connection_type)
+    peer_connection_type = Map.get(state.connected_peers, peer)
 
-    # Execute the GraphQL query with logging
-    result =
-      GraphQLLogging.run(
-        req.query,
-        MydiaWeb.Schema,
-        variables: variables,
-        operation_name: req.operation_name,
-        context: context
-      )
-
-    response =
-      case result do
-        {:ok, %{data: data, errors: errors}} ->
-          %P2p.GraphQLResponse{
-            data: encode_json(data),
-            errors: encode_graphql_errors(errors)
-          }
-
-        {:ok, %{data: data}} ->
-          %P2p.GraphQLResponse{
-            data: encode_json(data),
-            errors: nil
-          }
-
-        # Query validation errors (no data key, only errors)
-        {:ok, %{errors: errors}} ->
-          Logger.warning("GraphQL validation error: #{inspect(errors)}")
-
-          %P2p.GraphQLResponse{
-            data: nil,
-            errors: encode_graphql_errors(errors)
-          }
-
-        {:error, reason} ->
-          Logger.warning("GraphQL execution failed: #{inspect(reason)}")
-
-          %P2p.GraphQLResponse{
-            data: nil,
-            errors: encode_json([%{message: inspect(reason)}])
-          }
-      end
+    # Run the query in a task so a client cancellation can stop its resolvers
+    {:ok, pid} =
+      Task.start(fn ->
+        response = run_graphql(req, peer, session, peer_connection_type)
+        P2p.send_response(resource, request_id, {:graphql, response})
+      end)
 
-    P2p.send_response(state.resource, request_id, {:graphql, response})
-    {:noreply, state}
+    Process.monitor(pid)
+    {:noreply, %{state | graphql_tasks: Map.put(state.graphql_tasks, request_id, pid)}}
   end
 
   def handle_info({:ok, "unknown_request"}, state) do
@@ -344,23 +559,62 @@ defmodule Mydia.P2p.Server do
     {:noreply, state}
   end
 
-  def handle_info({:ok, "hls_stream", stream_id, req}, state) do
+  def handle_info({:ok, "hls_stream", stream_id, peer, session, req}, state) do
     Logger.debug("P2P Request: HLS stream session=#{req.session_id} path=#{req.path}")
 
     resource = state.resource
 
     # Spawn a task to handle the streaming so we don't block the GenServer
-    Task.start(fn ->
-      t0 = System.monotonic_time(:millisecond)
-      handle_hls_stream(resource, stream_id, req)
-      elapsed = System.monotonic_time(:millisecond) - t0
+    {:ok, pid} =
+      Task.start(fn ->
+        t0 = System.monotonic_time(:millisecond)
+        handle_hls_stream(resource, stream_id, peer, session, req)
+        elapsed = System.monotonic_time(:millisecond) - t0
 
-      Logger.info(
-        "p2p_metrics_elixir: handler_complete total_ms=#{elapsed} session=#{req.session_id} path=#{req.path}"
-      )
-    end)
+        Logger.info(
+          "p2p_metrics_elixir: handler_complete total_ms=#{elapsed} session=#{req.session_id} path=#{req.path}"
+        )
+      end)
 
-    {:noreply, state}
+    # Track the handler so a client cancellation can stop it
+    Process.monitor(pid)
+    {:noreply, %{state | hls_tasks: Map.put(state.hls_tasks, stream_id, pid)}}
+  end
+
+  def handle_info({:ok, "request_cancelled", request_id}, state) do
+    case Map.pop(state.graphql_tasks, request_id) do
+      {nil, _} ->
+        Logger.debug("P2P: Request #{request_id} cancelled by client")
+        {:noreply, state}
+
+      {pid, graphql_tasks} ->
+        Logger.debug("P2P: Request #{request_id} cancelled by client, stopping resolver")
+        Process.exit(pid, :kill)
+        {:noreply, %{state | graphql_tasks: graphql_tasks}}
+    end
+  end
+
+  # reason is "stopped" (player skipped ahead), "expired" or "connection_lost"
+  def handle_info({:ok, "stream_cancelled", stream_id, reason}, state) do
+    case Map.pop(state.hls_tasks, stream_id) do
+      {nil, _} ->
+        Logger.debug("P2P: HLS stream #{stream_id} cancelled (#{reason})")
+        {:noreply, state}
+
+      {pid, hls_tasks} ->
+        Logger.debug("P2P: HLS stream #{stream_id} cancelled (#{reason}), stopping handler")
+        Process.exit(pid, :kill)
+        {:noreply, %{state | hls_tasks: hls_tasks}}
+    end
+  end
+
+  def handle_info({:DOWN, _ref, :process, pid, _reason}, state) do
+    {:noreply,
+     %{
+       state
+       | hls_tasks: drop_task(state.hls_tasks, pid),
+         graphql_tasks: drop_task(state.graphql_tasks, pid)
+     }}
   end
 
   # Handle Rust/iroh log messages
@@ -385,13 +639,71 @@ defmodule Mydia.P2p.Server do
     {:noreply, state}
   end
 
+  defp drop_task(tasks, pid) do
+    tasks
+    |> Enum.reject(fn {_id, task_pid} -> task_pid == pid end)
+    |> Map.new()
+  end
+
+  # GraphQL handler
+
+  defp run_graphql(req, peer, session, peer_connection_type) do
+    # Parse variables from JSON
+    variables = parse_graphql_variables(req.variables)
+
+    # Build context from the auth token or the connection's session, marking source as p2p
+    auth = authenticate_p2p_request(req.auth_token, session, peer, "graphql")
+    context = build_graphql_context(auth, :p2p, peer_connection_type)
+
+    # Execute the GraphQL query with logging
+    result =
+      GraphQLLogging.run(
+        req.query,
+        MydiaWeb.Schema,
+        variables: variables,
+        operation_name: req.operation_name,
+        context: context
+      )
+
+    case result do
+      {:ok, %{data: data, errors: errors}} ->
+        %P2p.GraphQLResponse{
+          data: encode_json(data),
+          errors: encode_graphql_errors(errors)
+        }
+
+      {:ok, %{data: data}} ->
+        %P2p.GraphQLResponse{
+          data: encode_json(data),
+          errors: nil
+        }
+
+      # Query validation errors (no data key, only errors)
+      {:ok, %{errors: errors}} ->
+        Logger.warning("GraphQL validation error: #{inspect(errors)}")
+
+        %P2p.GraphQLResponse{
+          data: nil,
+          errors: encode_graphql_errors(errors)
+        }
+
+      {:error, reason} ->
+        Logger.warning("GraphQL execution failed: #{inspect(reason)}")
+
+        %P2p.GraphQLResponse{
+          data: nil,
+          errors: encode_json([%{message: inspect(reason)}])
+        }
+    end
+  end
+
   # HLS streaming handler
 
-  defp handle_hls_stream(resource, stream_id, req) do
+  defp handle_hls_stream(resource, stream_id, peer, session, req) do
     t0 = System.monotonic_time(:millisecond)
 
-    # Verify auth token
-    case verify_hls_auth(req.auth_token) do
+    # Verify auth token, or the session the connection authenticated as
+    case authenticate_p2p_request(req.auth_token, session, peer, "hls") do
       {:ok, user} ->
         auth_ms = System.monotonic_time(:millisecond) - t0
 
@@ -512,10 +824,28 @@ defmodule Mydia.P2p.Server do
     end
   end
 
-  defp verify_hls_auth(nil), do: {:error, :no_token}
+  # The user a P2P request acts as: its own token if it carries one, otherwise
+  # the session its connection authenticated as, if that grants `scope`
+  defp authenticate_p2p_request(auth_token, _session, peer, _scope) when is_binary(auth_token),
+    do: verify_p2p_token(auth_token, peer)
+
+  defp authenticate_p2p_request(nil, %P2p.Session{} = session, _peer, scope) do
+    if scope in session.scopes do
+      Mydia.Auth.Guardian.resource_from_claims(%{"sub" => session.principal})
+    else
+      {:error, :missing_scope}
+    end
+  end
+
+  defp authenticate_p2p_request(nil, nil, _peer, _scope), do: {:error, :no_token}
 
-  defp verify_hls_auth(auth_token) when is_binary(auth_token) do
-    Mydia.Auth.Guardian.verify_token(auth_token)
+  defp verify_p2p_token(auth_token, peer) when is_binary(auth_token) do
+    # Tokens issued at P2P pairing are only honored from the node they were issued to
+    if Application.get_env(:mydia, :p2p_bind_device_tokens, true) do
+      Mydia.Auth.Guardian.verify_token_for_node(auth_token, peer)
+    else
+      Mydia.Auth.Guardian.verify_token(auth_token)
+    end
   end
 
   defp lookup_hls_session(session_id) do
@@ -761,34 +1091,15 @@ defmodule Mydia.P2p.Server do
     end
   end
 
-  defp build_graphql_context(nil, source, peer_connection_type),
-    do: %{source: source, peer_connection_type: peer_connection_type}
-
-  defp build_graphql_context(auth_token, source, peer_connection_type)
-       when is_binary(auth_token) do
-    # Use Guardian to verify the token and get the user
-    case Mydia.Auth.Guardian.verify_token(auth_token) do
-      {:ok, user} ->
-        %{current_user: user, source: source, peer_connection_type: peer_connection_type}
-
-      {:error, _reason} ->
-        Logger.debug("P2P GraphQL: Invalid auth token")
-        %{source: source, peer_connection_type: peer_connection_type}
-    end
-  end
-
-  # Infer the peer connection type from connected peers map.
-  # Returns "relay", "direct", "mixed", or nil if no peers connected.
-  defp infer_peer_connection_type(connected_peers) when map_size(connected_peers) == 0, do: nil
+  defp build_graphql_context({:ok, user}, source, peer_connection_type),
+    do: %{current_user: user, source: source, peer_connection_type: peer_connection_type}
 
-  defp infer_peer_connection_type(connected_peers) do
-    types = Map.values(connected_peers) |> Enum.uniq()
+  defp build_graphql_context({:error, :no_token}, source, peer_connection_type),
+    do: %{source: source, peer_connection_type: peer_connection_type}
 
-    case types do
-      ["relay"] -> "relay"
-      ["direct"] -> "direct"
-      _ -> "mixed"
-    end
+  defp build_graphql_context({:error, reason}, source, peer_connection_type) do
+    Logger.debug("P2P GraphQL: Invalid auth token (#{inspect(reason)})")
+    %{source: source, peer_connection_type: peer_connection_type}
   end
 
   defp encode_json(nil), do: nil
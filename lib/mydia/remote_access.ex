@@ -247,6 +247,16 @@ defmodule Mydia.RemoteAccess do
     |> Repo.all()
   end
 
+  @doc """
+  Lists the P2P node ids of all active devices that paired over P2P.
+  """
+  def list_active_node_ids do
+    RemoteDevice
+    |> where([d], is_nil(d.revoked_at) and not is_nil(d.node_id))
+    |> select([d], d.node_id)
+    |> Repo.all()
+  end
+
   @doc """
   Gets a device by ID.
   """
@@ -375,6 +385,7 @@ defmodule Mydia.RemoteAccess do
       {:ok, updated_device} = result ->
         # Publish device status change event
         publish_device_event(updated_device, :revoked)
+        disconnect_p2p_device(updated_device)
         result
 
       error ->
@@ -390,6 +401,7 @@ defmodule Mydia.RemoteAccess do
       {:ok, deleted_device} = result ->
         # Publish device status change event
         publish_device_event(deleted_device, :deleted)
+        disconnect_p2p_device(deleted_device)
         result
 
       error ->
@@ -397,6 +409,19 @@ defmodule Mydia.RemoteAccess do
     end
   end
 
+  # Takes a removed device off the allowlist and closes its P2P connection,
+  # telling the device it was revoked. The P2P server may not be running,
+  # which leaves nothing to close.
+  defp disconnect_p2p_device(%RemoteDevice{node_id: nil}), do: :ok
+
+  defp disconnect_p2p_device(%RemoteDevice{node_id: node_id}) do
+    Mydia.P2p.Server.revoke_peer(node_id)
+    Mydia.P2p.Server.disconnect_peer(node_id, :revoked)
+    :ok
+  catch
+    :exit, _ -> :ok
+  end
+
   # Claim code management
 
   @doc """
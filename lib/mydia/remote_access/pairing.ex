@@ -18,6 +18,9 @@ defmodule Mydia.RemoteAccess.Pairing do
   3. Generates tokens
   4. Consumes the claim code
 
+  Pass the device's P2P `:node_id` in `device_attrs` when it pairs over P2P,
+  to bind its access token to that node.
+
   Returns `{:ok, device, media_token, access_token, device_token}` on success.
   """
   def complete_pairing(claim_code, device_attrs) do
@@ -59,20 +62,25 @@ defmodule Mydia.RemoteAccess.Pairing do
 
   @doc """
   Generates a JWT access token for the device's user.
+  Devices that paired over P2P get a token bound to their node id.
   """
   def generate_access_token(device) do
     # Preload user if not already loaded
     device = Mydia.Repo.preload(device, :user)
 
-    case Guardian.encode_and_sign(device.user, %{
-           "device_id" => device.id,
-           "typ" => "access"
-         }) do
+    claims =
+      %{"device_id" => device.id, "typ" => "access"}
+      |> maybe_put_node_id(device.node_id)
+
+    case Guardian.encode_and_sign(device.user, claims) do
       {:ok, token, _claims} -> token
       {:error, reason} -> raise "Failed to generate access token: #{inspect(reason)}"
     end
   end
 
+  defp maybe_put_node_id(claims, nil), do: claims
+  defp maybe_put_node_id(claims, node_id), do: Map.put(claims, "node_id", node_id)
+
   # Generates a unique device token
   defp generate_device_token do
     :crypto.strong_rand_bytes(32) |> Base.encode64(padding: false)
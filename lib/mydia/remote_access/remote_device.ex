@@ -13,6 +13,8 @@ defmodule Mydia.RemoteAccess.RemoteDevice do
     field :device_name, :string
     field :platform, :string
     field :device_static_public_key, :binary
+    # P2P node id the device paired from, if it paired over P2P
+    field :node_id, :string
     field :token_hash, :string
     field :token, :string, virtual: true
     field :last_seen_at, :utc_datetime
@@ -32,6 +34,7 @@ defmodule Mydia.RemoteAccess.RemoteDevice do
       :device_name,
       :platform,
       :device_static_public_key,
+      :node_id,
       :token,
       :user_id
     ])
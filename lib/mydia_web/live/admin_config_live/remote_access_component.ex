@@ -252,6 +252,29 @@ defmodule MydiaWeb.AdminConfigLive.RemoteAccessComponent do
                   iroh
                 </a>
               </div>
+              <%!-- Per-relay health, only worth showing when there is a choice --%>
+              <%= if length(@p2p_status.relay_health) > 1 do %>
+                <div class="space-y-0.5 pl-5">
+                  <%= for relay <- @p2p_status.relay_health do %>
+                    <div class="flex items-center gap-2 text-xs" title={relay.last_error}>
+                      <span class={[
+                        "w-1.5 h-1.5 rounded-full shrink-0",
+                        if(relay.reachable, do: "bg-success", else: "bg-error")
+                      ]}>
+                      </span>
+                      <code class="font-mono text-base-content/50 truncate flex-1">
+                        {relay.url}
+                      </code>
+                      <%= if relay.home do %>
+                        <span class="text-success">in use</span>
+                      <% end %>
+                      <span class="text-base-content/40 shrink-0">
+                        {display_relay_latency(relay)}
+                      </span>
+                    </div>
+                  <% end %>
+                </div>
+              <% end %>
             <% end %>
           </div>
         </div>
@@ -1331,6 +1354,10 @@ defmodule MydiaWeb.AdminConfigLive.RemoteAccessComponent do
   defp display_relay_url(nil), do: "(connecting...)"
   defp display_relay_url(url), do: url
 
+  defp display_relay_latency(%{reachable: true, latency_ms: latency_ms}), do: "#{latency_ms} ms"
+  defp display_relay_latency(%{last_error: nil}), do: "probing..."
+  defp display_relay_latency(_relay), do: "unreachable"
+
   defp connection_type_label("direct"), do: "Direct"
   defp connection_type_label("relay"), do: "Relay"
   defp connection_type_label("mixed"), do: "Mixed"
@@ -3,11 +3,13 @@
 //! Provides Erlang/Elixir interop for the p2p networking functionality.
 
 use mydia_p2p_core::{
-    Event, GraphQLResponse, HlsResponseHeader, Host, HostConfig, LogLevel, MydiaRequest,
-    MydiaResponse, PairingResponse,
+    ConnectionPolicy, DisconnectReason, Event, GraphQLResponse, HlsResponseHeader, Host,
+    HostConfig, LocalDiscovery, LogLevel, LookupService, MydiaRequest, MydiaResponse, P2pError,
+    PairingResponse, RelayPolicy, RequestLimits, Session, UnpairedLimits, WireError,
 };
 use rustler::{
-    Binary, Encoder, Env, LocalPid, NifStruct, NifTaggedEnum, OwnedEnv, ResourceArc, Term,
+    Atom, Binary, Encoder, Env, LocalPid, NifStruct, NifTaggedEnum, NifUnitEnum, OwnedEnv,
+    ResourceArc, Term,
 };
 use std::fs::File;
 use std::io::{Read, Seek, SeekFrom};
@@ -16,6 +18,21 @@ use std::thread;
 mod atoms {
     rustler::atoms! {
         ok,
+        not_connected,
+        connect,
+        invalid_address,
+        stream_not_found,
+        timeout,
+        protocol,
+        io,
+        remote,
+        cancelled,
+        shutdown,
+        bind,
+        invalid_config,
+        keypair,
+        keypair_corrupt,
+        refused,
     }
 }
 
@@ -27,29 +44,227 @@ struct HostResource {
 #[rustler::resource_impl]
 impl rustler::Resource for HostResource {}
 
+/// Relay policy as passed from Elixir:
+/// `:default`, `{:custom, urls}`, `{:custom_with_defaults, urls}` or `:disabled`.
+#[derive(NifTaggedEnum)]
+enum ElixirRelayPolicy {
+    Default,
+    Custom(Vec<String>),
+    CustomWithDefaults(Vec<String>),
+    Disabled,
+}
+
+impl From<ElixirRelayPolicy> for RelayPolicy {
+    fn from(policy: ElixirRelayPolicy) -> Self {
+        match policy {
+            ElixirRelayPolicy::Default => RelayPolicy::Default,
+            ElixirRelayPolicy::Custom(urls) => RelayPolicy::Custom(urls),
+            ElixirRelayPolicy::CustomWithDefaults(urls) => RelayPolicy::CustomWithDefaults(urls),
+            ElixirRelayPolicy::Disabled => RelayPolicy::Disabled,
+        }
+    }
+}
+
+/// Local discovery mode as passed from Elixir:
+/// `:disabled`, `:discover` or `:announce_and_discover`.
+#[derive(NifUnitEnum)]
+enum ElixirLocalDiscovery {
+    Disabled,
+    Discover,
+    AnnounceAndDiscover,
+}
+
+impl From<ElixirLocalDiscovery> for LocalDiscovery {
+    fn from(mode: ElixirLocalDiscovery) -> Self {
+        match mode {
+            ElixirLocalDiscovery::Disabled => LocalDiscovery::Disabled,
+            ElixirLocalDiscovery::Discover => LocalDiscovery::Discover,
+            ElixirLocalDiscovery::AnnounceAndDiscover => LocalDiscovery::AnnounceAndDiscover,
+        }
+    }
+}
+
+/// Why a peer is disconnected, as passed from Elixir:
+/// `:disconnected`, `:revoked`, `:banned` or `:session_revoked`.
+#[derive(NifUnitEnum)]
+enum ElixirDisconnectReason {
+    Disconnected,
+    Revoked,
+    Banned,
+    SessionRevoked,
+}
+
+impl From<ElixirDisconnectReason> for DisconnectReason {
+    fn from(reason: ElixirDisconnectReason) -> Self {
+        match reason {
+            ElixirDisconnectReason::Disconnected => DisconnectReason::Disconnected,
+            ElixirDisconnectReason::Revoked => DisconnectReason::Revoked,
+            ElixirDisconnectReason::Banned => DisconnectReason::Banned,
+            ElixirDisconnectReason::SessionRevoked => DisconnectReason::SessionRevoked,
+        }
+    }
+}
+
+/// Lookup service as passed from Elixir: `:n0`, `{:pkarr, url}` or `:disabled`.
+#[derive(NifTaggedEnum)]
+enum ElixirLookupService {
+    N0,
+    Pkarr(String),
+    Disabled,
+}
+
+impl From<ElixirLookupService> for LookupService {
+    fn from(service: ElixirLookupService) -> Self {
+        match service {
+            ElixirLookupService::N0 => LookupService::N0,
+            ElixirLookupService::Pkarr(url) => LookupService::Pkarr(url),
+            ElixirLookupService::Disabled => LookupService::Disabled,
+        }
+    }
+}
+
+/// Connection policy as passed from Elixir: `:open` or `{:allowlist, node_ids}`.
+#[derive(NifTaggedEnum)]
+enum ElixirConnectionPolicy {
+    Open,
+    Allowlist(Vec<String>),
+}
+
+impl ElixirConnectionPolicy {
+    fn into_policy(self, limits: UnpairedLimits) -> ConnectionPolicy {
+        match self {
+            ElixirConnectionPolicy::Open => ConnectionPolicy::Open,
+            ElixirConnectionPolicy::Allowlist(paired) => {
+                ConnectionPolicy::Allowlist { paired, limits }
+            }
+        }
+    }
+}
+
+/// Limits for unpaired peers as passed from Elixir, see `Mydia.P2p.UnpairedLimits`.
+#[derive(NifStruct)]
+#[module = "Mydia.P2p.UnpairedLimits"]
+struct ElixirUnpairedLimits {
+    pub max_open_streams: usize,
+    pub max_requests: u32,
+    pub window_ms: u64,
+    pub max_message_size: usize,
+}
+
+impl From<ElixirUnpairedLimits> for UnpairedLimits {
+    fn from(limits: ElixirUnpairedLimits) -> Self {
+        UnpairedLimits {
+            max_open_streams: limits.max_open_streams,
+            max_requests: limits.max_requests,
+            window: std::time::Duration::from_millis(limits.window_ms),
+            max_message_size: limits.max_message_size,
+        }
+    }
+}
+
+/// Per-peer request limits as passed from Elixir, see `Mydia.P2p.RequestLimits`.
+#[derive(NifStruct)]
+#[module = "Mydia.P2p.RequestLimits"]
+struct ElixirRequestLimits {
+    pub max_concurrent_streams: usize,
+    pub requests_per_second: u32,
+    pub burst: u32,
+    pub max_pending_responses: usize,
+}
+
+impl From<ElixirRequestLimits> for RequestLimits {
+    fn from(limits: ElixirRequestLimits) -> Self {
+        RequestLimits {
+            max_concurrent_streams: limits.max_concurrent_streams,
+            requests_per_second: limits.requests_per_second,
+            burst: limits.burst,
+            max_pending_responses: limits.max_pending_responses,
+        }
+    }
+}
+
 /// Start the p2p host with configuration.
-/// relay_url: Custom relay URL for NAT traversal (uses default relays if None).
+/// relay_policy: Relay servers to use for NAT traversal.
 /// bind_port: UDP port for direct connections (0 or None for random port).
 /// keypair_path: Path to store/load the node's keypair for persistent identity.
-/// Returns (resource, node_id_string).
-#[rustler::nif]
+/// bind_addrs: Explicit socket addresses to bind, at most one IPv4 and one IPv6.
+/// bind_interface: Only bind the addresses of this network interface.
+/// local_discovery: Whether to announce/discover Mydia nodes on the LAN over mDNS.
+/// lookup: Service to publish this node's addresses to and look peers up in.
+/// connection_policy: Whether only paired node ids get full access.
+/// request_limits: Caps on every peer's request rate and concurrency (nil for the defaults).
+/// unpaired_limits: Caps on what peers off the allowlist may send (nil for the defaults).
+/// Waits for the endpoint to bind and returns (resource, node_id_string),
+/// or the error that stopped startup.
+#[rustler::nif(schedule = "DirtyIo")]
+#[allow(clippy::too_many_arguments)] // mirrors Mydia.P2p.start_host/10
 fn start_host<'a>(
     env: Env<'a>,
-    relay_url: Option<String>,
+    relay_policy: ElixirRelayPolicy,
     bind_port: Option<u16>,
     keypair_path: Option<String>,
-) -> Term<'a> {
-    let config = HostConfig {
-        relay_url,
+    bind_addrs: Vec<String>,
+    bind_interface: Option<String>,
+    local_discovery: ElixirLocalDiscovery,
+    lookup: ElixirLookupService,
+    connection_policy: ElixirConnectionPolicy,
+    request_limits: Option<ElixirRequestLimits>,
+    unpaired_limits: Option<ElixirUnpairedLimits>,
+) -> Result<Term<'a>, rustler::Error> {
+    let mut config = HostConfig {
+        relay: relay_policy.into(),
         bind_port,
         keypair_path,
+        bind_interface,
+        local_discovery: local_discovery.into(),
+        lookup: lookup.into(),
+        connection_policy: connection_policy
+            .into_policy(unpaired_limits.map(Into::into).unwrap_or_default()),
+        request_limits: request_limits.map(Into::into).unwrap_or_default(),
+        ..Default::default()
     };
-    let (host, node_id_str) = Host::new(config);
+    for addr in bind_addrs {
+        set_bind_addr(&mut config, &addr).map_err(p2p_error)?;
+    }
+    let host = Host::start(config).map_err(p2p_error)?;
+    let node_id = host.node_id().to_string();
     let resource = ResourceArc::new(HostResource { host });
-    (resource, node_id_str).encode(env)
+    Ok((resource, node_id).encode(env))
+}
+
+/// Parse a socket address into the config's IPv4 or IPv6 bind address.
+fn set_bind_addr(config: &mut HostConfig, addr: &str) -> Result<(), P2pError> {
+    let parsed: std::net::SocketAddr = addr
+        .parse()
+        .map_err(|e| P2pError::InvalidConfig(format!("Invalid bind address {}: {}", addr, e)))?;
+    let duplicate = match parsed {
+        std::net::SocketAddr::V4(v4) => config.bind_addr_v4.replace(v4).is_some(),
+        std::net::SocketAddr::V6(v6) => config.bind_addr_v6.replace(v6).is_some(),
+    };
+    if duplicate {
+        return Err(P2pError::InvalidConfig(format!(
+            "Only one bind address per IP version is supported, got another: {}",
+            addr
+        )));
+    }
+    Ok(())
 }
 
-/// Dial a peer using their EndpointAddr JSON.
+/// Stop the p2p host, giving in-flight requests and HLS streams up to
+/// grace_ms to finish. The bind port is released once this returns.
+#[rustler::nif(schedule = "DirtyIo")]
+fn stop_host(resource: ResourceArc<HostResource>, grace_ms: u64) -> Result<String, rustler::Error> {
+    match resource
+        .host
+        .shutdown(std::time::Duration::from_millis(grace_ms))
+    {
+        Ok(()) => Ok("ok".to_string()),
+        Err(e) => Err(p2p_error(e)),
+    }
+}
+
+/// Dial a peer using their EndpointAddr JSON, or their bare node id when
+/// its address is known (address book, local discovery or lookup service).
 #[rustler::nif(schedule = "DirtyIo")]
 fn dial(
     resource: ResourceArc<HostResource>,
@@ -57,10 +272,95 @@ fn dial(
 ) -> Result<String, rustler::Error> {
     match resource.host.dial(endpoint_addr_json) {
         Ok(_) => Ok("ok".to_string()),
-        Err(e) => Err(rustler::Error::Term(Box::new(e))),
+        Err(e) => Err(p2p_error(e)),
     }
 }
 
+/// Add a peer's EndpointAddr JSON to the address book so it can be dialed by node id.
+#[rustler::nif(schedule = "DirtyIo")]
+fn add_peer_addr(
+    resource: ResourceArc<HostResource>,
+    endpoint_addr_json: String,
+) -> Result<String, rustler::Error> {
+    match resource.host.add_peer_addr(endpoint_addr_json) {
+        Ok(_) => Ok("ok".to_string()),
+        Err(e) => Err(p2p_error(e)),
+    }
+}
+
+/// Give a paired device's node id full access under the allowlist policy.
+#[rustler::nif(schedule = "DirtyIo")]
+fn allow_peer(
+    resource: ResourceArc<HostResource>,
+    node_id: String,
+) -> Result<String, rustler::Error> {
+    match resource.host.allow_peer(node_id) {
+        Ok(_) => Ok("ok".to_string()),
+        Err(e) => Err(p2p_error(e)),
+    }
+}
+
+/// Take full access away from a revoked device's node id.
+#[rustler::nif(schedule = "DirtyIo")]
+fn revoke_peer(
+    resource: ResourceArc<HostResource>,
+    node_id: String,
+) -> Result<String, rustler::Error> {
+    match resource.host.revoke_peer(node_id) {
+        Ok(_) => Ok("ok".to_string()),
+        Err(e) => Err(p2p_error(e)),
+    }
+}
+
+/// End the session of the connection from a node id by closing the connection.
+/// Returns whether there was an authenticated connection to close.
+#[rustler::nif(schedule = "DirtyIo")]
+fn revoke_session(
+    resource: ResourceArc<HostResource>,
+    node_id: String,
+) -> Result<bool, rustler::Error> {
+    resource.host.revoke_session(node_id).map_err(p2p_error)
+}
+
+/// Close the connection from a node id, telling the peer why.
+/// Returns whether there was a connection to close.
+#[rustler::nif(schedule = "DirtyIo")]
+fn disconnect_peer(
+    resource: ResourceArc<HostResource>,
+    node_id: String,
+    reason: ElixirDisconnectReason,
+) -> Result<bool, rustler::Error> {
+    resource
+        .host
+        .disconnect_peer(node_id, reason.into())
+        .map_err(p2p_error)
+}
+
+/// Refuse connections from a node id for `duration_ms`, or until unbanned
+/// if nil, and close its current connection. Bans are not persisted and
+/// end when the host stops.
+#[rustler::nif(schedule = "DirtyIo")]
+fn ban_peer(
+    resource: ResourceArc<HostResource>,
+    node_id: String,
+    duration_ms: Option<u64>,
+) -> Result<String, rustler::Error> {
+    let duration = duration_ms.map(std::time::Duration::from_millis);
+    match resource.host.ban_peer(node_id, duration) {
+        Ok(_) => Ok("ok".to_string()),
+        Err(e) => Err(p2p_error(e)),
+    }
+}
+
+/// Lift a ban. Returns whether the node id was banned.
+#[rustler::nif(schedule = "DirtyIo")]
+fn unban_peer(
+    resource: ResourceArc<HostResource>,
+    node_id: String,
+) -> Result<bool, rustler::Error> {
+    resource.host.unban_peer(node_id).map_err(p2p_error)
+}
+
 /// Get this node's EndpointAddr as JSON for sharing.
 #[rustler::nif(schedule = "DirtyIo")]
 fn get_node_addr(resource: ResourceArc<HostResource>) -> String {
@@ -75,18 +375,136 @@ fn get_network_stats(resource: ResourceArc<HostResource>) -> ElixirNetworkStats
         connected_peers: stats.connected_peers,
         relay_connected: stats.relay_connected,
         relay_url: stats.relay_url,
+        relay_policy: stats.relay_policy.as_str().to_string(),
+        bound_addrs: stats.bound_addrs,
         peer_connection_type: stats.peer_connection_type.as_str().to_string(),
+        rejected_requests: stats.rejected_requests,
     }
 }
 
+/// List every connected peer.
+#[rustler::nif(schedule = "DirtyIo")]
+fn list_peers(resource: ResourceArc<HostResource>) -> Vec<ElixirPeerInfo> {
+    resource
+        .host
+        .list_peers()
+        .into_iter()
+        .map(|peer| ElixirPeerInfo {
+            node_id: peer.node_id,
+            connection_type: peer.connection_type.as_str().to_string(),
+            remote_addrs: peer.remote_addrs,
+            rtt_ms: peer.rtt_ms,
+            bytes_sent: peer.bytes_sent,
+            bytes_received: peer.bytes_received,
+            connected_since: peer.connected_since,
+            open_streams: peer.open_streams,
+            rejected_requests: peer.rejected_requests,
+        })
+        .collect()
+}
+
+/// Get the latest probe results for every relay, lowest latency first.
+#[rustler::nif(schedule = "DirtyIo")]
+fn get_relay_health(resource: ResourceArc<HostResource>) -> Vec<ElixirRelayHealth> {
+    resource
+        .host
+        .relay_health()
+        .into_iter()
+        .map(|relay| ElixirRelayHealth {
+            url: relay.url,
+            home: relay.home,
+            reachable: relay.reachable,
+            latency_ms: relay.latency_ms,
+            last_error: relay.last_error,
+        })
+        .collect()
+}
+
 // Mirror structs for Elixir interop
+#[derive(NifStruct)]
+#[module = "Mydia.P2p.Error"]
+struct ElixirP2pError {
+    pub kind: Atom,
+    pub message: String,
+    pub code: Option<u16>,
+    pub retryable: bool,
+}
+
+impl From<P2pError> for ElixirP2pError {
+    fn from(e: P2pError) -> Self {
+        let kind = match e {
+            P2pError::NotConnected { .. } => atoms::not_connected(),
+            P2pError::Connect(_) => atoms::connect(),
+            P2pError::InvalidAddress(_) => atoms::invalid_address(),
+            P2pError::StreamNotFound(_) => atoms::stream_not_found(),
+            P2pError::Timeout => atoms::timeout(),
+            P2pError::Protocol(_) => atoms::protocol(),
+            P2pError::Io(_) => atoms::io(),
+            P2pError::Remote { .. } => atoms::remote(),
+            P2pError::Cancelled => atoms::cancelled(),
+            P2pError::Shutdown => atoms::shutdown(),
+            P2pError::Bind(_) => atoms::bind(),
+            P2pError::InvalidConfig(_) => atoms::invalid_config(),
+            P2pError::Keypair(_) => atoms::keypair(),
+            P2pError::KeypairCorrupt(_) => atoms::keypair_corrupt(),
+            P2pError::Refused(_) => atoms::refused(),
+        };
+        ElixirP2pError {
+            kind,
+            message: e.to_string(),
+            code: e.code(),
+            retryable: e.is_retryable(),
+        }
+    }
+}
+
+/// Wrap a core error as `{:error, %Mydia.P2p.Error{}}`.
+fn p2p_error(e: P2pError) -> rustler::Error {
+    rustler::Error::Term(Box::new(ElixirP2pError::from(e)))
+}
+
+#[derive(NifStruct)]
+#[module = "Mydia.P2p.WireError"]
+struct ElixirWireError {
+    pub code: u16,
+    pub message: String,
+    pub retryable: bool,
+}
+
 #[derive(NifStruct)]
 #[module = "Mydia.P2p.NetworkStats"]
 struct ElixirNetworkStats {
     pub connected_peers: usize,
     pub relay_connected: bool,
     pub relay_url: Option<String>,
+    pub relay_policy: String,
+    pub bound_addrs: Vec<String>,
     pub peer_connection_type: String,
+    pub rejected_requests: u64,
+}
+
+#[derive(NifStruct)]
+#[module = "Mydia.P2p.PeerInfo"]
+struct ElixirPeerInfo {
+    pub node_id: String,
+    pub connection_type: String,
+    pub remote_addrs: Vec<String>,
+    pub rtt_ms: Option<u64>,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    pub connected_since: u64,
+    pub open_streams: usize,
+    pub rejected_requests: u64,
+}
+
+#[derive(NifStruct)]
+#[module = "Mydia.P2p.RelayHealth"]
+struct ElixirRelayHealth {
+    pub url: String,
+    pub home: bool,
+    pub reachable: bool,
+    pub latency_ms: Option<u64>,
+    pub last_error: Option<String>,
 }
 
 #[derive(NifStruct)]
@@ -153,12 +571,30 @@ struct ElixirHlsResponseHeader {
     pub cache_control: Option<String>,
 }
 
+#[derive(NifStruct)]
+#[module = "Mydia.P2p.Session"]
+struct ElixirSession {
+    pub principal: String,
+    pub scopes: Vec<String>,
+}
+
+impl From<Session> for ElixirSession {
+    fn from(session: Session) -> Self {
+        ElixirSession {
+            principal: session.principal,
+            scopes: session.scopes,
+        }
+    }
+}
+
 #[derive(NifTaggedEnum)]
 enum ElixirResponse {
     Pairing(ElixirPairingResponse),
     MediaChunk(Vec<u8>),
     Graphql(ElixirGraphQLResponse),
+    Authenticated(ElixirSession),
     Error(String),
+    Failure(ElixirWireError),
 }
 
 /// Send a response to an incoming request.
@@ -182,12 +618,21 @@ fn send_response(
             data: r.data,
             errors: r.errors,
         }),
+        ElixirResponse::Authenticated(s) => MydiaResponse::Authenticated(Session {
+            principal: s.principal,
+            scopes: s.scopes,
+        }),
         ElixirResponse::Error(e) => MydiaResponse::Error(e),
+        ElixirResponse::Failure(e) => MydiaResponse::Failure(WireError {
+            code: e.code,
+            message: e.message,
+            retryable: e.retryable,
+        }),
     };
 
     match resource.host.send_response(request_id, core_response) {
         Ok(_) => Ok("ok".to_string()),
-        Err(e) => Err(rustler::Error::Term(Box::new(e))),
+        Err(e) => Err(p2p_error(e)),
     }
 }
 
@@ -213,13 +658,23 @@ fn respond_with_file_chunk(
                             buffer.truncate(n);
                             MydiaResponse::MediaChunk(buffer)
                         }
-                        Err(e) => MydiaResponse::Error(format!("Read error: {}", e)),
+                        Err(e) => MydiaResponse::Failure(WireError::new(
+                            WireError::INTERNAL,
+                            format!("Read error: {}", e),
+                        )),
                     }
                 } else {
-                    MydiaResponse::Error("Seek error".to_string())
+                    MydiaResponse::Failure(WireError::new(WireError::INTERNAL, "Seek error"))
                 }
             }
-            Err(e) => MydiaResponse::Error(format!("File open error: {}", e)),
+            Err(e) => {
+                let code = if e.kind() == std::io::ErrorKind::NotFound {
+                    WireError::NOT_FOUND
+                } else {
+                    WireError::INTERNAL
+                };
+                MydiaResponse::Failure(WireError::new(code, format!("File open error: {}", e)))
+            }
         };
 
         let _ = resource_clone.host.send_response(request_id, response);
@@ -247,7 +702,7 @@ fn send_hls_header(
 
     match resource.host.send_hls_header(stream_id, core_header) {
         Ok(_) => Ok("ok".to_string()),
-        Err(e) => Err(rustler::Error::Term(Box::new(e))),
+        Err(e) => Err(p2p_error(e)),
     }
 }
 
@@ -265,7 +720,7 @@ fn send_hls_chunk(
         .send_hls_chunk(stream_id, data.as_slice().to_vec())
     {
         Ok(_) => Ok("ok".to_string()),
-        Err(e) => Err(rustler::Error::Term(Box::new(e))),
+        Err(e) => Err(p2p_error(e)),
     }
 }
 
@@ -279,7 +734,7 @@ fn finish_hls_stream(
 ) -> Result<String, rustler::Error> {
     match resource.host.finish_hls_stream(stream_id) {
         Ok(_) => Ok("ok".to_string()),
-        Err(e) => Err(rustler::Error::Term(Box::new(e))),
+        Err(e) => Err(p2p_error(e)),
     }
 }
 
@@ -300,7 +755,7 @@ fn stream_file_range(
         .stream_file_range(stream_id, file_path, offset, length)
     {
         Ok(_) => Ok("ok".to_string()),
-        Err(e) => Err(rustler::Error::Term(Box::new(e))),
+        Err(e) => Err(p2p_error(e)),
     }
 }
 
@@ -325,11 +780,14 @@ fn start_listening(
                     Event::Connected {
                         peer_id,
                         connection_type,
+                        protocol,
                     } => (
                         atoms::ok(),
                         "peer_connected",
                         peer_id,
                         connection_type.as_str(),
+                        protocol.version.to_string(),
+                        protocol.capabilities,
                     )
                         .encode(env),
                     Event::Disconnected(peer_id) => {
@@ -345,68 +803,112 @@ fn start_listening(
                         connection_type.as_str(),
                     )
                         .encode(env),
+                    Event::SelectedPathChanged {
+                        peer_id,
+                        remote_addr,
+                    } => (atoms::ok(), "peer_path_changed", peer_id, remote_addr).encode(env),
+                    Event::Reconnecting { peer_id, attempt } => {
+                        (atoms::ok(), "peer_reconnecting", peer_id, attempt).encode(env)
+                    }
+                    Event::Reconnected { peer_id, attempts } => {
+                        (atoms::ok(), "peer_reconnected", peer_id, attempts).encode(env)
+                    }
+                    Event::PeerClosed { peer_id, reason } => {
+                        (atoms::ok(), "peer_closed", peer_id, reason.as_str()).encode(env)
+                    }
+                    Event::PeerDiscovered { node_id, addrs } => {
+                        (atoms::ok(), "peer_discovered", node_id, addrs).encode(env)
+                    }
                     Event::RequestReceived {
-                        peer: _,
+                        peer,
+                        session,
                         request,
                         request_id,
-                    } => match request {
-                        MydiaRequest::Pairing(req) => {
-                            let elixir_req = ElixirPairingRequest {
-                                claim_code: req.claim_code,
-                                device_name: req.device_name,
-                                device_type: req.device_type,
-                                device_os: req.device_os,
-                            };
-                            (
-                                atoms::ok(),
-                                "request_received",
-                                "pairing",
-                                request_id,
-                                elixir_req,
-                            )
-                                .encode(env)
-                        }
-                        MydiaRequest::ReadMedia(req) => {
-                            let elixir_req = ElixirReadMediaRequest {
-                                file_path: req.file_path,
-                                offset: req.offset,
-                                length: req.length,
-                            };
-                            (
+                    } => {
+                        let session = session.map(ElixirSession::from);
+                        match request {
+                            MydiaRequest::Pairing(req) => {
+                                let elixir_req = ElixirPairingRequest {
+                                    claim_code: req.claim_code,
+                                    device_name: req.device_name,
+                                    device_type: req.device_type,
+                                    device_os: req.device_os,
+                                };
+                                (
+                                    atoms::ok(),
+                                    "request_received",
+                                    "pairing",
+                                    request_id,
+                                    peer,
+                                    session,
+                                    elixir_req,
+                                )
+                                    .encode(env)
+                            }
+                            MydiaRequest::ReadMedia(req) => {
+                                let elixir_req = ElixirReadMediaRequest {
+                                    file_path: req.file_path,
+                                    offset: req.offset,
+                                    length: req.length,
+                                };
+                                (
+                                    atoms::ok(),
+                                    "request_received",
+                                    "read_media",
+                                    request_id,
+                                    peer,
+                                    session,
+                                    elixir_req,
+                                )
+                                    .encode(env)
+                            }
+                            MydiaRequest::GraphQL(req) => {
+                                let elixir_req = ElixirGraphQLRequest {
+                                    query: req.query,
+                                    variables: req.variables,
+                                    operation_name: req.operation_name,
+                                    auth_token: req.auth_token,
+                                };
+                                (
+                                    atoms::ok(),
+                                    "request_received",
+                                    "graphql",
+                                    request_id,
+                                    peer,
+                                    session,
+                                    elixir_req,
+                                )
+                                    .encode(env)
+                            }
+                            MydiaRequest::Ping => (
                                 atoms::ok(),
                                 "request_received",
-                                "read_media",
+                                "ping",
                                 request_id,
-                                elixir_req,
+                                peer,
+                                session,
                             )
-                                .encode(env)
-                        }
-                        MydiaRequest::GraphQL(req) => {
-                            let elixir_req = ElixirGraphQLRequest {
-                                query: req.query,
-                                variables: req.variables,
-                                operation_name: req.operation_name,
-                                auth_token: req.auth_token,
-                            };
-                            (
+                                .encode(env),
+                            MydiaRequest::Authenticate(req) => (
                                 atoms::ok(),
                                 "request_received",
-                                "graphql",
+                                "authenticate",
                                 request_id,
-                                elixir_req,
+                                peer,
+                                session,
+                                req.token,
                             )
-                                .encode(env)
+                                .encode(env),
+                            _ => (atoms::ok(), "unknown_request").encode(env),
                         }
-                        MydiaRequest::Ping => {
-                            (atoms::ok(), "request_received", "ping", request_id).encode(env)
-                        }
-                        _ => (atoms::ok(), "unknown_request").encode(env),
-                    },
+                    }
                     Event::HlsStreamRequest {
-                        peer: _,
+                        peer,
+                        session,
                         request,
                         stream_id,
                     } => {
+                        let session = session.map(ElixirSession::from);
                         let elixir_req = ElixirHlsRequest {
                             session_id: request.session_id,
                             path: request.path,
@@ -414,9 +916,24 @@ fn start_listening(
                             range_end: request.range_end,
                             auth_token: request.auth_token,
                         };
-                        (atoms::ok(), "hls_stream", stream_id, elixir_req).encode(env)
+                        (
+                            atoms::ok(),
+                            "hls_stream",
+                            stream_id,
+                            peer,
+                            session,
+                            elixir_req,
+                        )
+                            .encode(env)
+                    }
+                    Event::RequestCancelled { request_id } => {
+                        (atoms::ok(), "request_cancelled", request_id).encode(env)
+                    }
+                    Event::StreamCancelled { stream_id, reason } => {
+                        (atoms::ok(), "stream_cancelled", stream_id, reason.as_str()).encode(env)
                     }
                     Event::RelayConnected => (atoms::ok(), "relay_connected").encode(env),
+                    Event::RelayDisconnected => (atoms::ok(), "relay_disconnected").encode(env),
                     Event::Ready { node_addr } => (atoms::ok(), "ready", node_addr).encode(env),
                     Event::Log {
                         level,
@@ -0,0 +1,438 @@
+//! Which peers may do what: paired devices get full access, other peers only
+//! enough to ping the host and pair with it, and banned peers can't connect.
+//! Every peer's request rate and concurrency is capped on top of that.
+
+use crate::{MydiaRequest, P2pError, WireError};
+use iroh::EndpointId;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+/// Limits for peers that are not on the allowlist
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnpairedLimits {
+    /// Requests an unpaired peer may have in flight at once
+    pub max_open_streams: usize,
+    /// Requests an unpaired peer may make per `window`
+    pub max_requests: u32,
+    pub window: Duration,
+    /// Largest request an unpaired peer may send, checked before it is read.
+    /// Pairing requests are only a few hundred bytes.
+    pub max_message_size: usize,
+}
+
+impl Default for UnpairedLimits {
+    fn default() -> Self {
+        UnpairedLimits {
+            max_open_streams: 2,
+            max_requests: 10,
+            window: Duration::from_secs(60),
+            max_message_size: 4 * 1024,
+        }
+    }
+}
+
+/// Limits on the requests every peer may make, paired or not, so one peer
+/// can't flood the application
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestLimits {
+    /// Requests and HLS streams a peer may have in flight at once
+    pub max_concurrent_streams: usize,
+    /// Requests a peer may make per second on average, across reconnects
+    pub requests_per_second: u32,
+    /// Requests a peer may make at once after being idle
+    pub burst: u32,
+    /// Requests and HLS streams from all peers that may wait on the
+    /// application at once
+    pub max_pending_responses: usize,
+}
+
+impl Default for RequestLimits {
+    fn default() -> Self {
+        RequestLimits {
+            max_concurrent_streams: 32,
+            requests_per_second: 50,
+            burst: 100,
+            max_pending_responses: 512,
+        }
+    }
+}
+
+/// Enforces the request limits for incoming requests. Rates are tracked by
+/// node id, so reconnecting doesn't give a peer a fresh burst.
+pub(crate) struct RequestLimiter {
+    limits: RequestLimits,
+    buckets: Mutex<HashMap<String, TokenBucket>>,
+}
+
+impl RequestLimiter {
+    pub(crate) fn new(limits: RequestLimits) -> Self {
+        RequestLimiter {
+            limits,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub(crate) fn limits(&self) -> &RequestLimits {
+        &self.limits
+    }
+
+    /// Whether `peer_id` may make another request now, with `open_streams`
+    /// streams of its own already in flight. Takes a token from its bucket.
+    pub(crate) fn check(&self, peer_id: &str, open_streams: usize) -> Result<(), WireError> {
+        if open_streams >= self.limits.max_concurrent_streams {
+            return Err(WireError::new(
+                WireError::TOO_MANY_REQUESTS,
+                "Too many requests in flight",
+            ));
+        }
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+        if !buckets.contains_key(peer_id) {
+            // A full bucket is the same as a new one, so forget those to keep
+            // the map from growing unbounded
+            buckets.retain(|_, bucket| !bucket.is_full(now));
+        }
+        let bucket = buckets
+            .entry(peer_id.to_string())
+            .or_insert_with(|| TokenBucket::new(&self.limits));
+        if !bucket.take(now) {
+            return Err(WireError::new(
+                WireError::TOO_MANY_REQUESTS,
+                "Too many requests, try again later",
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Request rate limiter for one peer: holds up to `burst` tokens and
+/// refills at `requests_per_second`
+struct TokenBucket {
+    rate: f64,
+    capacity: f64,
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl TokenBucket {
+    fn new(limits: &RequestLimits) -> Self {
+        let capacity = f64::from(limits.burst.max(1));
+        TokenBucket {
+            rate: f64::from(limits.requests_per_second),
+            capacity,
+            tokens: capacity,
+            refilled_at: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now
+            .saturating_duration_since(self.refilled_at)
+            .as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+        self.refilled_at = now;
+    }
+
+    fn is_full(&mut self, now: Instant) -> bool {
+        self.refill(now);
+        self.tokens >= self.capacity
+    }
+
+    fn take(&mut self, now: Instant) -> bool {
+        self.refill(now);
+        if self.tokens < 1.0 {
+            return false;
+        }
+        self.tokens -= 1.0;
+        true
+    }
+}
+
+/// Which peers get full access to the host's requests
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum ConnectionPolicy {
+    /// Every peer gets full access
+    #[default]
+    Open,
+    /// Only the paired node ids get full access. Other peers may only send
+    /// `Ping` and `Pairing` requests, within `limits`.
+    Allowlist {
+        paired: Vec<String>,
+        limits: UnpairedLimits,
+    },
+}
+
+/// Enforces the connection policy for incoming requests. Shared between the
+/// event loop, which updates the allowlist, and the connection tasks.
+pub(crate) struct AccessControl {
+    /// None if every peer gets full access
+    limits: Option<UnpairedLimits>,
+    paired: RwLock<HashSet<String>>,
+    /// Start and request count of each unpaired peer's current window
+    windows: Mutex<HashMap<String, (Instant, u32)>>,
+    /// Banned node ids and when their ban ends, None if it doesn't
+    bans: Mutex<HashMap<String, Option<Instant>>>,
+}
+
+impl AccessControl {
+    pub(crate) fn new(policy: &ConnectionPolicy) -> Result<Self, P2pError> {
+        let (limits, paired) = match policy {
+            ConnectionPolicy::Open => (None, HashSet::new()),
+            ConnectionPolicy::Allowlist { paired, limits } => (
+                Some(limits.clone()),
+                paired
+                    .iter()
+                    .map(|node_id| parse_node_id(node_id))
+                    .collect::<Result<_, _>>()?,
+            ),
+        };
+        Ok(AccessControl {
+            limits,
+            paired: RwLock::new(paired),
+            windows: Mutex::new(HashMap::new()),
+            bans: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Refuse connections from a node id for `duration`, or until unbanned
+    /// or the host stops if None. Returns the normalized node id.
+    pub(crate) fn ban(
+        &self,
+        node_id: &str,
+        duration: Option<Duration>,
+    ) -> Result<String, P2pError> {
+        let node_id = parse_node_id(node_id)?;
+        let until = duration.map(|duration| Instant::now() + duration);
+        self.bans.lock().unwrap().insert(node_id.clone(), until);
+        Ok(node_id)
+    }
+
+    /// Lift a ban. Returns whether the node id was banned.
+    pub(crate) fn unban(&self, node_id: &str) -> Result<bool, P2pError> {
+        let node_id = parse_node_id(node_id)?;
+        Ok(self.bans.lock().unwrap().remove(&node_id).is_some())
+    }
+
+    /// Whether connections from `peer_id` are refused
+    pub(crate) fn is_banned(&self, peer_id: &str) -> bool {
+        let now = Instant::now();
+        let mut bans = self.bans.lock().unwrap();
+        bans.retain(|_, until| until.is_none_or(|until| until > now));
+        bans.contains_key(peer_id)
+    }
+
+    /// Give a node id full access. Returns whether it was newly added.
+    pub(crate) fn allow(&self, node_id: &str) -> Result<bool, P2pError> {
+        let node_id = parse_node_id(node_id)?;
+        self.windows.lock().unwrap().remove(&node_id);
+        Ok(self.paired.write().unwrap().insert(node_id))
+    }
+
+    /// Take full access away from a node id. Returns whether it had it.
+    pub(crate) fn revoke(&self, node_id: &str) -> Result<bool, P2pError> {
+        let node_id = parse_node_id(node_id)?;
+        Ok(self.paired.write().unwrap().remove(&node_id))
+    }
+
+    /// Largest request `peer_id` may send, if it is held to the unpaired limits
+    pub(crate) fn max_message_size(&self, peer_id: &str) -> Option<usize> {
+        let limits = self.limits.as_ref()?;
+        if self.paired.read().unwrap().contains(peer_id) {
+            return None;
+        }
+        Some(limits.max_message_size)
+    }
+
+    /// Whether `peer_id` may make `request` now, with `open_streams`
+    /// requests of its own already in flight
+    pub(crate) fn check(
+        &self,
+        peer_id: &str,
+        request: &MydiaRequest,
+        open_streams: usize,
+    ) -> Result<(), WireError> {
+        let Some(limits) = &self.limits else {
+            return Ok(());
+        };
+        if self.paired.read().unwrap().contains(peer_id) {
+            return Ok(());
+        }
+        if !matches!(request, MydiaRequest::Ping | MydiaRequest::Pairing(_)) {
+            return Err(WireError::new(
+                WireError::FORBIDDEN,
+                "This device is not paired",
+            ));
+        }
+        if open_streams >= limits.max_open_streams {
+            return Err(WireError::new(
+                WireError::TOO_MANY_REQUESTS,
+                "Too many requests in flight",
+            ));
+        }
+
+        let now = Instant::now();
+        let mut windows = self.windows.lock().unwrap();
+        // Forget peers whose window is over, so the map can't grow unbounded
+        windows.retain(|_, (start, _)| now.duration_since(*start) < limits.window);
+        let (_, count) = windows.entry(peer_id.to_string()).or_insert((now, 0));
+        *count += 1;
+        if *count > limits.max_requests {
+            return Err(WireError::new(
+                WireError::TOO_MANY_REQUESTS,
+                "Too many requests, try again later",
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Normalize a node id so lookups match the ids connections report
+fn parse_node_id(node_id: &str) -> Result<String, P2pError> {
+    node_id
+        .trim()
+        .parse::<EndpointId>()
+        .map(|id| id.to_string())
+        .map_err(|e| P2pError::InvalidAddress(format!("Invalid node id {}: {}", node_id, e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GraphQLRequest, PairingRequest};
+    use iroh::SecretKey;
+
+    fn node_id() -> String {
+        SecretKey::generate(&mut rand::rng()).public().to_string()
+    }
+
+    fn pairing() -> MydiaRequest {
+        MydiaRequest::Pairing(PairingRequest {
+            claim_code: "ABC123".to_string(),
+            device_name: "Phone".to_string(),
+            device_type: "mobile".to_string(),
+            device_os: None,
+        })
+    }
+
+    fn graphql() -> MydiaRequest {
+        MydiaRequest::GraphQL(GraphQLRequest {
+            query: "{ me { id } }".to_string(),
+            variables: None,
+            operation_name: None,
+            auth_token: None,
+        })
+    }
+
+    #[test]
+    fn test_open_policy_allows_everything() {
+        let access = AccessControl::new(&ConnectionPolicy::Open).unwrap();
+        assert!(access.check(&node_id(), &graphql(), 100).is_ok());
+        assert_eq!(access.max_message_size(&node_id()), None);
+    }
+
+    #[test]
+    fn test_unpaired_peers_can_only_pair() {
+        let paired = node_id();
+        let stranger = node_id();
+        let access = AccessControl::new(&ConnectionPolicy::Allowlist {
+            paired: vec![paired.clone()],
+            limits: UnpairedLimits::default(),
+        })
+        .unwrap();
+
+        assert!(access.check(&paired, &graphql(), 0).is_ok());
+        assert!(access.check(&stranger, &pairing(), 0).is_ok());
+        assert!(access.check(&stranger, &MydiaRequest::Ping, 0).is_ok());
+        let err = access.check(&stranger, &graphql(), 0).unwrap_err();
+        assert_eq!(err.code, WireError::FORBIDDEN);
+
+        // Pairing grants full access, revoking takes it away again
+        assert!(access.allow(&stranger).unwrap());
+        assert!(access.check(&stranger, &graphql(), 0).is_ok());
+        assert_eq!(access.max_message_size(&stranger), None);
+        assert!(access.revoke(&stranger).unwrap());
+        assert_eq!(access.max_message_size(&stranger), Some(4 * 1024));
+        assert!(access.check(&stranger, &graphql(), 0).is_err());
+
+        assert!(matches!(
+            access.allow("not-a-node-id"),
+            Err(P2pError::InvalidAddress(_))
+        ));
+    }
+
+    #[test]
+    fn test_unpaired_peers_are_rate_limited() {
+        let stranger = node_id();
+        let access = AccessControl::new(&ConnectionPolicy::Allowlist {
+            paired: Vec::new(),
+            limits: UnpairedLimits {
+                max_open_streams: 1,
+                max_requests: 2,
+                window: Duration::from_secs(60),
+                ..Default::default()
+            },
+        })
+        .unwrap();
+
+        let err = access.check(&stranger, &pairing(), 1).unwrap_err();
+        assert_eq!(err.code, WireError::TOO_MANY_REQUESTS);
+
+        assert!(access.check(&stranger, &pairing(), 0).is_ok());
+        assert!(access.check(&stranger, &pairing(), 0).is_ok());
+        let err = access.check(&stranger, &pairing(), 0).unwrap_err();
+        assert_eq!(err.code, WireError::TOO_MANY_REQUESTS);
+        assert!(err.retryable);
+
+        // Other peers have their own window
+        assert!(access.check(&node_id(), &pairing(), 0).is_ok());
+    }
+
+    #[test]
+    fn test_request_limits_cap_streams_and_rate() {
+        let limiter = RequestLimiter::new(RequestLimits {
+            max_concurrent_streams: 4,
+            requests_per_second: 20,
+            burst: 2,
+            max_pending_responses: 512,
+        });
+        let peer = node_id();
+
+        let err = limiter.check(&peer, 4).unwrap_err();
+        assert_eq!(err.code, WireError::TOO_MANY_REQUESTS);
+
+        // The burst is used up, then tokens come back at the configured rate
+        assert!(limiter.check(&peer, 0).is_ok());
+        assert!(limiter.check(&peer, 0).is_ok());
+        let err = limiter.check(&peer, 0).unwrap_err();
+        assert_eq!(err.code, WireError::TOO_MANY_REQUESTS);
+        assert!(err.retryable);
+
+        // Other peers have their own bucket
+        assert!(limiter.check(&node_id(), 0).is_ok());
+
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(limiter.check(&peer, 0).is_ok());
+    }
+
+    #[test]
+    fn test_bans_expire_or_last_until_lifted() {
+        let access = AccessControl::new(&ConnectionPolicy::Open).unwrap();
+        let (indefinite, temporary) = (node_id(), node_id());
+
+        access.ban(&indefinite, None).unwrap();
+        access
+            .ban(&temporary, Some(Duration::from_millis(50)))
+            .unwrap();
+        assert!(access.is_banned(&indefinite));
+        assert!(access.is_banned(&temporary));
+
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(access.is_banned(&indefinite));
+        assert!(!access.is_banned(&temporary));
+
+        assert!(access.unban(&indefinite).unwrap());
+        assert!(!access.is_banned(&indefinite));
+        assert!(!access.unban(&indefinite).unwrap());
+    }
+}
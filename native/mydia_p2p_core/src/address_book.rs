@@ -0,0 +1,326 @@
+//! Last known addresses of peers, optionally persisted so reconnects after a
+//! restart can go straight to the path that worked last time.
+
+use crate::{endpoint_addr_from_json, HostConfig, P2pError, PeerConnectionType};
+use iroh::address_lookup::MemoryLookup;
+use iroh::{EndpointAddr, EndpointId, RelayUrl, TransportAddr};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::task::JoinHandle;
+use tokio::time::{Duration, Instant};
+
+/// Provenance iroh reports for addresses from the address book
+const PROVENANCE: &str = "address_book";
+
+/// How long changes are collected before the book is written out
+const SAVE_DELAY: Duration = Duration::from_secs(2);
+
+/// What the address book remembers about a peer
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct KnownPeer {
+    /// Direct socket addresses the peer was last reached or advertised at
+    pub direct_addrs: Vec<SocketAddr>,
+    pub relay_url: Option<String>,
+    /// Connection type of the last successful connection
+    pub connection_type: Option<PeerConnectionType>,
+    /// Unix timestamp (seconds) of the last successful connection
+    pub last_connected: Option<u64>,
+}
+
+impl KnownPeer {
+    fn endpoint_addr(&self, id: EndpointId) -> EndpointAddr {
+        let relay = self
+            .relay_url
+            .as_ref()
+            .and_then(|url| url.parse::<RelayUrl>().ok());
+        EndpointAddr::from_parts(
+            id,
+            self.direct_addrs
+                .iter()
+                .copied()
+                .map(TransportAddr::Ip)
+                .chain(relay.map(TransportAddr::Relay)),
+        )
+    }
+
+    /// Take the peer's direct addresses and relay from `addrs`, keeping the
+    /// old ones for whichever kind `addrs` has none of
+    fn update_addrs<'a>(&mut self, addrs: impl IntoIterator<Item = &'a TransportAddr>) {
+        let mut direct_addrs = Vec::new();
+        for addr in addrs {
+            match addr {
+                TransportAddr::Ip(addr) => direct_addrs.push(*addr),
+                TransportAddr::Relay(url) => self.relay_url = Some(url.to_string()),
+                _ => {}
+            }
+        }
+        if !direct_addrs.is_empty() {
+            self.direct_addrs = direct_addrs;
+        }
+    }
+}
+
+/// Known peers keyed by node id. The endpoint looks addresses up in it when
+/// dialing by node id, and every dial is merged with what it holds.
+pub(crate) struct AddressBook {
+    /// Where the book is persisted. In memory only if None.
+    path: Option<PathBuf>,
+    peers: BTreeMap<String, KnownPeer>,
+    lookup: MemoryLookup,
+    /// When the oldest change not yet written out was made
+    dirty_since: Option<Instant>,
+    /// Number of snapshots handed to a writer so far
+    snapshots: u64,
+    /// Number of the last snapshot written. Writers hold the lock while
+    /// writing, so a slow write can't replace a newer snapshot.
+    written: Arc<Mutex<u64>>,
+    /// Most recently started write
+    write_task: Option<JoinHandle<()>>,
+}
+
+impl AddressBook {
+    /// Load the book from `address_book_path` and add `known_peers` to it.
+    /// A missing or unreadable file only loses the remembered addresses, so
+    /// it starts an empty book rather than failing. A malformed file is
+    /// moved aside so it isn't overwritten.
+    pub(crate) fn open(config: &HostConfig) -> Result<Self, P2pError> {
+        let path = config.address_book_path.as_deref().map(PathBuf::from);
+        let peers = path.as_deref().map(load).unwrap_or_default();
+        let mut book = AddressBook {
+            path,
+            peers,
+            lookup: MemoryLookup::with_provenance(PROVENANCE),
+            dirty_since: None,
+            snapshots: 0,
+            written: Arc::new(Mutex::new(0)),
+            write_task: None,
+        };
+        for (node_id, peer) in &book.peers {
+            book.publish(node_id, peer);
+        }
+        for json in &config.known_peers {
+            book.add(&endpoint_addr_from_json(json)?);
+        }
+        Ok(book)
+    }
+
+    /// Address lookup for the endpoint, kept in sync with the book
+    pub(crate) fn lookup(&self) -> &MemoryLookup {
+        &self.lookup
+    }
+
+    /// Remember the addresses a peer was dialed with or advertised
+    pub(crate) fn add(&mut self, addr: &EndpointAddr) {
+        if addr.is_empty() {
+            return;
+        }
+        let node_id = addr.id.to_string();
+        let peer = self.peers.entry(node_id.clone()).or_default();
+        peer.update_addrs(&addr.addrs);
+        let peer = peer.clone();
+        self.publish(&node_id, &peer);
+        self.mark_dirty();
+    }
+
+    /// Add the remembered addresses of the peer to `addr`
+    pub(crate) fn merge_into(&self, addr: EndpointAddr) -> EndpointAddr {
+        match self.peers.get(&addr.id.to_string()) {
+            Some(peer) => {
+                let known = peer.endpoint_addr(addr.id).addrs;
+                addr.with_addrs(known)
+            }
+            None => addr,
+        }
+    }
+
+    /// Record a successful connection and the paths it is using
+    pub(crate) fn record_connection(
+        &mut self,
+        node_id: &str,
+        connection_type: PeerConnectionType,
+        remote_addrs: &[TransportAddr],
+    ) {
+        let peer = self.peers.entry(node_id.to_string()).or_default();
+        peer.update_addrs(remote_addrs);
+        peer.connection_type = Some(connection_type);
+        peer.last_connected = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .ok();
+        let peer = peer.clone();
+        self.publish(node_id, &peer);
+        self.mark_dirty();
+    }
+
+    fn publish(&self, node_id: &str, peer: &KnownPeer) {
+        if let Ok(id) = node_id.parse::<EndpointId>() {
+            self.lookup.set_endpoint_info(peer.endpoint_addr(id));
+        }
+    }
+
+    fn mark_dirty(&mut self) {
+        if self.path.is_some() {
+            self.dirty_since.get_or_insert_with(Instant::now);
+        }
+    }
+
+    /// When unsaved changes are due to be written out, if there are any
+    pub(crate) fn save_deadline(&self) -> Option<Instant> {
+        self.dirty_since.map(|since| since + SAVE_DELAY)
+    }
+
+    /// Write the book to its file in the background
+    pub(crate) fn save(&mut self) {
+        self.dirty_since = None;
+        let Some(path) = self.path.clone() else {
+            return;
+        };
+        self.snapshots += 1;
+        let snapshot = self.snapshots;
+        let peers = self.peers.clone();
+        let written = self.written.clone();
+        self.write_task = Some(tokio::task::spawn_blocking(move || {
+            let mut written = written.lock().unwrap();
+            if *written > snapshot {
+                return;
+            }
+            if let Err(e) = write(&path, &peers) {
+                tracing::warn!("Failed to save address book to {}: {}", path.display(), e);
+            }
+            *written = snapshot;
+        }));
+    }
+
+    /// Write out any unsaved changes and wait for the write to finish
+    pub(crate) async fn flush(&mut self) {
+        if self.dirty_since.is_some() {
+            self.save();
+        }
+        if let Some(task) = self.write_task.take() {
+            let _ = task.await;
+        }
+    }
+}
+
+/// Write `peers` to `path` via a synced temporary file, so a crash mid-write
+/// can't leave a truncated book behind
+fn write(path: &Path, peers: &BTreeMap<String, KnownPeer>) -> std::io::Result<()> {
+    let json = serde_json::to_vec_pretty(peers).map_err(std::io::Error::other)?;
+    let tmp = path.with_extension("tmp");
+    let mut file = std::fs::File::create(&tmp)?;
+    file.write_all(&json)?;
+    file.sync_all()?;
+    std::fs::rename(&tmp, path)
+}
+
+fn load(path: &Path) -> BTreeMap<String, KnownPeer> {
+    let bytes = match std::fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return BTreeMap::new(),
+        Err(e) => {
+            tracing::warn!("Failed to read address book {}: {}", path.display(), e);
+            return BTreeMap::new();
+        }
+    };
+    match serde_json::from_slice(&bytes) {
+        Ok(peers) => {
+            tracing::info!("Loaded address book from {}", path.display());
+            peers
+        }
+        Err(e) => {
+            let mut aside = path.as_os_str().to_owned();
+            aside.push(".corrupt");
+            match std::fs::rename(path, &aside) {
+                Ok(()) => tracing::warn!(
+                    "Ignoring malformed address book {}, moved it to {}: {}",
+                    path.display(),
+                    Path::new(&aside).display(),
+                    e
+                ),
+                Err(rename_err) => tracing::warn!(
+                    "Ignoring malformed address book {} and failed to move it aside: {} ({})",
+                    path.display(),
+                    e,
+                    rename_err
+                ),
+            }
+            BTreeMap::new()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use iroh::SecretKey;
+
+    fn node_id() -> EndpointId {
+        SecretKey::generate(&mut rand::rng()).public()
+    }
+
+    #[tokio::test]
+    async fn test_record_connection_persists_across_restarts() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("address_book.json");
+        let config = HostConfig {
+            address_book_path: Some(path.to_string_lossy().into_owned()),
+            ..Default::default()
+        };
+        let id = node_id();
+        let direct: SocketAddr = "192.168.1.10:4001".parse().unwrap();
+        let relay: RelayUrl = "https://relay.example.com".parse().unwrap();
+
+        let mut book = AddressBook::open(&config).unwrap();
+        book.record_connection(
+            &id.to_string(),
+            PeerConnectionType::Mixed,
+            &[
+                TransportAddr::Ip(direct),
+                TransportAddr::Relay(relay.clone()),
+            ],
+        );
+        // Falling back to the relay keeps the last direct address
+        book.record_connection(
+            &id.to_string(),
+            PeerConnectionType::Relay,
+            &[TransportAddr::Relay(relay.clone())],
+        );
+        // Changes are only written out once they settle
+        assert!(book.save_deadline().is_some());
+        assert!(!path.exists());
+        book.flush().await;
+        assert!(book.save_deadline().is_none());
+        drop(book);
+
+        let book = AddressBook::open(&config).unwrap();
+        let peer = &book.peers[&id.to_string()];
+        assert_eq!(peer.direct_addrs, vec![direct]);
+        assert_eq!(peer.relay_url, Some(relay.to_string()));
+        assert_eq!(peer.connection_type, Some(PeerConnectionType::Relay));
+        assert!(peer.last_connected.is_some());
+
+        let merged = book.merge_into(EndpointAddr::new(id));
+        assert!(merged.ip_addrs().any(|addr| *addr == direct));
+        assert!(merged.relay_urls().any(|url| *url == relay));
+    }
+
+    #[test]
+    fn test_malformed_file_is_moved_aside() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("address_book.json");
+        std::fs::write(&path, b"not json").unwrap();
+        let config = HostConfig {
+            address_book_path: Some(path.to_string_lossy().into_owned()),
+            ..Default::default()
+        };
+
+        let book = AddressBook::open(&config).unwrap();
+        assert!(book.peers.is_empty());
+        assert!(!path.exists());
+        let aside = dir.path().join("address_book.json.corrupt");
+        assert_eq!(std::fs::read(aside).unwrap(), b"not json");
+    }
+}
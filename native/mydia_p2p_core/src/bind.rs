@@ -0,0 +1,143 @@
+//! Choosing which local sockets the endpoint binds.
+
+use crate::{HostConfig, P2pError};
+use iroh::endpoint::{BindOpts, Builder};
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
+
+/// Configure the endpoint's IP sockets from `bind_port`, `bind_addr_v4`,
+/// `bind_addr_v6` and `bind_interface`.
+///
+/// Without any of them iroh picks random ports on all interfaces. Explicit
+/// addresses replace the default socket of their address family only, while
+/// an interface replaces both.
+pub(crate) fn configure(mut builder: Builder, config: &HostConfig) -> Result<Builder, P2pError> {
+    let port = config.bind_port.unwrap_or(0);
+
+    if let Some(name) = &config.bind_interface {
+        if config.bind_addr_v4.is_some() || config.bind_addr_v6.is_some() {
+            return Err(P2pError::InvalidConfig(
+                "bind_interface cannot be combined with explicit bind addresses".to_string(),
+            ));
+        }
+        let interface = netdev::get_interfaces()
+            .into_iter()
+            .find(|interface| &interface.name == name)
+            .ok_or_else(|| {
+                P2pError::InvalidConfig(format!("No network interface named {}", name))
+            })?;
+        let addrs = interface_addrs(&interface, port);
+        if addrs.is_empty() {
+            return Err(P2pError::InvalidConfig(format!(
+                "Network interface {} has no usable IP address",
+                name
+            )));
+        }
+
+        builder = builder.clear_ip_transports();
+        for (addr, prefix_len) in addrs {
+            builder = bind_with_prefix(builder, addr, prefix_len)?;
+        }
+        return Ok(builder);
+    }
+
+    let v4 = config
+        .bind_addr_v4
+        .or_else(|| (port > 0).then(|| SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, port)));
+    if let Some(addr) = v4 {
+        builder = builder.bind_addr(addr).map_err(invalid_bind_addr)?;
+    }
+    if let Some(addr) = config.bind_addr_v6 {
+        builder = builder.bind_addr(addr).map_err(invalid_bind_addr)?;
+    }
+    Ok(builder)
+}
+
+/// The first IPv4 address and the first routable IPv6 address of an
+/// interface, with their prefix lengths. Link-local IPv6 addresses are
+/// skipped as they can't reach peers outside the link.
+fn interface_addrs(interface: &netdev::Interface, port: u16) -> Vec<(SocketAddr, u8)> {
+    let v4 = interface
+        .ipv4
+        .first()
+        .map(|net| (SocketAddrV4::new(net.addr(), port).into(), net.prefix_len()));
+    let v6 = interface
+        .ipv6
+        .iter()
+        .find(|net| !net.addr().is_unicast_link_local())
+        .map(|net| {
+            (
+                SocketAddrV6::new(net.addr(), port, 0, 0).into(),
+                net.prefix_len(),
+            )
+        });
+    v4.into_iter().chain(v6).collect()
+}
+
+/// Bind an interface address. It is also the default route so traffic to
+/// peers outside its subnet still leaves through it.
+fn bind_with_prefix(
+    builder: Builder,
+    addr: SocketAddr,
+    prefix_len: u8,
+) -> Result<Builder, P2pError> {
+    let opts = BindOpts::default()
+        .set_prefix_len(prefix_len)
+        .set_is_default_route(true);
+    builder
+        .bind_addr_with_opts(addr, opts)
+        .map_err(invalid_bind_addr)
+}
+
+fn invalid_bind_addr(e: impl std::fmt::Display) -> P2pError {
+    P2pError::InvalidConfig(format!("Invalid bind address: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use iroh::Endpoint;
+    use netdev::ipnet::{Ipv4Net, Ipv6Net};
+
+    #[test]
+    fn test_interface_addrs_skip_link_local_v6() {
+        let mut interface = netdev::Interface::dummy();
+        interface.ipv4 = vec![Ipv4Net::new("192.168.1.10".parse().unwrap(), 24).unwrap()];
+        interface.ipv6 = vec![
+            Ipv6Net::new("fe80::1".parse().unwrap(), 64).unwrap(),
+            Ipv6Net::new("2001:db8::10".parse().unwrap(), 64).unwrap(),
+        ];
+
+        assert_eq!(
+            interface_addrs(&interface, 4001),
+            vec![
+                ("192.168.1.10:4001".parse().unwrap(), 24),
+                ("[2001:db8::10]:4001".parse().unwrap(), 64),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_interface_and_explicit_addrs_conflict() {
+        let config = HostConfig {
+            bind_interface: Some("eth0".to_string()),
+            bind_addr_v4: Some("0.0.0.0:4001".parse().unwrap()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            configure(Endpoint::builder(), &config),
+            Err(P2pError::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn test_unknown_interface_is_rejected() {
+        let config = HostConfig {
+            bind_interface: Some("no-such-interface0".to_string()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            configure(Endpoint::builder(), &config),
+            Err(P2pError::InvalidConfig(_))
+        ));
+    }
+}
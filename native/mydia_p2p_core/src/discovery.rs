@@ -0,0 +1,118 @@
+//! Finding the addresses of other nodes, so they can be dialed by bare node id.
+//!
+//! Addresses come from an in-memory address book, mDNS on the local network
+//! and a lookup service this node also publishes its own addresses to.
+
+use crate::{transport_addr_to_string, Event, HostConfig, P2pError};
+use futures::StreamExt;
+use iroh::address_lookup::{
+    DiscoveryEvent, MdnsAddressLookup, MemoryLookup, PkarrPublisher, PkarrResolver,
+};
+use iroh::endpoint::Builder;
+use iroh::Endpoint;
+use tokio::sync::mpsc;
+
+/// Service a Host publishes its addresses to and looks peers up in.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum LookupService {
+    /// iroh's public DNS and pkarr servers
+    #[default]
+    N0,
+    /// A self-hosted pkarr relay at this URL, used over HTTP both ways
+    Pkarr(String),
+    /// Neither publish nor look up; only the address book and mDNS are used
+    Disabled,
+}
+
+impl LookupService {
+    /// Return a string representation of the service, without its URL
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LookupService::N0 => "n0",
+            LookupService::Pkarr(_) => "pkarr",
+            LookupService::Disabled => "disabled",
+        }
+    }
+}
+
+/// Configure the endpoint's address lookup from `lookup`. The address book
+/// is always consulted.
+pub(crate) fn configure(
+    builder: Builder,
+    config: &HostConfig,
+    address_book: &MemoryLookup,
+) -> Result<Builder, P2pError> {
+    let builder = match &config.lookup {
+        // Already set up by Endpoint::builder()
+        LookupService::N0 => builder,
+        LookupService::Pkarr(url) => {
+            let url: url::Url = url.parse().map_err(|e| {
+                P2pError::InvalidConfig(format!("Invalid lookup service URL {}: {}", url, e))
+            })?;
+            builder
+                .clear_address_lookup()
+                .address_lookup(PkarrPublisher::builder(url.clone()))
+                .address_lookup(PkarrResolver::builder(url))
+        }
+        LookupService::Disabled => builder.clear_address_lookup(),
+    };
+    Ok(builder.address_lookup(address_book.clone()))
+}
+
+/// mDNS service name Mydia nodes use, so plain iroh nodes on the LAN are ignored
+pub const MDNS_SERVICE_NAME: &str = "mydia";
+
+/// Whether a Host takes part in local network discovery.
+///
+/// Discovered nodes are reported as `Event::PeerDiscovered` and can be dialed
+/// by their bare node id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LocalDiscovery {
+    /// No mDNS traffic at all
+    #[default]
+    Disabled,
+    /// Listen for other nodes without announcing this one (e.g. players)
+    Discover,
+    /// Announce this node and listen for others (e.g. servers)
+    AnnounceAndDiscover,
+}
+
+/// Register mDNS lookup on the endpoint and forward discovered nodes as
+/// events until the endpoint closes. Returns None when discovery is disabled.
+pub(crate) fn start(
+    endpoint: &Endpoint,
+    mode: LocalDiscovery,
+    event_tx: mpsc::Sender<Event>,
+) -> Result<Option<tokio::task::JoinHandle<()>>, P2pError> {
+    if mode == LocalDiscovery::Disabled {
+        return Ok(None);
+    }
+
+    let mdns = MdnsAddressLookup::builder()
+        .advertise(mode == LocalDiscovery::AnnounceAndDiscover)
+        .service_name(MDNS_SERVICE_NAME)
+        .build(endpoint.id())
+        .map_err(|e| P2pError::Bind(format!("Failed to start local discovery: {}", e)))?;
+    endpoint.address_lookup().add(mdns.clone());
+    tracing::info!("Local network discovery enabled ({:?})", mode);
+
+    Ok(Some(tokio::spawn(async move {
+        let mut events = mdns.subscribe().await;
+        while let Some(event) = events.next().await {
+            let DiscoveryEvent::Discovered { endpoint_info, .. } = event else {
+                continue;
+            };
+            let addr = endpoint_info.into_endpoint_addr();
+            let node_id = addr.id.to_string();
+            let addrs = addr.addrs.iter().map(transport_addr_to_string).collect();
+            tracing::debug!("Discovered {} on the local network", node_id);
+            if event_tx
+                .send(Event::PeerDiscovered { node_id, addrs })
+                .await
+                .is_err()
+            {
+                break;
+            }
+        }
+    })))
+}
@@ -0,0 +1,186 @@
+//! Error types for the P2P host and the wire protocol.
+
+use crate::DisconnectReason;
+
+/// Errors returned by the [`Host`](crate::Host) API.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum P2pError {
+    /// No live connection to the given peer
+    NotConnected { peer_id: String },
+    /// Establishing a connection to a peer failed
+    Connect(String),
+    /// The peer closed the connection on purpose, e.g. because we are banned
+    Refused(DisconnectReason),
+    /// A peer address (node id or EndpointAddr JSON) could not be parsed
+    InvalidAddress(String),
+    /// No pending HLS stream with the given id
+    StreamNotFound(String),
+    /// The peer did not answer in time
+    Timeout,
+    /// The peer sent something we could not understand
+    Protocol(String),
+    /// Reading from or writing to a stream failed
+    Io(String),
+    /// The peer answered with an error response
+    Remote {
+        code: u16,
+        message: String,
+        retryable: bool,
+    },
+    /// The request was cancelled through its `CancelHandle`
+    Cancelled,
+    /// The host's event loop is no longer running
+    Shutdown,
+    /// The endpoint could not bind its socket (e.g. the port is in use)
+    Bind(String),
+    /// A `HostConfig` value could not be used (e.g. a malformed relay URL)
+    InvalidConfig(String),
+    /// The node keypair could not be read or saved
+    Keypair(String),
+    /// The node keypair file or identity holds no valid key. It is left
+    /// untouched, as replacing it would unpair every device.
+    KeypairCorrupt(String),
+}
+
+impl P2pError {
+    /// Whether retrying the same operation later may succeed
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            P2pError::NotConnected { .. }
+            | P2pError::Connect(_)
+            | P2pError::Timeout
+            | P2pError::Io(_) => true,
+            P2pError::Remote { retryable, .. } => *retryable,
+            P2pError::InvalidAddress(_)
+            | P2pError::Refused(_)
+            | P2pError::StreamNotFound(_)
+            | P2pError::Protocol(_)
+            | P2pError::Cancelled
+            | P2pError::Shutdown
+            | P2pError::Bind(_)
+            | P2pError::InvalidConfig(_)
+            | P2pError::Keypair(_)
+            | P2pError::KeypairCorrupt(_) => false,
+        }
+    }
+
+    /// Stable machine-readable name of the error kind
+    pub fn kind(&self) -> &'static str {
+        match self {
+            P2pError::NotConnected { .. } => "not_connected",
+            P2pError::Connect(_) => "connect",
+            P2pError::Refused(_) => "refused",
+            P2pError::InvalidAddress(_) => "invalid_address",
+            P2pError::StreamNotFound(_) => "stream_not_found",
+            P2pError::Timeout => "timeout",
+            P2pError::Protocol(_) => "protocol",
+            P2pError::Io(_) => "io",
+            P2pError::Remote { .. } => "remote",
+            P2pError::Cancelled => "cancelled",
+            P2pError::Shutdown => "shutdown",
+            P2pError::Bind(_) => "bind",
+            P2pError::InvalidConfig(_) => "invalid_config",
+            P2pError::Keypair(_) => "keypair",
+            P2pError::KeypairCorrupt(_) => "keypair_corrupt",
+        }
+    }
+
+    /// Numeric code for remote errors
+    pub fn code(&self) -> Option<u16> {
+        match self {
+            P2pError::Remote { code, .. } => Some(*code),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for P2pError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            P2pError::NotConnected { peer_id } => write!(f, "Not connected to peer: {}", peer_id),
+            P2pError::Connect(e) => write!(f, "Failed to connect: {}", e),
+            P2pError::Refused(reason) => write!(f, "Connection refused by peer: {}", reason),
+            P2pError::InvalidAddress(e) => write!(f, "Invalid address: {}", e),
+            P2pError::StreamNotFound(id) => write!(f, "HLS stream not found: {}", id),
+            P2pError::Timeout => write!(f, "Request timed out"),
+            P2pError::Protocol(e) => write!(f, "Protocol error: {}", e),
+            P2pError::Io(e) => write!(f, "I/O error: {}", e),
+            P2pError::Remote { code, message, .. } => {
+                write!(f, "Remote error {}: {}", code, message)
+            }
+            P2pError::Cancelled => write!(f, "Request cancelled"),
+            P2pError::Shutdown => write!(f, "P2P host is shut down"),
+            P2pError::Bind(e) => write!(f, "Failed to bind endpoint: {}", e),
+            P2pError::InvalidConfig(e) => write!(f, "Invalid host config: {}", e),
+            P2pError::Keypair(e) => write!(f, "Keypair error: {}", e),
+            P2pError::KeypairCorrupt(e) => write!(f, "Corrupt keypair: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for P2pError {}
+
+impl From<WireError> for P2pError {
+    fn from(e: WireError) -> Self {
+        P2pError::Remote {
+            code: e.code,
+            message: e.message,
+            retryable: e.retryable,
+        }
+    }
+}
+
+/// Structured error carried in `MydiaResponse::Failure`.
+///
+/// Codes follow HTTP status semantics so they line up with the HLS path.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct WireError {
+    pub code: u16,
+    pub message: String,
+    #[serde(default)]
+    pub retryable: bool,
+}
+
+impl WireError {
+    pub const BAD_REQUEST: u16 = 400;
+    pub const UNAUTHORIZED: u16 = 401;
+    pub const FORBIDDEN: u16 = 403;
+    pub const NOT_FOUND: u16 = 404;
+    pub const PAYLOAD_TOO_LARGE: u16 = 413;
+    pub const TOO_MANY_REQUESTS: u16 = 429;
+    pub const INTERNAL: u16 = 500;
+    pub const UNAVAILABLE: u16 = 503;
+    pub const TIMEOUT: u16 = 504;
+
+    pub fn new(code: u16, message: impl Into<String>) -> Self {
+        WireError {
+            code,
+            message: message.into(),
+            retryable: matches!(
+                code,
+                WireError::TOO_MANY_REQUESTS | WireError::UNAVAILABLE | WireError::TIMEOUT
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retryable_flags() {
+        assert!(P2pError::Timeout.is_retryable());
+        assert!(!P2pError::Shutdown.is_retryable());
+        assert!(P2pError::from(WireError::new(WireError::UNAVAILABLE, "busy")).is_retryable());
+        assert!(!P2pError::from(WireError::new(WireError::NOT_FOUND, "gone")).is_retryable());
+    }
+
+    #[test]
+    fn test_remote_error_code() {
+        let err = P2pError::from(WireError::new(WireError::UNAUTHORIZED, "bad token"));
+        assert_eq!(err.kind(), "remote");
+        assert_eq!(err.code(), Some(401));
+        assert_eq!(P2pError::Timeout.code(), None);
+    }
+}
@@ -0,0 +1,363 @@
+//! The node's Ed25519 identity, which its node id is derived from.
+//!
+//! Identities are exported as the secret key in lowercase hex, so they can be
+//! kept in platform secure storage or moved to a new install.
+//!
+//! Keypair files are JSON with a format version, so metadata can be added
+//! later. Files from older releases holding the raw 32-byte key still load.
+//! An existing keypair is never replaced without a backup, as losing it
+//! unpairs every device.
+
+use crate::{HostConfig, P2pError};
+use iroh::SecretKey;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Version of the keypair file format written by this release
+pub const KEYPAIR_FILE_VERSION: u32 = 1;
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct KeypairFile {
+    version: u32,
+    /// Secret key in lowercase hex
+    secret_key: String,
+}
+
+/// Generate a new identity, ready to be stored and passed as `HostConfig::identity`
+pub fn generate_identity() -> String {
+    encode(&SecretKey::generate(&mut rand::rng()))
+}
+
+/// Node id an identity gives a Host
+pub fn identity_node_id(identity: &str) -> Result<String, P2pError> {
+    Ok(decode(identity)?.public().to_string())
+}
+
+/// Read the identity from a keypair file, e.g. to back it up
+pub fn export_identity(keypair_path: &str) -> Result<String, P2pError> {
+    let bytes = std::fs::read(keypair_path).map_err(|e| {
+        P2pError::Keypair(format!(
+            "Failed to read keypair from {}: {}",
+            keypair_path, e
+        ))
+    })?;
+    Ok(encode(&parse_keypair_file(keypair_path, &bytes)?))
+}
+
+/// Write an exported identity to a keypair file. A different identity
+/// already stored there is first copied to `<keypair_path>.bak.<timestamp>`.
+/// Returns the node id the imported identity gives.
+pub fn import_identity(keypair_path: &str, identity: &str) -> Result<String, P2pError> {
+    let secret_key = decode(identity)?;
+    let path = Path::new(keypair_path);
+    match std::fs::read(path) {
+        Ok(bytes) => {
+            let unchanged = parse_keypair_file(keypair_path, &bytes)
+                .is_ok_and(|existing| existing.to_bytes() == secret_key.to_bytes());
+            if !unchanged {
+                let backup = backup_path(path);
+                write_private(&backup, &bytes).map_err(|e| {
+                    P2pError::Keypair(format!(
+                        "Failed to back up keypair to {}: {}",
+                        backup.display(),
+                        e
+                    ))
+                })?;
+                tracing::warn!("Backed up replaced keypair to {}", backup.display());
+            }
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => {
+            return Err(P2pError::Keypair(format!(
+                "Failed to read keypair from {}: {}",
+                keypair_path, e
+            )))
+        }
+    }
+    save_keypair(keypair_path, &secret_key)?;
+    tracing::info!("Imported keypair to {}", keypair_path);
+    Ok(secret_key.public().to_string())
+}
+
+/// The secret key a Host runs with: `identity` if given, else the keypair
+/// at `keypair_path`, else a new random one
+pub(crate) fn secret_key(config: &HostConfig) -> Result<SecretKey, P2pError> {
+    match (&config.identity, &config.keypair_path) {
+        (Some(_), Some(_)) => Err(P2pError::InvalidConfig(
+            "identity cannot be combined with keypair_path".to_string(),
+        )),
+        (Some(identity), None) => decode(identity),
+        (None, path) => load_or_generate_keypair(path.as_deref()),
+    }
+}
+
+/// Load or generate an Ed25519 keypair for the node identity.
+/// A new keypair is only generated when there is no file at `path` yet;
+/// an unreadable or corrupt file is an error rather than silently replaced.
+fn load_or_generate_keypair(path: Option<&str>) -> Result<SecretKey, P2pError> {
+    let Some(path) = path else {
+        return Ok(SecretKey::generate(&mut rand::rng()));
+    };
+
+    match std::fs::read(path) {
+        Ok(bytes) => {
+            let secret_key = parse_keypair_file(path, &bytes)?;
+            tracing::info!("Loaded keypair from {}", path);
+            Ok(secret_key)
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            let secret_key = SecretKey::generate(&mut rand::rng());
+            save_keypair(path, &secret_key)?;
+            tracing::info!("Generated and saved new keypair to {}", path);
+            Ok(secret_key)
+        }
+        Err(e) => Err(P2pError::Keypair(format!(
+            "Failed to read keypair from {}: {}",
+            path, e
+        ))),
+    }
+}
+
+/// Parse a keypair file: the versioned JSON format, or a raw 32-byte key
+/// written by older releases
+fn parse_keypair_file(path: &str, bytes: &[u8]) -> Result<SecretKey, P2pError> {
+    if let Ok(raw) = <[u8; 32]>::try_from(bytes) {
+        return Ok(SecretKey::from_bytes(&raw));
+    }
+    let file: KeypairFile = serde_json::from_slice(bytes).map_err(|e| {
+        P2pError::KeypairCorrupt(format!(
+            "{} is neither a keypair file nor a 32-byte key: {}",
+            path, e
+        ))
+    })?;
+    if file.version > KEYPAIR_FILE_VERSION {
+        return Err(P2pError::Keypair(format!(
+            "{} uses keypair file version {}, this release reads up to {}",
+            path, file.version, KEYPAIR_FILE_VERSION
+        )));
+    }
+    decode(&file.secret_key).map_err(|e| P2pError::KeypairCorrupt(format!("{}: {}", path, e)))
+}
+
+fn save_keypair(path: &str, secret_key: &SecretKey) -> Result<(), P2pError> {
+    let file = KeypairFile {
+        version: KEYPAIR_FILE_VERSION,
+        secret_key: encode(secret_key),
+    };
+    let json = serde_json::to_vec_pretty(&file).map_err(|e| P2pError::Keypair(e.to_string()))?;
+    write_private(Path::new(path), &json)
+        .map_err(|e| P2pError::Keypair(format!("Failed to save keypair to {}: {}", path, e)))
+}
+
+/// Write a file readable only by its owner, via a temporary file in the
+/// same directory so a crash mid-write can't leave a truncated key behind
+fn write_private(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let mut tmp_name = path.as_os_str().to_owned();
+    tmp_name.push(".tmp");
+    let tmp = PathBuf::from(tmp_name);
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(&tmp)?;
+    // The mode only applies to new files, and a leftover temporary file
+    // keeps whatever permissions it had
+    #[cfg(unix)]
+    file.set_permissions(std::os::unix::fs::PermissionsExt::from_mode(0o600))?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    drop(file);
+    std::fs::rename(&tmp, path)?;
+    sync_parent(path)
+}
+
+/// Sync the directory holding `path` so a rename into it survives a crash
+#[cfg(unix)]
+fn sync_parent(path: &Path) -> std::io::Result<()> {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    std::fs::File::open(parent)?.sync_all()
+}
+
+#[cfg(not(unix))]
+fn sync_parent(_path: &Path) -> std::io::Result<()> {
+    Ok(())
+}
+
+fn backup_path(path: &Path) -> PathBuf {
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".bak.{}", timestamp));
+    PathBuf::from(name)
+}
+
+fn encode(secret_key: &SecretKey) -> String {
+    secret_key
+        .to_bytes()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn decode(identity: &str) -> Result<SecretKey, P2pError> {
+    let identity = identity.trim();
+    if identity.len() != 64 {
+        return Err(P2pError::KeypairCorrupt(
+            "Identity must be 64 hex characters".to_string(),
+        ));
+    }
+    identity
+        .parse()
+        .map_err(|e| P2pError::KeypairCorrupt(format!("Invalid identity: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path_in(dir: &tempfile::TempDir, name: &str) -> String {
+        dir.path().join(name).to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn test_export_import_keeps_node_id() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = path_in(&dir, "keypair.json");
+        let original = load_or_generate_keypair(Some(&path)).unwrap();
+        let identity = export_identity(&path).unwrap();
+        assert_eq!(
+            identity_node_id(&identity).unwrap(),
+            original.public().to_string()
+        );
+
+        // Restoring onto another install gives the same node id
+        let restored = path_in(&dir, "restored.json");
+        let node_id = import_identity(&restored, &identity).unwrap();
+        assert_eq!(node_id, original.public().to_string());
+        assert_eq!(
+            load_or_generate_keypair(Some(&restored)).unwrap().public(),
+            original.public()
+        );
+    }
+
+    #[test]
+    fn test_identity_config() {
+        let identity = generate_identity();
+        let config = HostConfig {
+            identity: Some(identity.clone()),
+            ..Default::default()
+        };
+        assert_eq!(
+            secret_key(&config).unwrap().public().to_string(),
+            identity_node_id(&identity).unwrap()
+        );
+
+        let both = HostConfig {
+            keypair_path: Some("/tmp/unused".to_string()),
+            ..config
+        };
+        assert!(matches!(secret_key(&both), Err(P2pError::InvalidConfig(_))));
+
+        assert!(matches!(
+            identity_node_id("not an identity"),
+            Err(P2pError::KeypairCorrupt(_))
+        ));
+    }
+
+    #[test]
+    fn test_corrupt_keypair_is_not_replaced() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = path_in(&dir, "keypair.json");
+        std::fs::write(&path, b"truncated").unwrap();
+
+        assert!(matches!(
+            load_or_generate_keypair(Some(&path)),
+            Err(P2pError::KeypairCorrupt(_))
+        ));
+        assert_eq!(std::fs::read(&path).unwrap(), b"truncated");
+    }
+
+    #[test]
+    fn test_legacy_raw_keypair_still_loads() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = path_in(&dir, "keypair.json");
+        let secret_key = SecretKey::generate(&mut rand::rng());
+        std::fs::write(&path, secret_key.to_bytes()).unwrap();
+
+        let loaded = load_or_generate_keypair(Some(&path)).unwrap();
+        assert_eq!(loaded.public(), secret_key.public());
+    }
+
+    #[test]
+    fn test_newer_file_version_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = path_in(&dir, "keypair.json");
+        let json = format!(
+            r#"{{"version": {}, "secret_key": "{}"}}"#,
+            KEYPAIR_FILE_VERSION + 1,
+            generate_identity()
+        );
+        std::fs::write(&path, json).unwrap();
+
+        assert!(matches!(
+            load_or_generate_keypair(Some(&path)),
+            Err(P2pError::Keypair(_))
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_leftover_temp_file_is_made_private() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::tempdir().unwrap();
+        let path = path_in(&dir, "keypair.json");
+        let tmp = path_in(&dir, "keypair.json.tmp");
+        std::fs::write(&tmp, b"stale").unwrap();
+        std::fs::set_permissions(&tmp, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+        import_identity(&path, &generate_identity()).unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert!(!Path::new(&tmp).exists());
+    }
+
+    #[test]
+    fn test_import_backs_up_replaced_keypair() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = path_in(&dir, "keypair.json");
+        let original = load_or_generate_keypair(Some(&path)).unwrap();
+        let original_bytes = std::fs::read(&path).unwrap();
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        import_identity(&path, &generate_identity()).unwrap();
+        assert_ne!(
+            load_or_generate_keypair(Some(&path)).unwrap().public(),
+            original.public()
+        );
+
+        let backups: Vec<_> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|p| {
+                p.file_name()
+                    .unwrap()
+                    .to_string_lossy()
+                    .starts_with("keypair.json.bak.")
+            })
+            .collect();
+        assert_eq!(backups.len(), 1);
+        assert_eq!(std::fs::read(&backups[0]).unwrap(), original_bytes);
+    }
+}
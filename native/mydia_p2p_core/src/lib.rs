@@ -4,22 +4,48 @@
 //! NAT traversal and QUIC-based connections.
 
 use iroh::{
-    defaults::prod as default_relays,
+    address_lookup::MemoryLookup,
     dns::DnsResolver,
-    endpoint::{Connection, SendStream},
-    Endpoint, EndpointAddr, EndpointId, RelayConfig, RelayMap, RelayMode, RelayUrl, SecretKey,
-    Watcher,
+    endpoint::{
+        ConnectOptions, Connection, Incoming, PathInfoList, QuicTransportConfig, RecvStream,
+        SendStream, Side, StoppedError, VarInt,
+    },
+    Endpoint, EndpointAddr, EndpointId, SecretKey, TransportAddr, Watcher,
 };
-use iroh_relay::RelayQuicConfig;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
-use std::sync::OnceLock;
 use tokio::runtime::Runtime;
 use tokio::sync::{mpsc, oneshot, Mutex};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Layer};
 
-// Protocol identifier for mydia connections
-const ALPN: &[u8] = b"/mydia/1.0.0";
+mod access;
+mod address_book;
+mod bind;
+mod discovery;
+mod error;
+mod identity;
+mod protocol;
+mod reconnect;
+mod relay;
+
+use access::{AccessControl, RequestLimiter};
+pub use access::{ConnectionPolicy, RequestLimits, UnpairedLimits};
+use address_book::AddressBook;
+pub use address_book::KnownPeer;
+pub use discovery::{LocalDiscovery, LookupService, MDNS_SERVICE_NAME};
+pub use error::{P2pError, WireError};
+pub use identity::{
+    export_identity, generate_identity, identity_node_id, import_identity, KEYPAIR_FILE_VERSION,
+};
+pub use protocol::{
+    DisconnectReason, Handshake, PeerProtocol, ProtocolVersion, CAP_FRAMED_MESSAGES,
+    CAP_SESSION_AUTH, CAP_STRUCTURED_ERRORS, DEFAULT_MAX_MESSAGE_SIZE, LOCAL_CAPABILITIES,
+    SUPPORTED_VERSIONS,
+};
+pub use reconnect::ReconnectPolicy;
+pub use relay::{RelayHealth, RelayPolicy};
+use relay::{RelayHealthTracker, RelayProber};
 
 // Request/Response Types (using Serde/CBOR)
 #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
@@ -29,9 +55,21 @@ pub enum MydiaRequest {
     ReadMedia(ReadMediaRequest),
     GraphQL(GraphQLRequest),
     HlsStream(HlsRequest),
+    /// Authenticate the connection (requires CAP_SESSION_AUTH)
+    Authenticate(AuthenticateRequest),
     Custom(Vec<u8>),
 }
 
+impl MydiaRequest {
+    /// Capability the peer must have agreed on to understand this request
+    fn required_capability(&self) -> Option<&'static str> {
+        match self {
+            MydiaRequest::Authenticate(_) => Some(CAP_SESSION_AUTH),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct PairingRequest {
     pub claim_code: String,
@@ -65,6 +103,21 @@ pub struct HlsRequest {
     pub auth_token: Option<String>,
 }
 
+/// Authenticates a connection once, so later requests on it can leave
+/// their `auth_token` out. A new connection starts unauthenticated.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct AuthenticateRequest {
+    pub token: String,
+}
+
+/// Who a connection authenticated as. The host remembers it for the
+/// connection and attaches it to every later request event.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Session {
+    pub principal: String,
+    pub scopes: Vec<String>,
+}
+
 /// HLS response header (sent first, then raw bytes stream)
 #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct HlsResponseHeader {
@@ -88,8 +141,36 @@ pub enum MydiaResponse {
     MediaChunk(Vec<u8>),
     GraphQL(GraphQLResponse),
     HlsHeader(HlsResponseHeader),
+    /// Answer to a successful `MydiaRequest::Authenticate`
+    Authenticated(Session),
     Custom(Vec<u8>),
+    /// Free-text error, understood by every protocol version
     Error(String),
+    /// Structured error with a numeric code (requires CAP_STRUCTURED_ERRORS)
+    Failure(WireError),
+}
+
+impl MydiaResponse {
+    /// Downgrade structured errors to free text for peers that don't understand them
+    fn for_peer(self, protocol: &PeerProtocol) -> MydiaResponse {
+        match self {
+            MydiaResponse::Failure(e) if !protocol.supports(CAP_STRUCTURED_ERRORS) => {
+                MydiaResponse::Error(e.message)
+            }
+            other => other,
+        }
+    }
+
+    /// Turn error responses from a peer into a `P2pError::Remote`
+    fn into_result(self) -> Result<MydiaResponse, P2pError> {
+        match self {
+            MydiaResponse::Failure(e) => Err(e.into()),
+            MydiaResponse::Error(message) => {
+                Err(WireError::new(WireError::INTERNAL, message).into())
+            }
+            other => Ok(other),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
@@ -111,16 +192,79 @@ pub struct HlsStreamResponse {
     pub chunk_rx: mpsc::Receiver<Vec<u8>>,
 }
 
+/// Handle for cancelling an in-flight client request.
+///
+/// Cancelling resets the request's QUIC stream so the server can abandon the
+/// work; for HLS requests this also stops the chunk stream. Dropping the handle
+/// without calling `cancel` lets the request run to completion.
+#[derive(Debug)]
+pub struct CancelHandle(oneshot::Sender<()>);
+
+impl CancelHandle {
+    fn new() -> (Self, oneshot::Receiver<()>) {
+        let (tx, rx) = oneshot::channel();
+        (CancelHandle(tx), rx)
+    }
+
+    /// Cancel the request
+    pub fn cancel(self) {
+        let _ = self.0.send(());
+    }
+}
+
+/// Resolves once the request's CancelHandle is cancelled.
+/// Never resolves if the handle was dropped instead.
+async fn cancelled(cancel_rx: oneshot::Receiver<()>) {
+    if cancel_rx.await.is_err() {
+        std::future::pending::<()>().await;
+    }
+}
+
 /// Commands that can be sent to the Host
 enum Command {
+    Shutdown {
+        grace: std::time::Duration,
+        reply: oneshot::Sender<()>,
+    },
     Dial {
         endpoint_addr_json: String,
-        reply: oneshot::Sender<Result<(), String>>,
+        reply: oneshot::Sender<Result<(), P2pError>>,
+    },
+    AddPeerAddr {
+        endpoint_addr_json: String,
+        reply: oneshot::Sender<Result<(), P2pError>>,
+    },
+    AllowPeer {
+        node_id: String,
+        reply: oneshot::Sender<Result<(), P2pError>>,
+    },
+    RevokePeer {
+        node_id: String,
+        reply: oneshot::Sender<Result<(), P2pError>>,
+    },
+    RevokeSession {
+        node_id: String,
+        reply: oneshot::Sender<bool>,
+    },
+    DisconnectPeer {
+        node_id: String,
+        reason: DisconnectReason,
+        reply: oneshot::Sender<bool>,
+    },
+    BanPeer {
+        node_id: String,
+        duration: Option<std::time::Duration>,
+        reply: oneshot::Sender<Result<(), P2pError>>,
+    },
+    UnbanPeer {
+        node_id: String,
+        reply: oneshot::Sender<Result<bool, P2pError>>,
     },
     SendRequest {
         node_id: String,
         request: MydiaRequest,
-        reply: oneshot::Sender<Result<MydiaResponse, String>>,
+        cancel_rx: oneshot::Receiver<()>,
+        reply: oneshot::Sender<Result<MydiaResponse, P2pError>>,
     },
     SendResponse {
         request_id: String,
@@ -129,62 +273,146 @@ enum Command {
     SendHlsHeader {
         stream_id: String,
         header: HlsResponseHeader,
-        reply: oneshot::Sender<Result<(), String>>,
+        reply: oneshot::Sender<Result<(), P2pError>>,
     },
     SendHlsChunk {
         stream_id: String,
         data: Vec<u8>,
-        reply: oneshot::Sender<Result<(), String>>,
+        reply: oneshot::Sender<Result<(), P2pError>>,
     },
     FinishHlsStream {
         stream_id: String,
-        reply: oneshot::Sender<Result<(), String>>,
+        reply: oneshot::Sender<Result<(), P2pError>>,
     },
     StreamFileRange {
         stream_id: String,
         file_path: String,
         offset: u64,
         length: u64,
-        reply: oneshot::Sender<Result<(), String>>,
+        reply: oneshot::Sender<Result<(), P2pError>>,
     },
     SendHlsRequest {
         node_id: String,
         request: HlsRequest,
-        reply: oneshot::Sender<Result<HlsStreamResponse, String>>,
+        cancel_rx: oneshot::Receiver<()>,
+        reply: oneshot::Sender<Result<HlsStreamResponse, P2pError>>,
     },
     GetNodeAddr {
         reply: oneshot::Sender<String>,
     },
+    ListPeers {
+        reply: oneshot::Sender<Vec<PeerInfo>>,
+    },
+    GetRelayHealth {
+        reply: oneshot::Sender<Vec<RelayHealth>>,
+    },
     GetNetworkStats {
         reply: oneshot::Sender<NetworkStats>,
     },
 }
 
+/// Why an HLS stream was dropped before it finished
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamCancelReason {
+    /// The client stopped reading (STOP_SENDING) with the given error code
+    Stopped(u64),
+    /// No header or chunk was written within the stream TTL
+    Expired,
+    /// The connection to the client closed
+    ConnectionLost,
+}
+
+impl StreamCancelReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StreamCancelReason::Stopped(_) => "stopped",
+            StreamCancelReason::Expired => "expired",
+            StreamCancelReason::ConnectionLost => "connection_lost",
+        }
+    }
+}
+
 /// Events emitted by the Host
 #[derive(Debug)]
 pub enum Event {
     Connected {
         peer_id: String,
         connection_type: PeerConnectionType,
+        /// Protocol version and capabilities agreed on during the handshake
+        protocol: PeerProtocol,
     },
     Disconnected(String),
     RequestReceived {
+        /// Node id of the peer, as authenticated by the connection handshake
         peer: String,
+        /// Session the connection authenticated as, if any
+        session: Option<Session>,
         request: MydiaRequest,
         request_id: String,
     },
+    /// The peer cancelled a request before its response was sent.
+    /// Any later send_response for this request_id is ignored.
+    RequestCancelled {
+        request_id: String,
+    },
+    /// An HLS stream was abandoned before it finished. Later writes to it
+    /// fail with `StreamNotFound`.
+    StreamCancelled {
+        stream_id: String,
+        reason: StreamCancelReason,
+    },
     /// HLS streaming request - requires streaming response via send_hls_header/chunk/finish
     HlsStreamRequest {
+        /// Node id of the peer, as authenticated by the connection handshake
         peer: String,
+        /// Session the connection authenticated as, if any
+        session: Option<Session>,
         request: HlsRequest,
         stream_id: String,
     },
-    /// Connection type changed (e.g. relay -> direct after hole-punching)
+    /// Connection type changed (e.g. relay -> direct after hole-punching,
+    /// or back to relay after a network switch)
     ConnectionTypeChanged {
         peer_id: String,
         connection_type: PeerConnectionType,
     },
+    /// The path used to send to a peer changed. `remote_addr` is a socket
+    /// address for direct paths or the relay URL for relayed ones.
+    SelectedPathChanged {
+        peer_id: String,
+        remote_addr: String,
+    },
+    /// The connection to a dialed peer dropped and the host is about to redial
+    /// it. `attempt` starts at 1. `Disconnected` follows only if the reconnect
+    /// policy gives up.
+    Reconnecting {
+        peer_id: String,
+        attempt: u32,
+    },
+    /// A dropped peer was redialed. Follows the `Connected` event for the new
+    /// connection.
+    Reconnected {
+        peer_id: String,
+        attempts: u32,
+    },
+    /// The peer closed the connection on purpose, e.g. because this device
+    /// was revoked. It is not redialed; `Disconnected` follows.
+    PeerClosed {
+        peer_id: String,
+        reason: DisconnectReason,
+    },
     RelayConnected,
+    /// The connection to the home relay was lost. Peers can still connect
+    /// directly; `RelayConnected` follows once it is back.
+    RelayDisconnected,
+    /// A Mydia node was found on the local network. `addrs` are its direct
+    /// socket addresses; it can be dialed by its bare node id.
+    PeerDiscovered {
+        node_id: String,
+        addrs: Vec<String>,
+    },
+    /// Emitted once the relay connected or `HostConfig::online_timeout`
+    /// passed without it. Peers can connect before this.
     Ready {
         node_addr: String,
     },
@@ -219,7 +447,8 @@ impl From<tracing::Level> for LogLevel {
 }
 
 /// Connection type for a peer (relay vs direct)
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum PeerConnectionType {
     /// Direct peer-to-peer connection
     Direct,
@@ -228,14 +457,17 @@ pub enum PeerConnectionType {
     /// Using both relay and direct paths
     Mixed,
     /// No active connection
+    #[default]
     None,
 }
 
 impl PeerConnectionType {
     /// Determine connection type from a Connection's paths
     pub fn from_connection(conn: &Connection) -> Self {
-        let mut paths = conn.paths();
-        let paths_list = paths.get();
+        Self::from_paths(&conn.paths().get())
+    }
+
+    fn from_paths(paths_list: &PathInfoList) -> Self {
         if paths_list.is_empty() {
             return PeerConnectionType::None;
         }
@@ -260,8 +492,27 @@ impl PeerConnectionType {
     }
 }
 
-/// Global log channel for forwarding logs to Elixir
-static LOG_TX: OnceLock<mpsc::Sender<Event>> = OnceLock::new();
+/// Event channels of the running hosts. Logs are process-wide, so every
+/// running host is sent all of them.
+static LOG_SINKS: std::sync::Mutex<Vec<mpsc::Sender<Event>>> = std::sync::Mutex::new(Vec::new());
+
+/// Forwards logs to a host's event channel until dropped
+struct LogSink(mpsc::Sender<Event>);
+
+impl LogSink {
+    fn install(event_tx: mpsc::Sender<Event>) -> Self {
+        init_tracing();
+        LOG_SINKS.lock().unwrap().push(event_tx.clone());
+        LogSink(event_tx)
+    }
+}
+
+impl Drop for LogSink {
+    fn drop(&mut self) {
+        let mut sinks = LOG_SINKS.lock().unwrap_or_else(|e| e.into_inner());
+        sinks.retain(|tx| !tx.same_channel(&self.0));
+    }
+}
 
 /// Custom tracing layer that forwards logs to Elixir via the event channel
 struct ElixirLogLayer;
@@ -275,20 +526,23 @@ where
         event: &tracing::Event<'_>,
         _ctx: tracing_subscriber::layer::Context<'_, S>,
     ) {
-        if let Some(tx) = LOG_TX.get() {
-            // Extract message from event
-            let mut message = String::new();
-            let mut visitor = MessageVisitor(&mut message);
-            event.record(&mut visitor);
+        let sinks = LOG_SINKS.lock().unwrap_or_else(|e| e.into_inner());
+        if sinks.is_empty() {
+            return;
+        }
 
-            let log_event = Event::Log {
+        // Extract message from event
+        let mut message = String::new();
+        let mut visitor = MessageVisitor(&mut message);
+        event.record(&mut visitor);
+
+        // Non-blocking send - drop if channel is full
+        for tx in sinks.iter() {
+            let _ = tx.try_send(Event::Log {
                 level: (*event.metadata().level()).into(),
                 target: event.metadata().target().to_string(),
-                message,
-            };
-
-            // Non-blocking send - drop if channel is full
-            let _ = tx.try_send(log_event);
+                message: message.clone(),
+            });
         }
     }
 }
@@ -316,20 +570,19 @@ impl<'a> tracing::field::Visit for MessageVisitor<'a> {
 }
 
 /// Initialize tracing with the Elixir log layer
-fn init_tracing(event_tx: mpsc::Sender<Event>) {
+fn init_tracing() {
     // Only initialize once
-    if LOG_TX.set(event_tx).is_err() {
-        return;
-    }
-
-    // Set up tracing with env filter (default to info, but can be overridden with RUST_LOG)
-    let filter = EnvFilter::try_from_default_env()
-        .unwrap_or_else(|_| EnvFilter::new("info,iroh=info,quinn=warn,rustls=warn"));
-
-    let _ = tracing_subscriber::registry()
-        .with(filter)
-        .with(ElixirLogLayer)
-        .try_init();
+    static INIT: std::sync::Once = std::sync::Once::new();
+    INIT.call_once(|| {
+        // Set up tracing with env filter (default to info, but can be overridden with RUST_LOG)
+        let filter = EnvFilter::try_from_default_env()
+            .unwrap_or_else(|_| EnvFilter::new("info,iroh=info,quinn=warn,rustls=warn"));
+
+        let _ = tracing_subscriber::registry()
+            .with(filter)
+            .with(ElixirLogLayer)
+            .try_init();
+    });
 }
 
 /// Network statistics
@@ -337,56 +590,102 @@ fn init_tracing(event_tx: mpsc::Sender<Event>) {
 pub struct NetworkStats {
     pub connected_peers: usize,
     pub relay_connected: bool,
-    /// The relay URL currently in use (None if using iroh defaults)
+    /// The home relay URL currently in use (None if not connected to one)
     pub relay_url: Option<String>,
-    /// Connection type for the first connected peer (for UI display)
+    /// The relay policy the host was started with
+    pub relay_policy: RelayPolicy,
+    /// Local socket addresses the endpoint is bound to
+    pub bound_addrs: Vec<String>,
+    /// Connection type for the first connected peer (for UI display).
+    /// Use `Host::list_peers` for per-peer details.
     pub peer_connection_type: PeerConnectionType,
+    /// Incoming requests refused since the host started, by the connection
+    /// policy or for going over `HostConfig::request_limits`
+    pub rejected_requests: u64,
 }
 
-impl Default for PeerConnectionType {
-    fn default() -> Self {
-        PeerConnectionType::None
-    }
+/// Details about a single peer connection
+#[derive(Debug, Clone)]
+pub struct PeerInfo {
+    pub node_id: String,
+    pub connection_type: PeerConnectionType,
+    /// Remote addresses of all open paths: socket addresses for direct
+    /// paths, relay URLs for relayed ones
+    pub remote_addrs: Vec<String>,
+    /// Round-trip time on the selected path
+    pub rtt_ms: Option<u64>,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    /// Unix timestamp (seconds) when the connection was established
+    pub connected_since: u64,
+    /// Requests and HLS streams currently in flight in either direction
+    pub open_streams: usize,
+    /// Incoming requests refused on this connection
+    pub rejected_requests: u64,
 }
 
 /// Configuration for the Host
 #[derive(Clone, Default)]
 pub struct HostConfig {
-    /// Custom relay URL for NAT traversal. If None, uses iroh's default relays.
-    pub relay_url: Option<String>,
-    /// UDP port for direct connections. If None or 0, uses a random port.
+    /// Relay servers to use for NAT traversal. Defaults to iroh's relays.
+    pub relay: RelayPolicy,
+    /// UDP port for direct connections over IPv4, or over both families when
+    /// `bind_interface` is set. If None or 0, uses a random port.
     pub bind_port: Option<u16>,
+    /// IPv4 socket address to bind instead of `bind_port` on all interfaces
+    pub bind_addr_v4: Option<std::net::SocketAddrV4>,
+    /// IPv6 socket address to bind. If None, a random IPv6 port is used when available.
+    pub bind_addr_v6: Option<std::net::SocketAddrV6>,
+    /// Only bind the addresses of this network interface (e.g. "eth0"), on
+    /// `bind_port`. Cannot be combined with `bind_addr_v4`/`bind_addr_v6`.
+    pub bind_interface: Option<String>,
     /// Path to store/load keypair (optional). If not set, a new random keypair is generated.
     pub keypair_path: Option<String>,
+    /// Exported identity to run with instead of a keypair file, for callers
+    /// that keep it in platform secure storage. Cannot be combined with `keypair_path`.
+    pub identity: Option<String>,
+    /// Maximum size in bytes of a single request or response message.
+    /// If None, uses DEFAULT_MAX_MESSAGE_SIZE.
+    pub max_message_size: Option<usize>,
+    /// How long an HLS stream may go without a header or chunk being written
+    /// before it is dropped. If None, uses DEFAULT_HLS_STREAM_TTL.
+    pub hls_stream_ttl: Option<std::time::Duration>,
+    /// Redial peers this host dialed when their connection drops.
+    /// If None, a dropped connection is reported as `Disconnected` straight away.
+    pub reconnect: Option<ReconnectPolicy>,
+    /// Make `Host::start` return only once `Ready` has been emitted rather
+    /// than as soon as the endpoint is bound.
+    pub wait_for_ready: bool,
+    /// Announce and/or discover Mydia nodes on the local network over mDNS
+    pub local_discovery: LocalDiscovery,
+    /// Where to publish this node's addresses and look up peers dialed by node id
+    pub lookup: LookupService,
+    /// EndpointAddr JSON of peers to put in the address book up front, so
+    /// they can be dialed by node id without any lookup
+    pub known_peers: Vec<String>,
+    /// File to persist the address book to, so peers' last known addresses
+    /// survive a restart. If None, the address book is kept in memory only.
+    pub address_book_path: Option<String>,
+    /// Which peers get full access to incoming requests. Defaults to all of them.
+    pub connection_policy: ConnectionPolicy,
+    /// Caps on the rate and concurrency of every peer's requests
+    pub request_limits: RequestLimits,
+    /// How long to wait for the relay before emitting `Ready` without it.
+    /// If None, uses DEFAULT_ONLINE_TIMEOUT. Unused when relays are disabled.
+    pub online_timeout: Option<std::time::Duration>,
+    /// How often to probe every relay for `Host::relay_health`.
+    /// If None, uses DEFAULT_RELAY_PROBE_INTERVAL.
+    pub relay_probe_interval: Option<std::time::Duration>,
 }
 
-/// Load or generate an Ed25519 keypair for the node identity
-fn load_or_generate_keypair(path: Option<&str>) -> SecretKey {
-    if let Some(path) = path {
-        if let Ok(bytes) = std::fs::read(path) {
-            if bytes.len() == 32 {
-                let mut arr = [0u8; 32];
-                arr.copy_from_slice(&bytes);
-                tracing::info!("Loaded keypair from {}", path);
-                return SecretKey::from_bytes(&arr);
-            }
-        }
-    }
-
-    // Generate new
-    let secret_key = SecretKey::generate(&mut rand::rng());
+/// Default idle timeout for HLS streams waiting on the application
+pub const DEFAULT_HLS_STREAM_TTL: std::time::Duration = std::time::Duration::from_secs(60);
 
-    // Save if path provided
-    if let Some(path) = path {
-        if let Err(e) = std::fs::write(path, secret_key.to_bytes()) {
-            tracing::warn!("Failed to save keypair to {}: {}", path, e);
-        } else {
-            tracing::info!("Generated and saved new keypair to {}", path);
-        }
-    }
+/// Default time to wait for the relay before emitting `Ready` without it
+pub const DEFAULT_ONLINE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
 
-    secret_key
-}
+/// Default time between rounds of relay probes
+pub const DEFAULT_RELAY_PROBE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
 
 /// Serialize EndpointAddr to JSON for sharing
 fn endpoint_addr_to_json(addr: &EndpointAddr) -> String {
@@ -394,8 +693,21 @@ fn endpoint_addr_to_json(addr: &EndpointAddr) -> String {
 }
 
 /// Deserialize EndpointAddr from JSON
-fn endpoint_addr_from_json(json: &str) -> Result<EndpointAddr, String> {
-    serde_json::from_str(json).map_err(|e| format!("Invalid EndpointAddr JSON: {}", e))
+fn endpoint_addr_from_json(json: &str) -> Result<EndpointAddr, P2pError> {
+    serde_json::from_str(json)
+        .map_err(|e| P2pError::InvalidAddress(format!("Invalid EndpointAddr JSON: {}", e)))
+}
+
+/// Parse a dial target: EndpointAddr JSON, or a bare node id whose
+/// addresses iroh looks up (e.g. from local discovery)
+fn parse_dial_addr(addr: &str) -> Result<EndpointAddr, P2pError> {
+    if addr.trim_start().starts_with('{') {
+        return endpoint_addr_from_json(addr);
+    }
+    addr.trim()
+        .parse::<EndpointId>()
+        .map(EndpointAddr::new)
+        .map_err(|e| P2pError::InvalidAddress(format!("Invalid node id: {}", e)))
 }
 
 /// The core Host struct that manages the iroh Endpoint
@@ -403,43 +715,203 @@ pub struct Host {
     pub(crate) cmd_tx: mpsc::Sender<Command>,
     pub event_rx: Arc<Mutex<mpsc::Receiver<Event>>>,
     node_id: String,
+    /// Event loop thread, taken by `shutdown`
+    thread: std::sync::Mutex<Option<std::thread::JoinHandle<()>>>,
 }
 
+/// Grace period callers can pass to `Host::shutdown`
+pub const DEFAULT_SHUTDOWN_GRACE: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// How often a shutting down host checks whether in-flight streams finished
+const SHUTDOWN_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(50);
+
 impl Host {
+    /// Start a host without waiting for it to come up.
+    ///
+    /// Startup failures are only logged and every later call fails with
+    /// `P2pError::Shutdown`; an unreadable keypair or identity is replaced by
+    /// a random one for this run. Prefer `Host::start`, which reports them.
     pub fn new(config: HostConfig) -> (Self, String) {
-        let secret_key = load_or_generate_keypair(config.keypair_path.as_deref());
+        let secret_key = identity::secret_key(&config).unwrap_or_else(|e| {
+            tracing::warn!("{} - using a temporary keypair", e);
+            SecretKey::generate(&mut rand::rng())
+        });
+        let (host, _started) = Host::spawn(secret_key, config);
+        let node_id = host.node_id.clone();
+        (host, node_id)
+    }
+
+    /// Start a host and wait until its endpoint is bound, or until `Ready`
+    /// has been emitted if `config.wait_for_ready` is set.
+    ///
+    /// Returns the error that stopped startup, such as the port being in use,
+    /// a malformed relay URL or an unreadable keypair. Blocks, so it must not
+    /// be called from inside an async runtime.
+    pub fn start(config: HostConfig) -> Result<Host, P2pError> {
+        let secret_key = identity::secret_key(&config)?;
+        let (host, started) = Host::spawn(secret_key, config);
+        match started.blocking_recv() {
+            Ok(Ok(())) => Ok(host),
+            Ok(Err(e)) => Err(e),
+            Err(_) => Err(P2pError::Shutdown),
+        }
+    }
+
+    /// Spawn the event loop in a background thread with its own runtime.
+    /// The returned receiver resolves once startup succeeded or failed.
+    fn spawn(
+        secret_key: SecretKey,
+        config: HostConfig,
+    ) -> (Self, oneshot::Receiver<Result<(), P2pError>>) {
         let node_id = secret_key.public().to_string();
-        let node_id_str = node_id.clone();
 
         let (cmd_tx, cmd_rx) = mpsc::channel::<Command>(32);
         let (event_tx, event_rx) = mpsc::channel::<Event>(100);
+        let (started_tx, started_rx) = oneshot::channel();
 
-        // Spawn the event loop in a background thread with its own runtime
-        std::thread::spawn(move || {
+        let thread = std::thread::spawn(move || {
             let rt = Runtime::new().expect("Failed to create Tokio runtime");
-            rt.block_on(run_event_loop(secret_key, config, cmd_rx, event_tx));
+            rt.block_on(run_event_loop(
+                secret_key, config, cmd_rx, event_tx, started_tx,
+            ));
         });
 
         (
             Host {
                 cmd_tx,
                 event_rx: Arc::new(Mutex::new(event_rx)),
-                node_id: node_id_str.clone(),
+                node_id,
+                thread: std::sync::Mutex::new(Some(thread)),
             },
-            node_id_str,
+            started_rx,
         )
     }
 
-    /// Dial a peer using their EndpointAddr JSON
-    pub fn dial(&self, endpoint_addr_json: String) -> Result<(), String> {
+    /// Stop the host: stop accepting connections, give in-flight requests and
+    /// HLS streams up to `grace` to finish, then close every connection and the
+    /// endpoint and wait for the background thread to exit. The UDP port is
+    /// free again once this returns.
+    ///
+    /// Calling it again is a no-op. Blocks, so it must not be called from
+    /// inside an async runtime.
+    pub fn shutdown(&self, grace: std::time::Duration) -> Result<(), P2pError> {
+        let mut thread = self.thread.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(handle) = thread.take() else {
+            return Ok(());
+        };
+
+        let (tx, rx) = oneshot::channel();
+        if self
+            .cmd_tx
+            .blocking_send(Command::Shutdown { grace, reply: tx })
+            .is_ok()
+        {
+            let _ = rx.blocking_recv();
+        }
+
+        handle
+            .join()
+            .map_err(|_| P2pError::Io("Event loop thread panicked".to_string()))
+    }
+
+    /// Dial a peer using their EndpointAddr JSON, or their bare node id when
+    /// its address can be looked up (e.g. found by local discovery)
+    pub fn dial(&self, endpoint_addr_json: String) -> Result<(), P2pError> {
         let (tx, rx) = oneshot::channel();
         self.cmd_tx
             .blocking_send(Command::Dial {
                 endpoint_addr_json,
                 reply: tx,
             })
-            .map_err(|_| "send_failed".to_string())?;
-        rx.blocking_recv().map_err(|_| "recv_failed".to_string())?
+            .map_err(|_| P2pError::Shutdown)?;
+        rx.blocking_recv().map_err(|_| P2pError::Shutdown)?
+    }
+
+    /// Add a peer's EndpointAddr JSON to the address book, so it can be
+    /// dialed by node id later. Peers dialed by EndpointAddr are added automatically.
+    pub fn add_peer_addr(&self, endpoint_addr_json: String) -> Result<(), P2pError> {
+        let (tx, rx) = oneshot::channel();
+        self.cmd_tx
+            .blocking_send(Command::AddPeerAddr {
+                endpoint_addr_json,
+                reply: tx,
+            })
+            .map_err(|_| P2pError::Shutdown)?;
+        rx.blocking_recv().map_err(|_| P2pError::Shutdown)?
+    }
+
+    /// Give a paired device full access under `ConnectionPolicy::Allowlist`
+    pub fn allow_peer(&self, node_id: String) -> Result<(), P2pError> {
+        let (tx, rx) = oneshot::channel();
+        self.cmd_tx
+            .blocking_send(Command::AllowPeer { node_id, reply: tx })
+            .map_err(|_| P2pError::Shutdown)?;
+        rx.blocking_recv().map_err(|_| P2pError::Shutdown)?
+    }
+
+    /// Take full access away from a device, e.g. when it is revoked.
+    /// Its current connection stays open but is limited like any unpaired peer.
+    pub fn revoke_peer(&self, node_id: String) -> Result<(), P2pError> {
+        let (tx, rx) = oneshot::channel();
+        self.cmd_tx
+            .blocking_send(Command::RevokePeer { node_id, reply: tx })
+            .map_err(|_| P2pError::Shutdown)?;
+        rx.blocking_recv().map_err(|_| P2pError::Shutdown)?
+    }
+
+    /// End the session of the connection from `node_id` by closing the
+    /// connection. Returns whether there was an authenticated connection.
+    pub fn revoke_session(&self, node_id: String) -> Result<bool, P2pError> {
+        let (tx, rx) = oneshot::channel();
+        self.cmd_tx
+            .blocking_send(Command::RevokeSession { node_id, reply: tx })
+            .map_err(|_| P2pError::Shutdown)?;
+        rx.blocking_recv().map_err(|_| P2pError::Shutdown)
+    }
+
+    /// Close the connection to a peer, telling it why. A peer we dialed is
+    /// not redialed. Returns whether there was a connection to close.
+    pub fn disconnect_peer(
+        &self,
+        node_id: String,
+        reason: DisconnectReason,
+    ) -> Result<bool, P2pError> {
+        let (tx, rx) = oneshot::channel();
+        self.cmd_tx
+            .blocking_send(Command::DisconnectPeer {
+                node_id,
+                reason,
+                reply: tx,
+            })
+            .map_err(|_| P2pError::Shutdown)?;
+        rx.blocking_recv().map_err(|_| P2pError::Shutdown)
+    }
+
+    /// Refuse connections from a peer for `duration`, or until `unban_peer`
+    /// if None, and close its current connection. Bans last until the host stops.
+    pub fn ban_peer(
+        &self,
+        node_id: String,
+        duration: Option<std::time::Duration>,
+    ) -> Result<(), P2pError> {
+        let (tx, rx) = oneshot::channel();
+        self.cmd_tx
+            .blocking_send(Command::BanPeer {
+                node_id,
+                duration,
+                reply: tx,
+            })
+            .map_err(|_| P2pError::Shutdown)?;
+        rx.blocking_recv().map_err(|_| P2pError::Shutdown)?
+    }
+
+    /// Lift a ban. Returns whether the peer was banned.
+    pub fn unban_peer(&self, node_id: String) -> Result<bool, P2pError> {
+        let (tx, rx) = oneshot::channel();
+        self.cmd_tx
+            .blocking_send(Command::UnbanPeer { node_id, reply: tx })
+            .map_err(|_| P2pError::Shutdown)?;
+        rx.blocking_recv().map_err(|_| P2pError::Shutdown)?
     }
 
     /// Get this node's address as JSON for sharing
@@ -455,32 +927,58 @@ impl Host {
         rx.blocking_recv().unwrap_or_default()
     }
 
-    /// Send a request to a peer and wait for a response
+    /// Send a request to a peer and wait for a response.
+    /// Error responses from the peer are returned as `P2pError::Remote`.
     pub async fn send_request(
         &self,
         node_id: String,
         request: MydiaRequest,
-    ) -> Result<MydiaResponse, String> {
-        let (tx, rx) = oneshot::channel();
-        self.cmd_tx
-            .send(Command::SendRequest {
-                node_id,
-                request,
-                reply: tx,
-            })
-            .await
-            .map_err(|_| "send_failed".to_string())?;
-        rx.await.map_err(|_| "recv_failed".to_string())?
+    ) -> Result<MydiaResponse, P2pError> {
+        let (_cancel, response) = self.send_request_cancellable(node_id, request);
+        response.await
+    }
+
+    /// Send a request to a peer, returning a handle that can cancel it
+    /// together with a future that resolves to the response.
+    /// A cancelled request resolves to `P2pError::Cancelled`.
+    pub fn send_request_cancellable(
+        &self,
+        node_id: String,
+        request: MydiaRequest,
+    ) -> (
+        CancelHandle,
+        impl std::future::Future<Output = Result<MydiaResponse, P2pError>> + Send + 'static,
+    ) {
+        let (cancel, cancel_rx) = CancelHandle::new();
+        let cmd_tx = self.cmd_tx.clone();
+        let response = async move {
+            let (tx, rx) = oneshot::channel();
+            cmd_tx
+                .send(Command::SendRequest {
+                    node_id,
+                    request,
+                    cancel_rx,
+                    reply: tx,
+                })
+                .await
+                .map_err(|_| P2pError::Shutdown)?;
+            rx.await.map_err(|_| P2pError::Shutdown)?
+        };
+        (cancel, response)
     }
 
     /// Send a response to an incoming request
-    pub fn send_response(&self, request_id: String, response: MydiaResponse) -> Result<(), String> {
+    pub fn send_response(
+        &self,
+        request_id: String,
+        response: MydiaResponse,
+    ) -> Result<(), P2pError> {
         self.cmd_tx
             .blocking_send(Command::SendResponse {
                 request_id,
                 response,
             })
-            .map_err(|_| "send_failed".to_string())
+            .map_err(|_| P2pError::Shutdown)
     }
 
     /// Send a response to an incoming request (async version)
@@ -488,14 +986,41 @@ impl Host {
         &self,
         request_id: String,
         response: MydiaResponse,
-    ) -> Result<(), String> {
+    ) -> Result<(), P2pError> {
         self.cmd_tx
             .send(Command::SendResponse {
                 request_id,
                 response,
             })
             .await
-            .map_err(|_| "send_failed".to_string())
+            .map_err(|_| P2pError::Shutdown)
+    }
+
+    /// Get details about every connected peer
+    pub fn list_peers(&self) -> Vec<PeerInfo> {
+        let (tx, rx) = oneshot::channel();
+        if self
+            .cmd_tx
+            .blocking_send(Command::ListPeers { reply: tx })
+            .is_err()
+        {
+            return Vec::new();
+        }
+        rx.blocking_recv().unwrap_or_default()
+    }
+
+    /// Get the latest probe results for every relay the host may use,
+    /// lowest latency first
+    pub fn relay_health(&self) -> Vec<RelayHealth> {
+        let (tx, rx) = oneshot::channel();
+        if self
+            .cmd_tx
+            .blocking_send(Command::GetRelayHealth { reply: tx })
+            .is_err()
+        {
+            return Vec::new();
+        }
+        rx.blocking_recv().unwrap_or_default()
     }
 
     /// Get network statistics
@@ -522,7 +1047,7 @@ impl Host {
         &self,
         stream_id: String,
         header: HlsResponseHeader,
-    ) -> Result<(), String> {
+    ) -> Result<(), P2pError> {
         let (tx, rx) = oneshot::channel();
         self.cmd_tx
             .blocking_send(Command::SendHlsHeader {
@@ -530,13 +1055,13 @@ impl Host {
                 header,
                 reply: tx,
             })
-            .map_err(|_| "send_failed".to_string())?;
-        rx.blocking_recv().map_err(|_| "recv_failed".to_string())?
+            .map_err(|_| P2pError::Shutdown)?;
+        rx.blocking_recv().map_err(|_| P2pError::Shutdown)?
     }
 
     /// Send a chunk of HLS data.
     /// Must be called after send_hls_header and before finish_hls_stream.
-    pub fn send_hls_chunk(&self, stream_id: String, data: Vec<u8>) -> Result<(), String> {
+    pub fn send_hls_chunk(&self, stream_id: String, data: Vec<u8>) -> Result<(), P2pError> {
         let (tx, rx) = oneshot::channel();
         self.cmd_tx
             .blocking_send(Command::SendHlsChunk {
@@ -544,21 +1069,21 @@ impl Host {
                 data,
                 reply: tx,
             })
-            .map_err(|_| "send_failed".to_string())?;
-        rx.blocking_recv().map_err(|_| "recv_failed".to_string())?
+            .map_err(|_| P2pError::Shutdown)?;
+        rx.blocking_recv().map_err(|_| P2pError::Shutdown)?
     }
 
     /// Finish an HLS stream.
     /// Must be called after all chunks have been sent.
-    pub fn finish_hls_stream(&self, stream_id: String) -> Result<(), String> {
+    pub fn finish_hls_stream(&self, stream_id: String) -> Result<(), P2pError> {
         let (tx, rx) = oneshot::channel();
         self.cmd_tx
             .blocking_send(Command::FinishHlsStream {
                 stream_id,
                 reply: tx,
             })
-            .map_err(|_| "send_failed".to_string())?;
-        rx.blocking_recv().map_err(|_| "recv_failed".to_string())?
+            .map_err(|_| P2pError::Shutdown)?;
+        rx.blocking_recv().map_err(|_| P2pError::Shutdown)?
     }
 
     /// Stream a file range directly to a QUIC stream.
@@ -570,7 +1095,7 @@ impl Host {
         file_path: String,
         offset: u64,
         length: u64,
-    ) -> Result<(), String> {
+    ) -> Result<(), P2pError> {
         let (tx, rx) = oneshot::channel();
         self.cmd_tx
             .blocking_send(Command::StreamFileRange {
@@ -580,8 +1105,8 @@ impl Host {
                 length,
                 reply: tx,
             })
-            .map_err(|_| "send_failed".to_string())?;
-        rx.blocking_recv().map_err(|_| "recv_failed".to_string())?
+            .map_err(|_| P2pError::Shutdown)?;
+        rx.blocking_recv().map_err(|_| P2pError::Shutdown)?
     }
 
     /// Send an HLS streaming request to a peer (client-side).
@@ -590,17 +1115,24 @@ impl Host {
         &self,
         node_id: String,
         request: HlsRequest,
-    ) -> Result<HlsStreamResponse, String> {
-        let (tx, rx) = oneshot::channel();
-        self.cmd_tx
-            .send(Command::SendHlsRequest {
-                node_id,
-                request,
-                reply: tx,
-            })
+    ) -> Result<HlsStreamResponse, P2pError> {
+        self.hls_requester()
+            .send_hls_request(node_id, request)
             .await
-            .map_err(|_| "send_failed".to_string())?;
-        rx.await.map_err(|_| "recv_failed".to_string())?
+    }
+
+    /// Send an HLS streaming request to a peer, returning a handle that can
+    /// cancel it before or while chunks are streaming.
+    pub fn send_hls_request_cancellable(
+        &self,
+        node_id: String,
+        request: HlsRequest,
+    ) -> (
+        CancelHandle,
+        impl std::future::Future<Output = Result<HlsStreamResponse, P2pError>> + Send + 'static,
+    ) {
+        self.hls_requester()
+            .send_hls_request_cancellable(node_id, request)
     }
 }
 
@@ -620,17 +1152,55 @@ impl HlsRequester {
         &self,
         node_id: String,
         request: HlsRequest,
-    ) -> Result<HlsStreamResponse, String> {
-        let (tx, rx) = oneshot::channel();
-        self.cmd_tx
-            .send(Command::SendHlsRequest {
-                node_id,
-                request,
+    ) -> Result<HlsStreamResponse, P2pError> {
+        let (_cancel, response) = self.send_hls_request_cancellable(node_id, request);
+        response.await
+    }
+
+    /// Send an HLS streaming request to a peer, returning a handle that can
+    /// cancel it before or while chunks are streaming.
+    /// A request cancelled before the header arrives resolves to `P2pError::Cancelled`;
+    /// cancelling later ends the chunk stream early.
+    pub fn send_hls_request_cancellable(
+        &self,
+        node_id: String,
+        request: HlsRequest,
+    ) -> (
+        CancelHandle,
+        impl std::future::Future<Output = Result<HlsStreamResponse, P2pError>> + Send + 'static,
+    ) {
+        let (cancel, cancel_rx) = CancelHandle::new();
+        let cmd_tx = self.cmd_tx.clone();
+        let response = async move {
+            let (tx, rx) = oneshot::channel();
+            cmd_tx
+                .send(Command::SendHlsRequest {
+                    node_id,
+                    request,
+                    cancel_rx,
+                    reply: tx,
+                })
+                .await
+                .map_err(|_| P2pError::Shutdown)?;
+            rx.await.map_err(|_| P2pError::Shutdown)?
+        };
+        (cancel, response)
+    }
+}
+
+impl Drop for Host {
+    fn drop(&mut self) {
+        if tokio::runtime::Handle::try_current().is_ok() {
+            // Blocking here would stall the caller's runtime; ask the event loop
+            // to stop and let its thread exit on its own
+            let (tx, _rx) = oneshot::channel();
+            let _ = self.cmd_tx.try_send(Command::Shutdown {
+                grace: std::time::Duration::ZERO,
                 reply: tx,
-            })
-            .await
-            .map_err(|_| "send_failed".to_string())?;
-        rx.await.map_err(|_| "recv_failed".to_string())?
+            });
+            return;
+        }
+        let _ = self.shutdown(std::time::Duration::ZERO);
     }
 }
 
@@ -647,185 +1217,620 @@ impl Host {
     }
 }
 
-/// Shared state for pending responses
-struct SharedState {
-    pending_responses: HashMap<String, oneshot::Sender<MydiaResponse>>,
-    /// Active HLS streaming connections - stores the send half of the stream
-    hls_streams: HashMap<String, SendStream>,
+/// A live connection to a peer together with the protocol agreed on for it
+#[derive(Clone)]
+struct PeerConnection {
+    conn: Connection,
+    protocol: PeerProtocol,
+    /// Identifies this connection among all connections to the same peer
+    generation: u64,
+    connected_at: std::time::SystemTime,
+    open_streams: Arc<AtomicUsize>,
+    /// Incoming requests refused on this connection
+    rejected_requests: Arc<AtomicU64>,
+    /// Set once the peer authenticates, on connections we accepted
+    session: Arc<std::sync::RwLock<Option<Session>>>,
 }
 
-/// Create a DNS resolver using the system default.
-fn create_dns_resolver() -> DnsResolver {
-    DnsResolver::default()
-}
+impl PeerConnection {
+    fn new(conn: Connection, protocol: PeerProtocol, generation: u64) -> Self {
+        PeerConnection {
+            conn,
+            protocol,
+            generation,
+            connected_at: std::time::SystemTime::now(),
+            open_streams: Arc::new(AtomicUsize::new(0)),
+            rejected_requests: Arc::new(AtomicU64::new(0)),
+            session: Arc::new(std::sync::RwLock::new(None)),
+        }
+    }
 
-/// Main event loop that runs in a background thread
-async fn run_event_loop(
-    secret_key: SecretKey,
-    config: HostConfig,
-    mut cmd_rx: mpsc::Receiver<Command>,
+    fn session(&self) -> Option<Session> {
+        self.session.read().unwrap().clone()
+    }
+
+    /// Count a stream as open until the returned guard is dropped
+    fn open_stream(&self) -> OpenStream {
+        self.open_streams.fetch_add(1, Ordering::Relaxed);
+        OpenStream(self.open_streams.clone())
+    }
+
+    fn info(&self, node_id: &str) -> PeerInfo {
+        let paths = self.conn.paths().get();
+        let remote_addrs = paths
+            .iter()
+            .map(|path| transport_addr_to_string(path.remote_addr()))
+            .collect();
+        let rtt_ms = paths
+            .iter()
+            .find(|path| path.is_selected())
+            .map(|path| path.rtt().as_millis() as u64);
+        let stats = self.conn.stats();
+
+        PeerInfo {
+            node_id: node_id.to_string(),
+            connection_type: PeerConnectionType::from_connection(&self.conn),
+            remote_addrs,
+            rtt_ms,
+            bytes_sent: stats.udp_tx.bytes,
+            bytes_received: stats.udp_rx.bytes,
+            connected_since: self
+                .connected_at
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            open_streams: self.open_streams.load(Ordering::Relaxed),
+            rejected_requests: self.rejected_requests.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Format a path's remote address for display
+fn transport_addr_to_string(addr: &TransportAddr) -> String {
+    match addr {
+        TransportAddr::Ip(addr) => addr.to_string(),
+        TransportAddr::Relay(url) => url.to_string(),
+        other => format!("{:?}", other),
+    }
+}
+
+/// Guard for a stream counted in `PeerConnection::open_streams`
+struct OpenStream(Arc<AtomicUsize>);
+
+impl Drop for OpenStream {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// An HLS stream waiting on the application to write to it
+struct HlsStream {
+    send: SendStream,
+    /// When the stream was opened or last written to
+    last_activity: tokio::time::Instant,
+    _open_stream: OpenStream,
+}
+
+impl HlsStream {
+    fn new(send: SendStream, open_stream: OpenStream) -> Self {
+        HlsStream {
+            send,
+            last_activity: tokio::time::Instant::now(),
+            _open_stream: open_stream,
+        }
+    }
+}
+
+/// What connection and relay tasks report back to the event loop
+enum ConnectionEvent {
+    /// An incoming connection completed the handshake
+    Accepted {
+        peer_id: String,
+        conn: Connection,
+        protocol: PeerProtocol,
+    },
+    /// A connection's accept loop ended, with the reason the peer gave if
+    /// it closed the connection on purpose
+    Closed {
+        peer_id: String,
+        generation: u64,
+        reason: Option<DisconnectReason>,
+    },
+    /// A reconnect task re-established a connection
+    Redialed {
+        peer_id: String,
+        conn: Connection,
+        protocol: PeerProtocol,
+        attempts: u32,
+    },
+    /// A reconnect task ran out of attempts
+    ReconnectFailed { peer_id: String },
+    /// Whether the endpoint currently has a home relay. The first report
+    /// comes once the relay connected or the online timeout passed.
+    RelayStatus { connected: bool },
+    /// A round of relay probes finished: each relay's latency, or why its
+    /// probe failed
+    RelayProbed {
+        results: Vec<(String, Result<std::time::Duration, String>)>,
+    },
+    /// A connection came up or its connection type changed, with the
+    /// remote addresses of its current paths
+    PathsChanged {
+        peer_id: String,
+        connection_type: PeerConnectionType,
+        remote_addrs: Vec<TransportAddr>,
+    },
+}
+
+/// Everything a connection's background tasks need from the event loop
+#[derive(Clone)]
+struct ConnectionContext {
+    /// This host's node id
+    local_id: String,
+    max_message_size: usize,
     event_tx: mpsc::Sender<Event>,
-) {
-    // Initialize tracing to forward logs to Elixir
-    init_tracing(event_tx.clone());
+    shared_state: Arc<Mutex<SharedState>>,
+    internal_tx: mpsc::UnboundedSender<ConnectionEvent>,
+    access: Arc<AccessControl>,
+    request_limiter: Arc<RequestLimiter>,
+    /// Incoming requests refused since the host started
+    rejected_requests: Arc<AtomicU64>,
+    /// Generation of the next registered connection
+    next_generation: Arc<AtomicU64>,
+}
+
+/// A client request on its way to a peer
+enum ClientRequest {
+    Request {
+        request: MydiaRequest,
+        cancel_rx: oneshot::Receiver<()>,
+        reply: oneshot::Sender<Result<MydiaResponse, P2pError>>,
+    },
+    Hls {
+        request: HlsRequest,
+        cancel_rx: oneshot::Receiver<()>,
+        reply: oneshot::Sender<Result<HlsStreamResponse, P2pError>>,
+    },
+}
+
+impl ClientRequest {
+    /// Run the exchange off the event loop so slow responses don't block other commands
+    fn dispatch(self, peer: PeerConnection, max_message_size: usize) {
+        match self {
+            ClientRequest::Request {
+                request,
+                cancel_rx,
+                reply,
+            } => {
+                tokio::spawn(async move {
+                    let result =
+                        handle_send_request(peer, request, max_message_size, cancel_rx).await;
+                    let _ = reply.send(result);
+                });
+            }
+            ClientRequest::Hls {
+                request,
+                cancel_rx,
+                reply,
+            } => {
+                tokio::spawn(async move {
+                    let result = handle_send_hls_request(peer, request, cancel_rx).await;
+                    let _ = reply.send(result);
+                });
+            }
+        }
+    }
 
-    // Build the endpoint
+    fn fail(self, error: P2pError) {
+        match self {
+            ClientRequest::Request { reply, .. } => {
+                let _ = reply.send(Err(error));
+            }
+            ClientRequest::Hls { reply, .. } => {
+                let _ = reply.send(Err(error));
+            }
+        }
+    }
+}
+
+/// A dropped peer that is being redialed
+struct Reconnect {
+    task: tokio::task::AbortHandle,
+    /// Requests held until the peer is back (if the policy queues them)
+    queued: Vec<ClientRequest>,
+}
+
+impl Reconnect {
+    /// Stop redialing and fail the held requests
+    fn abort(self, error: P2pError) {
+        self.task.abort();
+        for request in self.queued {
+            request.fail(error.clone());
+        }
+    }
+
+    /// The peer is connected again: stop redialing and send the held requests
+    fn resume(self, peer: &PeerConnection, max_message_size: usize) {
+        self.task.abort();
+        for request in self.queued {
+            request.dispatch(peer.clone(), max_message_size);
+        }
+    }
+}
+
+/// Shared state for pending responses
+struct SharedState {
+    pending_responses: HashMap<String, oneshot::Sender<MydiaResponse>>,
+    /// Active HLS streaming connections - stores the send half of the stream
+    hls_streams: HashMap<String, HlsStream>,
+    /// Idle timeout for entries in hls_streams
+    hls_stream_ttl: std::time::Duration,
+}
+
+impl SharedState {
+    /// Requests and HLS streams waiting on the application
+    fn pending(&self) -> usize {
+        self.pending_responses.len() + self.hls_streams.len()
+    }
+}
+
+/// Create a DNS resolver using the system default.
+fn create_dns_resolver() -> DnsResolver {
+    DnsResolver::default()
+}
+
+/// Build and bind the iroh endpoint described by the config
+async fn bind_endpoint(
+    secret_key: SecretKey,
+    config: &HostConfig,
+    address_book: &MemoryLookup,
+) -> Result<Endpoint, P2pError> {
     let mut builder = Endpoint::builder()
         .secret_key(secret_key)
-        .alpns(vec![ALPN.to_vec()])
+        .alpns(protocol::supported_alpns())
         .dns_resolver(create_dns_resolver());
 
+    // Let QUIC hold back streams over the limit instead of opening them
+    let max_streams =
+        u32::try_from(config.request_limits.max_concurrent_streams).unwrap_or(u32::MAX);
+    builder = builder.transport_config(
+        QuicTransportConfig::builder()
+            .max_concurrent_bidi_streams(VarInt::from_u32(max_streams))
+            .build(),
+    );
+
     // Configure relay
-    if let Some(relay_url) = &config.relay_url {
-        if let Ok(url) = relay_url.parse::<RelayUrl>() {
-            // Custom relay with QUIC enabled
-            let custom = RelayConfig {
-                url,
-                quic: Some(RelayQuicConfig::default()),
-            };
-            // Combine with iroh's default production relays for fallback
-            let relay_map = RelayMap::from_iter([
-                custom,
-                default_relays::default_na_east_relay(),
-                default_relays::default_eu_relay(),
-                default_relays::default_ap_relay(),
-            ]);
-            builder = builder.relay_mode(RelayMode::Custom(relay_map));
-        }
-    }
-
-    // Configure bind port
-    if let Some(port) = config.bind_port {
-        if port > 0 {
-            match builder.bind_addr(std::net::SocketAddrV4::new(
-                std::net::Ipv4Addr::UNSPECIFIED,
-                port,
-            )) {
-                Ok(b) => builder = b,
-                Err(e) => {
-                    tracing::error!("Failed to set bind address: {}", e);
-                    return;
-                }
-            }
-        }
-    }
+    builder = builder.relay_mode(config.relay.relay_mode()?);
+
+    // Configure bind addresses
+    builder = bind::configure(builder, config)?;
+
+    // Configure where peer addresses are looked up
+    builder = discovery::configure(builder, config, address_book)?;
+
+    builder
+        .bind()
+        .await
+        .map_err(|e| P2pError::Bind(e.to_string()))
+}
+
+/// Main event loop that runs in a background thread.
+/// `started` is told whether startup succeeded once the endpoint is bound,
+/// or once `Ready` is emitted if `config.wait_for_ready` is set.
+async fn run_event_loop(
+    secret_key: SecretKey,
+    config: HostConfig,
+    mut cmd_rx: mpsc::Receiver<Command>,
+    event_tx: mpsc::Sender<Event>,
+    started: oneshot::Sender<Result<(), P2pError>>,
+) {
+    // Forward logs to Elixir while the host runs
+    let _log_sink = LogSink::install(event_tx.clone());
 
-    let endpoint = match builder.bind().await {
+    let mut address_book = match AddressBook::open(&config) {
+        Ok(book) => book,
+        Err(e) => {
+            tracing::error!("Failed to start iroh endpoint: {}", e);
+            let _ = started.send(Err(e));
+            return;
+        }
+    };
+    let access = match AccessControl::new(&config.connection_policy) {
+        Ok(access) => Arc::new(access),
+        Err(e) => {
+            tracing::error!("Failed to start iroh endpoint: {}", e);
+            let _ = started.send(Err(e));
+            return;
+        }
+    };
+    let endpoint = match bind_endpoint(secret_key, &config, address_book.lookup()).await {
         Ok(ep) => ep,
         Err(e) => {
-            tracing::error!("Failed to bind iroh endpoint: {}", e);
+            tracing::error!("Failed to start iroh endpoint: {}", e);
+            let _ = started.send(Err(e));
+            return;
+        }
+    };
+    let discovery_task = match discovery::start(&endpoint, config.local_discovery, event_tx.clone())
+    {
+        Ok(task) => task,
+        Err(e) => {
+            tracing::error!("Failed to start iroh endpoint: {}", e);
+            endpoint.close().await;
+            let _ = started.send(Err(e));
             return;
         }
     };
+    let mut started = if config.wait_for_ready {
+        Some(started)
+    } else {
+        let _ = started.send(Ok(()));
+        None
+    };
 
     let endpoint_id = endpoint.id();
     tracing::info!("Iroh endpoint bound, endpoint_id: {}", endpoint_id);
 
     // Track state
-    let mut connected_peers: HashMap<String, Connection> = HashMap::new();
+    let max_message_size = config.max_message_size.unwrap_or(DEFAULT_MAX_MESSAGE_SIZE);
+    let mut connected_peers: HashMap<String, PeerConnection> = HashMap::new();
     let shared_state = Arc::new(Mutex::new(SharedState {
         pending_responses: HashMap::new(),
         hls_streams: HashMap::new(),
+        hls_stream_ttl: config.hls_stream_ttl.unwrap_or(DEFAULT_HLS_STREAM_TTL),
     }));
     let mut relay_connected = false;
-
-    // Wait for endpoint to be online (relay connected + local IP available)
-    // Use a timeout to avoid blocking indefinitely if relay is unreachable
-    tracing::info!("Waiting for relay connection...");
-    match tokio::time::timeout(std::time::Duration::from_secs(30), endpoint.online()).await {
-        Ok(()) => {
-            relay_connected = true;
-            tracing::info!("Relay connection established");
-            let _ = event_tx.send(Event::RelayConnected).await;
-        }
-        Err(_) => {
-            tracing::warn!("Relay connection timed out after 30s - continuing without relay");
-        }
-    }
-
-    // Get endpoint address and emit Ready event
-    let addr = endpoint.addr();
-    let addr_json = endpoint_addr_to_json(&addr);
-    let _ = event_tx
-        .send(Event::Ready {
-            node_addr: addr_json,
-        })
-        .await;
+    let mut relay_health = RelayHealthTracker::new(config.relay.relay_urls());
+    let (internal_tx, mut internal_rx) = mpsc::unbounded_channel();
+    let ctx = ConnectionContext {
+        local_id: endpoint_id.to_string(),
+        max_message_size,
+        event_tx: event_tx.clone(),
+        shared_state: shared_state.clone(),
+        internal_tx,
+        access: access.clone(),
+        request_limiter: Arc::new(RequestLimiter::new(config.request_limits.clone())),
+        rejected_requests: Arc::new(AtomicU64::new(0)),
+        next_generation: Arc::new(AtomicU64::new(0)),
+    };
+    // Last address used to dial each peer, for reconnecting
+    let mut dialed_addrs: HashMap<String, EndpointAddr> = HashMap::new();
+    let mut reconnecting: HashMap<String, Reconnect> = HashMap::new();
+    // Set once shutdown starts: when to stop waiting for in-flight streams,
+    // and who to tell once the endpoint is closed
+    let mut shutdown: Option<(tokio::time::Instant, Vec<oneshot::Sender<()>>)> = None;
+    // Still waiting for the first relay status, which triggers `Ready`
+    let mut ready = false;
+
+    // Track the relay in the background so direct connections are served right away
+    let relay_task = tokio::spawn(monitor_relay(
+        endpoint.clone(),
+        config
+            .relay
+            .is_enabled()
+            .then(|| config.online_timeout.unwrap_or(DEFAULT_ONLINE_TIMEOUT)),
+        ctx.internal_tx.clone(),
+    ));
+    let probe_task = config.relay.is_enabled().then(|| {
+        tokio::spawn(probe_relays(
+            config.relay.relay_urls(),
+            config
+                .relay_probe_interval
+                .unwrap_or(DEFAULT_RELAY_PROBE_INTERVAL),
+            ctx.internal_tx.clone(),
+        ))
+    });
 
     loop {
         tokio::select! {
             // Handle incoming connections
-            Some(incoming) = endpoint.accept() => {
-                // Accept the connection
-                let accepting = match incoming.accept() {
-                    Ok(accepting) => accepting,
-                    Err(e) => {
-                        tracing::warn!("Failed to accept connection: {}", e);
-                        continue;
-                    }
-                };
+            Some(incoming) = endpoint.accept(), if shutdown.is_none() => {
+                // Handshake in the background so a slow peer can't hold up the loop
+                tokio::spawn(accept_peer(incoming, ctx.clone()));
+            }
 
-                // Check ALPN
-                let mut accepting = accepting;
-                let alpn = match accepting.alpn().await {
-                    Ok(alpn) => alpn,
-                    Err(e) => {
-                        tracing::warn!("Failed to get ALPN: {}", e);
-                        continue;
+            // Handle closed and re-established connections
+            Some(conn_event) = internal_rx.recv() => {
+                match conn_event {
+                    ConnectionEvent::Accepted { peer_id, conn, protocol } => {
+                        if shutdown.is_some() {
+                            conn.close(0u32.into(), b"shutting down");
+                            continue;
+                        }
+                        tracing::info!("Peer connected: {} (protocol {})", peer_id, protocol.version);
+                        let peer = register_peer(&peer_id, conn, protocol, &mut connected_peers, &ctx).await;
+                        // The peer came back on its own while being redialed
+                        if let Some(reconnect) = reconnecting.remove(&peer_id) {
+                            reconnect.resume(&peer, max_message_size);
+                        }
                     }
-                };
-
-                if alpn.as_slice() != ALPN {
-                    tracing::warn!("Unknown ALPN: {:?}", alpn);
-                    continue;
-                }
-
-                // Complete the connection
-                let conn = match accepting.await {
-                    Ok(conn) => conn,
-                    Err(e) => {
-                        tracing::warn!("Connection failed: {}", e);
-                        continue;
+                    ConnectionEvent::Closed { peer_id, generation, reason } => {
+                        // A newer connection to the same peer replaced this one
+                        if connected_peers.get(&peer_id).is_some_and(|peer| peer.generation != generation) {
+                            tracing::debug!("Superseded connection to {} closed", peer_id);
+                            continue;
+                        }
+                        connected_peers.remove(&peer_id);
+                        // Redialing a peer that dropped us on purpose would only be refused
+                        if let Some(reason) = reason {
+                            tracing::info!("{} closed the connection: {}", peer_id, reason);
+                            dialed_addrs.remove(&peer_id);
+                            let _ = event_tx.send(Event::PeerClosed { peer_id: peer_id.clone(), reason }).await;
+                        }
+                        match (&config.reconnect, dialed_addrs.get(&peer_id)) {
+                            (Some(policy), Some(addr)) if !reconnecting.contains_key(&peer_id) => {
+                                tracing::info!("Connection to {} lost, reconnecting", peer_id);
+                                let task = tokio::spawn(reconnect_peer(
+                                    endpoint.clone(),
+                                    peer_id.clone(),
+                                    addr.clone(),
+                                    policy.clone(),
+                                    ctx.clone(),
+                                ));
+                                reconnecting.insert(peer_id, Reconnect {
+                                    task: task.abort_handle(),
+                                    queued: Vec::new(),
+                                });
+                            }
+                            _ => {
+                                let _ = event_tx.send(Event::Disconnected(peer_id)).await;
+                            }
+                        }
                     }
-                };
-
-                let peer_id = conn.remote_id().to_string();
-                let connection_type = PeerConnectionType::from_connection(&conn);
-                tracing::info!("Peer connected: {} ({:?})", peer_id, connection_type);
+                    ConnectionEvent::Redialed { peer_id, conn, protocol, attempts } => {
+                        let Some(reconnect) = reconnecting.remove(&peer_id) else {
+                            // Dialed manually in the meantime
+                            conn.close(0u32.into(), b"duplicate connection");
+                            continue;
+                        };
+                        tracing::info!("Reconnected to {} after {} attempt(s)", peer_id, attempts);
+                        let peer = register_peer(&peer_id, conn, protocol, &mut connected_peers, &ctx).await;
+                        let _ = event_tx.send(Event::Reconnected { peer_id, attempts }).await;
+                        reconnect.resume(&peer, max_message_size);
+                    }
+                    ConnectionEvent::ReconnectFailed { peer_id } => {
+                        tracing::warn!("Giving up reconnecting to {}", peer_id);
+                        if let Some(reconnect) = reconnecting.remove(&peer_id) {
+                            for request in reconnect.queued {
+                                request.fail(P2pError::NotConnected { peer_id: peer_id.clone() });
+                            }
+                        }
+                        dialed_addrs.remove(&peer_id);
+                        let _ = event_tx.send(Event::Disconnected(peer_id)).await;
+                    }
+                    ConnectionEvent::RelayProbed { results } => {
+                        relay_health.update(results);
+                    }
+                    ConnectionEvent::PathsChanged { peer_id, connection_type, remote_addrs } => {
+                        address_book.record_connection(&peer_id, connection_type, &remote_addrs);
+                    }
+                    ConnectionEvent::RelayStatus { connected } => {
+                        if connected != relay_connected {
+                            relay_connected = connected;
+                            if connected {
+                                tracing::info!("Relay connection established");
+                                let _ = event_tx.send(Event::RelayConnected).await;
+                            } else {
+                                tracing::warn!("Relay connection lost");
+                                let _ = event_tx.send(Event::RelayDisconnected).await;
+                            }
+                        }
+                        if !ready {
+                            ready = true;
+                            let addr_json = endpoint_addr_to_json(&endpoint.addr());
+                            let _ = event_tx.send(Event::Ready { node_addr: addr_json }).await;
+                            if let Some(started) = started.take() {
+                                let _ = started.send(Ok(()));
+                            }
+                        }
+                    }
+                }
+            }
 
-                connected_peers.insert(peer_id.clone(), conn.clone());
-                let _ = event_tx.send(Event::Connected {
-                    peer_id: peer_id.clone(),
-                    connection_type,
-                }).await;
-
-                // Spawn a task to handle incoming streams from this peer
-                let event_tx_clone = event_tx.clone();
-                let peer_id_clone = peer_id.clone();
-                let shared_state_clone = shared_state.clone();
-                let conn_clone = conn.clone();
-                tokio::spawn(async move {
-                    handle_connection(conn_clone, peer_id_clone, event_tx_clone, shared_state_clone).await;
-                });
+            // Write the address book once changes have had time to settle
+            _ = tokio::time::sleep_until(address_book.save_deadline().unwrap_or_else(tokio::time::Instant::now)), if address_book.save_deadline().is_some() => {
+                address_book.save();
+            }
 
-                // Monitor connection type changes (relay -> direct)
-                let monitor_tx = event_tx.clone();
-                tokio::spawn(async move {
-                    monitor_connection_type(conn, peer_id, monitor_tx).await;
-                });
+            // While shutting down, wait for in-flight streams to finish
+            _ = tokio::time::sleep(SHUTDOWN_POLL_INTERVAL), if shutdown.is_some() => {
+                let drained = connected_peers
+                    .values()
+                    .all(|peer| peer.open_streams.load(Ordering::Relaxed) == 0);
+                let deadline_passed = shutdown
+                    .as_ref()
+                    .is_some_and(|(deadline, _)| tokio::time::Instant::now() >= *deadline);
+                if drained || deadline_passed {
+                    break;
+                }
             }
 
             // Handle commands
             Some(cmd) = cmd_rx.recv() => {
                 match cmd {
+                    Command::Shutdown { grace, reply } => match &mut shutdown {
+                        Some((_, replies)) => replies.push(reply),
+                        None => {
+                            tracing::info!("Shutting down, waiting up to {:?} for in-flight streams", grace);
+                            shutdown = Some((tokio::time::Instant::now() + grace, vec![reply]));
+                        }
+                    },
+                    // No new outgoing work once shutdown has started
+                    Command::Dial { reply, .. } if shutdown.is_some() => {
+                        let _ = reply.send(Err(P2pError::Shutdown));
+                    }
+                    Command::SendRequest { reply, .. } if shutdown.is_some() => {
+                        let _ = reply.send(Err(P2pError::Shutdown));
+                    }
+                    Command::SendHlsRequest { reply, .. } if shutdown.is_some() => {
+                        let _ = reply.send(Err(P2pError::Shutdown));
+                    }
                     Command::Dial { endpoint_addr_json, reply } => {
-                        let result = handle_dial(&endpoint, &endpoint_addr_json, &mut connected_peers, &event_tx, &shared_state).await;
+                        let result = handle_dial(&endpoint, &endpoint_addr_json, &mut address_book, &mut connected_peers, &ctx).await;
+                        let result = result.map(|(node_id, addr)| {
+                            if let Some(reconnect) = reconnecting.remove(&node_id) {
+                                reconnect.resume(&connected_peers[&node_id], max_message_size);
+                            }
+                            dialed_addrs.insert(node_id, addr);
+                        });
                         let _ = reply.send(result);
                     }
-                    Command::SendRequest { node_id, request, reply } => {
-                        let result = handle_send_request(&connected_peers, &node_id, request).await;
+                    Command::AddPeerAddr { endpoint_addr_json, reply } => {
+                        let result = endpoint_addr_from_json(&endpoint_addr_json)
+                            .map(|addr| address_book.add(&addr));
                         let _ = reply.send(result);
                     }
+                    Command::AllowPeer { node_id, reply } => {
+                        let result = access.allow(&node_id).map(|added| {
+                            if added {
+                                tracing::info!("Allowed paired peer {}", node_id);
+                            }
+                        });
+                        let _ = reply.send(result);
+                    }
+                    Command::RevokePeer { node_id, reply } => {
+                        let result = access.revoke(&node_id).map(|removed| {
+                            if removed {
+                                tracing::info!("Revoked peer {}", node_id);
+                            }
+                        });
+                        let _ = reply.send(result);
+                    }
+                    Command::RevokeSession { node_id, reply } => {
+                        let node_id = resolve_node_id(&node_id);
+                        let revoked = match connected_peers.get(&node_id) {
+                            Some(peer) => {
+                                let session = peer.session.write().unwrap().take();
+                                if let Some(session) = &session {
+                                    tracing::info!("Revoked session of {} ({})", node_id, session.principal);
+                                    DisconnectReason::SessionRevoked.close(&peer.conn);
+                                }
+                                session.is_some()
+                            }
+                            None => false,
+                        };
+                        let _ = reply.send(revoked);
+                    }
+                    Command::DisconnectPeer { node_id, reason, reply } => {
+                        let node_id = resolve_node_id(&node_id);
+                        let closed = disconnect_peer(&node_id, reason, &connected_peers, &mut dialed_addrs, &mut reconnecting, &event_tx).await;
+                        let _ = reply.send(closed);
+                    }
+                    Command::BanPeer { node_id, duration, reply } => {
+                        let result = access.ban(&node_id, duration);
+                        if let Ok(node_id) = &result {
+                            tracing::info!("Banned {} for {:?}", node_id, duration);
+                            disconnect_peer(node_id, DisconnectReason::Banned, &connected_peers, &mut dialed_addrs, &mut reconnecting, &event_tx).await;
+                        }
+                        let _ = reply.send(result.map(|_| ()));
+                    }
+                    Command::UnbanPeer { node_id, reply } => {
+                        let _ = reply.send(access.unban(&node_id));
+                    }
+                    Command::SendRequest { node_id, request, cancel_rx, reply } => {
+                        let request = ClientRequest::Request { request, cancel_rx, reply };
+                        route_request(&connected_peers, &mut reconnecting, config.reconnect.as_ref(), &node_id, request, max_message_size);
+                    }
                     Command::SendResponse { request_id, response } => {
                         let mut state = shared_state.lock().await;
                         if let Some(tx) = state.pending_responses.remove(&request_id) {
@@ -837,13 +1842,26 @@ async fn run_event_loop(
                         let addr_json = endpoint_addr_to_json(&addr);
                         let _ = reply.send(addr_json);
                     }
+                    Command::ListPeers { reply } => {
+                        let peers = connected_peers
+                            .iter()
+                            .filter(|(_, peer)| peer.conn.close_reason().is_none())
+                            .map(|(node_id, peer)| peer.info(node_id))
+                            .collect();
+                        let _ = reply.send(peers);
+                    }
+                    Command::GetRelayHealth { reply } => {
+                        let home = endpoint.addr().relay_urls().next().map(|url| url.to_string());
+                        let _ = reply.send(relay_health.report(home.as_deref()));
+                    }
                     Command::GetNetworkStats { reply } => {
                         // Get the actual relay URL from the endpoint address
                         let addr = endpoint.addr();
                         let relay_url = addr.relay_urls().next().map(|u| u.to_string());
 
                         // Get connection type for the first connected peer
-                        let peer_connection_type = if let Some((peer_key, conn)) = connected_peers.iter().next() {
+                        let peer_connection_type = if let Some((peer_key, peer)) = connected_peers.iter().next() {
+                            let conn = &peer.conn;
                             let peer_id = conn.remote_id();
                             tracing::info!("GetNetworkStats: checking paths for peer {} (key={})", peer_id, peer_key);
                             let ct = PeerConnectionType::from_connection(conn);
@@ -860,14 +1878,19 @@ async fn run_event_loop(
                             connected_peers: connected_peers.len(),
                             relay_connected,
                             relay_url,
+                            relay_policy: config.relay.clone(),
+                            bound_addrs: endpoint.bound_sockets().iter().map(|addr| addr.to_string()).collect(),
                             peer_connection_type,
+                            rejected_requests: ctx.rejected_requests.load(Ordering::Relaxed),
                         };
                         let _ = reply.send(stats);
                     }
                     Command::SendHlsHeader { stream_id, header, reply } => {
                         let result = {
                             let mut state = shared_state.lock().await;
-                            if let Some(send) = state.hls_streams.get_mut(&stream_id) {
+                            if let Some(stream) = state.hls_streams.get_mut(&stream_id) {
+                                stream.last_activity = tokio::time::Instant::now();
+                                let send = &mut stream.send;
                                 // First write the HlsHeader response
                                 let header_response = MydiaResponse::HlsHeader(header);
                                 match serde_cbor::to_vec(&header_response) {
@@ -876,17 +1899,17 @@ async fn run_event_loop(
                                         let len = header_data.len() as u32;
                                         let len_bytes = len.to_be_bytes();
                                         if let Err(e) = send.write_all(&len_bytes).await {
-                                            Err(format!("Failed to write header length: {}", e))
+                                            Err(P2pError::Io(format!("Failed to write header length: {}", e)))
                                         } else if let Err(e) = send.write_all(&header_data).await {
-                                            Err(format!("Failed to write header: {}", e))
+                                            Err(P2pError::Io(format!("Failed to write header: {}", e)))
                                         } else {
                                             Ok(())
                                         }
                                     }
-                                    Err(e) => Err(format!("Failed to encode header: {}", e)),
+                                    Err(e) => Err(P2pError::Protocol(format!("Failed to encode header: {}", e))),
                                 }
                             } else {
-                                Err(format!("HLS stream not found: {}", stream_id))
+                                Err(P2pError::StreamNotFound(stream_id))
                             }
                         };
                         let _ = reply.send(result);
@@ -894,19 +1917,21 @@ async fn run_event_loop(
                     Command::SendHlsChunk { stream_id, data, reply } => {
                         let result = {
                             let mut state = shared_state.lock().await;
-                            if let Some(send) = state.hls_streams.get_mut(&stream_id) {
+                            if let Some(stream) = state.hls_streams.get_mut(&stream_id) {
+                                stream.last_activity = tokio::time::Instant::now();
+                                let send = &mut stream.send;
                                 // Write chunk length (4 bytes) then data
                                 let len = data.len() as u32;
                                 let len_bytes = len.to_be_bytes();
                                 if let Err(e) = send.write_all(&len_bytes).await {
-                                    Err(format!("Failed to write chunk length: {}", e))
+                                    Err(P2pError::Io(format!("Failed to write chunk length: {}", e)))
                                 } else if let Err(e) = send.write_all(&data).await {
-                                    Err(format!("Failed to write chunk: {}", e))
+                                    Err(P2pError::Io(format!("Failed to write chunk: {}", e)))
                                 } else {
                                     Ok(())
                                 }
                             } else {
-                                Err(format!("HLS stream not found: {}", stream_id))
+                                Err(P2pError::StreamNotFound(stream_id))
                             }
                         };
                         let _ = reply.send(result);
@@ -914,19 +1939,19 @@ async fn run_event_loop(
                     Command::FinishHlsStream { stream_id, reply } => {
                         let result = {
                             let mut state = shared_state.lock().await;
-                            if let Some(mut send) = state.hls_streams.remove(&stream_id) {
+                            if let Some(HlsStream { mut send, .. }) = state.hls_streams.remove(&stream_id) {
                                 // Write zero-length terminator
                                 let zero_bytes = [0u8; 4];
                                 if let Err(e) = send.write_all(&zero_bytes).await {
-                                    Err(format!("Failed to write terminator: {}", e))
+                                    Err(P2pError::Io(format!("Failed to write terminator: {}", e)))
                                 } else if let Err(e) = send.finish() {
-                                    Err(format!("Failed to finish stream: {}", e))
+                                    Err(P2pError::Io(format!("Failed to finish stream: {}", e)))
                                 } else {
                                     tracing::debug!("HLS stream {} finished", stream_id);
                                     Ok(())
                                 }
                             } else {
-                                Err(format!("HLS stream not found: {}", stream_id))
+                                Err(P2pError::StreamNotFound(stream_id))
                             }
                         };
                         let _ = reply.send(result);
@@ -938,21 +1963,22 @@ async fn run_event_loop(
                             state.hls_streams.remove(&stream_id)
                         };
                         match send_stream {
-                            Some(send) => {
+                            Some(HlsStream { send, _open_stream: open_stream, .. }) => {
                                 // Spawn a task to stream the file data
                                 tokio::spawn(async move {
+                                    let _open_stream = open_stream;
                                     let result = stream_file_to_quic(send, &file_path, offset, length).await;
                                     let _ = reply.send(result);
                                 });
                             }
                             None => {
-                                let _ = reply.send(Err(format!("HLS stream not found: {}", stream_id)));
+                                let _ = reply.send(Err(P2pError::StreamNotFound(stream_id)));
                             }
                         }
                     }
-                    Command::SendHlsRequest { node_id, request, reply } => {
-                        let result = handle_send_hls_request(&connected_peers, &node_id, request).await;
-                        let _ = reply.send(result);
+                    Command::SendHlsRequest { node_id, request, cancel_rx, reply } => {
+                        let request = ClientRequest::Hls { request, cancel_rx, reply };
+                        route_request(&connected_peers, &mut reconnecting, config.reconnect.as_ref(), &node_id, request, max_message_size);
                     }
                 }
             }
@@ -961,69 +1987,358 @@ async fn run_event_loop(
         }
     }
 
+    // Tear down: stop redialing, relay tracking and discovery, close every connection, then the endpoint,
+    // and write out the address book
+    relay_task.abort();
+    if let Some(task) = probe_task {
+        task.abort();
+    }
+    if let Some(task) = discovery_task {
+        task.abort();
+    }
+    for (_, reconnect) in reconnecting.drain() {
+        reconnect.abort(P2pError::Shutdown);
+    }
+    for (peer_id, peer) in connected_peers.drain() {
+        peer.conn.close(0u32.into(), b"shutdown");
+        // Nobody may be reading events any more, so don't wait for room
+        let _ = event_tx.try_send(Event::Disconnected(peer_id));
+    }
+    endpoint.close().await;
+    address_book.flush().await;
+
+    if let Some((_, replies)) = shutdown {
+        for reply in replies {
+            let _ = reply.send(());
+        }
+    }
+
     tracing::info!("Event loop terminated");
 }
 
-/// Handle dialing a peer
+/// Handle dialing a peer. Returns the peer's node id and the address used.
 async fn handle_dial(
     endpoint: &Endpoint,
     endpoint_addr_json: &str,
-    connected_peers: &mut HashMap<String, Connection>,
-    event_tx: &mpsc::Sender<Event>,
-    shared_state: &Arc<Mutex<SharedState>>,
-) -> Result<(), String> {
-    let endpoint_addr = endpoint_addr_from_json(endpoint_addr_json)?;
+    address_book: &mut AddressBook,
+    connected_peers: &mut HashMap<String, PeerConnection>,
+    ctx: &ConnectionContext,
+) -> Result<(String, EndpointAddr), P2pError> {
+    let given_addr = parse_dial_addr(endpoint_addr_json)?;
+    // Also try wherever the peer was last reached
+    let endpoint_addr = address_book.merge_into(given_addr.clone());
     let endpoint_id: EndpointId = endpoint_addr.id;
     let node_id = endpoint_id.to_string();
 
     tracing::info!("Dialing peer: {}", node_id);
 
+    let (conn, protocol) = connect_peer(endpoint, endpoint_addr.clone()).await?;
+    tracing::info!(
+        "Connected to peer: {} (protocol {})",
+        node_id,
+        protocol.version
+    );
+    address_book.add(&given_addr);
+    register_peer(&node_id, conn, protocol, connected_peers, ctx).await;
+
+    Ok((node_id, endpoint_addr))
+}
+
+/// Open a connection to a peer and run the protocol handshake
+async fn connect_peer(
+    endpoint: &Endpoint,
+    endpoint_addr: EndpointAddr,
+) -> Result<(Connection, PeerProtocol), P2pError> {
+    // Offer every supported version; the acceptor picks the one it prefers
+    let older_alpns = SUPPORTED_VERSIONS
+        .iter()
+        .filter(|v| **v != ProtocolVersion::CURRENT)
+        .map(|v| v.alpn())
+        .collect();
     let conn = endpoint
-        .connect(endpoint_addr, ALPN)
+        .connect_with_opts(
+            endpoint_addr,
+            &ProtocolVersion::CURRENT.alpn(),
+            ConnectOptions::new().with_additional_alpns(older_alpns),
+        )
+        .await
+        .map_err(|e| P2pError::Connect(e.to_string()))?
         .await
-        .map_err(|e| format!("Failed to connect: {}", e))?;
+        .map_err(|e| P2pError::Connect(e.to_string()))?;
 
-    let connection_type = PeerConnectionType::from_connection(&conn);
-    tracing::info!("Connected to peer: {} ({:?})", node_id, connection_type);
+    match protocol::client_handshake(&conn).await {
+        Ok(protocol) => Ok((conn, protocol)),
+        Err(e) => {
+            // A peer that bans us closes the connection before the handshake
+            if let Some(reason) = conn
+                .close_reason()
+                .as_ref()
+                .and_then(DisconnectReason::from_close)
+            {
+                return Err(P2pError::Refused(reason));
+            }
+            conn.close(0u32.into(), b"handshake failed");
+            Err(e)
+        }
+    }
+}
+
+/// Whether a new connection to a peer should replace the live one.
+///
+/// A reconnect from the same side always wins. When both sides dialed each
+/// other, both keep the connection dialed by the lower node id so they agree
+/// on which one survives.
+fn replaces_existing(local_id: &str, peer_id: &str, existing: Side, new: Side) -> bool {
+    if existing == new {
+        return true;
+    }
+    let dialed_by_lower = if local_id < peer_id {
+        Side::Client
+    } else {
+        Side::Server
+    };
+    new == dialed_by_lower
+}
 
-    connected_peers.insert(node_id.clone(), conn.clone());
-    let _ = event_tx.send(Event::Connected {
-        peer_id: node_id.clone(),
-        connection_type,
-    }).await;
+/// Track a newly established connection, emit `Connected` and start the tasks
+/// serving it. If the peer already has a live connection, only one of the two
+/// is kept and the returned PeerConnection is the survivor.
+async fn register_peer(
+    peer_id: &str,
+    conn: Connection,
+    protocol: PeerProtocol,
+    connected_peers: &mut HashMap<String, PeerConnection>,
+    ctx: &ConnectionContext,
+) -> PeerConnection {
+    if let Some(existing) = connected_peers.get(peer_id) {
+        if existing.conn.close_reason().is_none() {
+            if !replaces_existing(&ctx.local_id, peer_id, existing.conn.side(), conn.side()) {
+                tracing::info!(
+                    "Keeping existing connection to {}, closing duplicate",
+                    peer_id
+                );
+                conn.close(0u32.into(), b"duplicate connection");
+                return existing.clone();
+            }
+            tracing::info!("Replacing existing connection to {}", peer_id);
+            existing.conn.close(0u32.into(), b"duplicate connection");
+        }
+    }
+
+    let connection_type = PeerConnectionType::from_connection(&conn);
+    let generation = ctx.next_generation.fetch_add(1, Ordering::Relaxed);
+    let peer = PeerConnection::new(conn.clone(), protocol.clone(), generation);
+    connected_peers.insert(peer_id.to_string(), peer.clone());
+    let _ = ctx
+        .event_tx
+        .send(Event::Connected {
+            peer_id: peer_id.to_string(),
+            connection_type,
+            protocol,
+        })
+        .await;
 
     // Spawn a task to handle incoming streams from this peer
-    let event_tx_clone = event_tx.clone();
-    let shared_state_clone = shared_state.clone();
-    let conn_clone = conn.clone();
-    let node_id_clone = node_id.clone();
-    tokio::spawn(async move {
-        handle_connection(conn_clone, node_id_clone, event_tx_clone, shared_state_clone).await;
-    });
+    tokio::spawn(handle_connection(
+        peer.clone(),
+        peer_id.to_string(),
+        ctx.clone(),
+    ));
+
+    // Monitor path changes (relay <-> direct) for the life of the connection
+    tokio::spawn(monitor_connection_paths(
+        conn,
+        peer_id.to_string(),
+        ctx.event_tx.clone(),
+        ctx.internal_tx.clone(),
+    ));
+
+    peer
+}
 
-    // Monitor connection type changes (relay -> direct)
-    let monitor_tx = event_tx.clone();
-    tokio::spawn(async move {
-        monitor_connection_type(conn, node_id, monitor_tx).await;
+/// Complete an incoming connection and hand it to the event loop once the
+/// handshake is done
+async fn accept_peer(incoming: Incoming, ctx: ConnectionContext) {
+    let mut accepting = match incoming.accept() {
+        Ok(accepting) => accepting,
+        Err(e) => {
+            tracing::warn!("Failed to accept connection: {}", e);
+            return;
+        }
+    };
+
+    // Check ALPN
+    let alpn = match accepting.alpn().await {
+        Ok(alpn) => alpn,
+        Err(e) => {
+            tracing::warn!("Failed to get ALPN: {}", e);
+            return;
+        }
+    };
+    if ProtocolVersion::from_alpn(&alpn).is_none() {
+        tracing::warn!("Unsupported ALPN: {}", String::from_utf8_lossy(&alpn));
+        return;
+    }
+
+    let conn = match accepting.await {
+        Ok(conn) => conn,
+        Err(e) => {
+            tracing::warn!("Connection failed: {}", e);
+            return;
+        }
+    };
+
+    let peer_id = conn.remote_id().to_string();
+    if ctx.access.is_banned(&peer_id) {
+        tracing::info!("Refused connection from banned peer {}", peer_id);
+        DisconnectReason::Banned.close(&conn);
+        return;
+    }
+    let protocol = match protocol::server_handshake(&conn).await {
+        Ok(protocol) => protocol,
+        Err(e) => {
+            tracing::warn!("Handshake with {} failed: {}", peer_id, e);
+            conn.close(0u32.into(), b"handshake failed");
+            return;
+        }
+    };
+    let _ = ctx.internal_tx.send(ConnectionEvent::Accepted {
+        peer_id,
+        conn,
+        protocol,
     });
+}
 
-    Ok(())
+/// Redial a dropped peer with backoff until it answers or the policy gives up
+async fn reconnect_peer(
+    endpoint: Endpoint,
+    peer_id: String,
+    endpoint_addr: EndpointAddr,
+    policy: ReconnectPolicy,
+    ctx: ConnectionContext,
+) {
+    let mut attempt = 1;
+    loop {
+        let _ = ctx
+            .event_tx
+            .send(Event::Reconnecting {
+                peer_id: peer_id.clone(),
+                attempt,
+            })
+            .await;
+        tokio::time::sleep(policy.backoff(attempt)).await;
+
+        match connect_peer(&endpoint, endpoint_addr.clone()).await {
+            Ok((conn, protocol)) => {
+                let _ = ctx.internal_tx.send(ConnectionEvent::Redialed {
+                    peer_id,
+                    conn,
+                    protocol,
+                    attempts: attempt,
+                });
+                return;
+            }
+            Err(e) => {
+                tracing::warn!("Reconnect attempt {} to {} failed: {}", attempt, peer_id, e);
+                if policy.gives_up_after(attempt) || matches!(e, P2pError::Refused(_)) {
+                    let _ = ctx
+                        .internal_tx
+                        .send(ConnectionEvent::ReconnectFailed { peer_id });
+                    return;
+                }
+                attempt += 1;
+            }
+        }
+    }
 }
 
-/// Monitor a peer connection for type changes (e.g. relay -> direct after hole-punching).
-/// Checks every 5 seconds for up to 2 minutes, then stops.
-async fn monitor_connection_type(
+/// Report the endpoint's relay status to the event loop: once the relay
+/// connected or `online_timeout` passed, then on every change until the
+/// endpoint closes. With relays disabled (`online_timeout` None) the first
+/// report is immediate.
+async fn monitor_relay(
+    endpoint: Endpoint,
+    online_timeout: Option<std::time::Duration>,
+    internal_tx: mpsc::UnboundedSender<ConnectionEvent>,
+) {
+    if let Some(online_timeout) = online_timeout {
+        tracing::info!("Waiting for relay connection...");
+        if tokio::time::timeout(online_timeout, endpoint.online())
+            .await
+            .is_err()
+        {
+            tracing::warn!(
+                "Relay connection timed out after {:?} - continuing without relay",
+                online_timeout
+            );
+        }
+    } else {
+        tracing::info!("Relays disabled - direct connections only");
+    }
+
+    let mut addr = endpoint.watch_addr();
+    loop {
+        let connected = addr.get().relay_urls().next().is_some();
+        if internal_tx
+            .send(ConnectionEvent::RelayStatus { connected })
+            .is_err()
+        {
+            break;
+        }
+        if addr.updated().await.is_err() {
+            break;
+        }
+    }
+}
+
+/// Probe every relay now and then every `interval`, reporting each round to
+/// the event loop for `Host::relay_health`
+async fn probe_relays(
+    urls: Vec<String>,
+    interval: std::time::Duration,
+    internal_tx: mpsc::UnboundedSender<ConnectionEvent>,
+) {
+    let prober = match RelayProber::new() {
+        Ok(prober) => prober,
+        Err(e) => {
+            tracing::warn!("Failed to set up relay probes: {}", e);
+            return;
+        }
+    };
+    let mut ticks = tokio::time::interval(interval);
+    ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        ticks.tick().await;
+        let results = prober.probe_all(&urls).await;
+        if internal_tx
+            .send(ConnectionEvent::RelayProbed { results })
+            .is_err()
+        {
+            break;
+        }
+    }
+}
+
+/// Watch a peer connection's paths until it closes, reporting every change of
+/// connection type (e.g. relay -> direct after hole-punching, or direct -> relay
+/// after a NAT rebinding) and of the selected path. The event loop is told
+/// about the initial paths too, for the address book.
+async fn monitor_connection_paths(
     conn: Connection,
     peer_id: String,
     event_tx: mpsc::Sender<Event>,
+    internal_tx: mpsc::UnboundedSender<ConnectionEvent>,
 ) {
-    let mut current_type = PeerConnectionType::from_connection(&conn);
+    let mut paths = conn.paths();
+    let mut current_type = PeerConnectionType::from_paths(&paths.get());
+    let mut recorded_type = None;
+    // Start from None so the initial selected path is reported too
+    let mut current_addr: Option<String> = None;
+
+    loop {
+        let paths_list = paths.get();
 
-    // Check for connection type changes for up to 2 minutes
-    // (hole-punching typically completes within this window)
-    for _ in 0..24 {
-        tokio::time::sleep(std::time::Duration::from_secs(5)).await;
-        let new_type = PeerConnectionType::from_connection(&conn);
+        let new_type = PeerConnectionType::from_paths(&paths_list);
         if new_type != current_type {
             tracing::info!(
                 "Connection type changed for {}: {:?} -> {:?}",
@@ -1038,13 +2353,47 @@ async fn monitor_connection_type(
                 })
                 .await;
             current_type = new_type;
+        }
+        if new_type != PeerConnectionType::None && recorded_type != Some(new_type) {
+            let _ = internal_tx.send(ConnectionEvent::PathsChanged {
+                peer_id: peer_id.clone(),
+                connection_type: new_type,
+                remote_addrs: paths_list
+                    .iter()
+                    .map(|path| path.remote_addr().clone())
+                    .collect(),
+            });
+            recorded_type = Some(new_type);
+        }
 
-            // If we reached Direct, no need to keep monitoring
-            if new_type == PeerConnectionType::Direct {
-                break;
+        let selected_addr = paths_list
+            .iter()
+            .find(|path| path.is_selected())
+            .map(|path| transport_addr_to_string(path.remote_addr()));
+        if let Some(remote_addr) = selected_addr {
+            if current_addr.as_ref() != Some(&remote_addr) {
+                tracing::debug!("Selected path for {}: {}", peer_id, remote_addr);
+                let _ = event_tx
+                    .send(Event::SelectedPathChanged {
+                        peer_id: peer_id.clone(),
+                        remote_addr: remote_addr.clone(),
+                    })
+                    .await;
+                current_addr = Some(remote_addr);
             }
         }
+
+        tokio::select! {
+            result = paths.updated() => {
+                if result.is_err() {
+                    break;
+                }
+            }
+            _ = conn.closed() => break,
+        }
     }
+
+    tracing::debug!("Stopped monitoring paths for {}", peer_id);
 }
 
 /// Stream a file range to a QUIC SendStream with length-prefixed chunks.
@@ -1055,7 +2404,7 @@ async fn stream_file_to_quic(
     file_path: &str,
     offset: u64,
     length: u64,
-) -> Result<(), String> {
+) -> Result<(), P2pError> {
     use std::time::Instant;
 
     const CHUNK_SIZE: usize = 1024 * 1024; // 1 MB
@@ -1094,7 +2443,11 @@ async fn stream_file_to_quic(
                 Ok(_) => {}
                 Err(e) => {
                     tracing::error!("Read error: {}", e);
-                    return (io_nanos / 1_000_000, backpressure_nanos / 1_000_000, chunk_count);
+                    return (
+                        io_nanos / 1_000_000,
+                        backpressure_nanos / 1_000_000,
+                        chunk_count,
+                    );
                 }
             }
             io_nanos += io_start.elapsed().as_nanos() as u64;
@@ -1107,12 +2460,20 @@ async fn stream_file_to_quic(
             if chunk_tx.blocking_send(buf).is_err() {
                 // Receiver dropped (QUIC write failed or stream cancelled)
                 tracing::debug!("stream_file_to_quic: receiver dropped, stopping read");
-                return (io_nanos / 1_000_000, backpressure_nanos / 1_000_000, chunk_count);
+                return (
+                    io_nanos / 1_000_000,
+                    backpressure_nanos / 1_000_000,
+                    chunk_count,
+                );
             }
             backpressure_nanos += bp_start.elapsed().as_nanos() as u64;
         }
         // chunk_tx is dropped here, signalling end of data
-        (io_nanos / 1_000_000, backpressure_nanos / 1_000_000, chunk_count)
+        (
+            io_nanos / 1_000_000,
+            backpressure_nanos / 1_000_000,
+            chunk_count,
+        )
     });
 
     // Async QUIC writer: receives chunks and writes length-prefixed data
@@ -1128,10 +2489,10 @@ async fn stream_file_to_quic(
         let w_start = Instant::now();
         send.write_all(&len_bytes)
             .await
-            .map_err(|e| format!("Failed to write chunk length: {}", e))?;
+            .map_err(|e| P2pError::Io(format!("Failed to write chunk length: {}", e)))?;
         send.write_all(&chunk)
             .await
-            .map_err(|e| format!("Failed to write chunk data: {}", e))?;
+            .map_err(|e| P2pError::Io(format!("Failed to write chunk data: {}", e)))?;
         quic_write_nanos += w_start.elapsed().as_nanos() as u64;
 
         total_bytes += chunk_len;
@@ -1142,9 +2503,9 @@ async fn stream_file_to_quic(
     let zero_bytes = [0u8; 4];
     send.write_all(&zero_bytes)
         .await
-        .map_err(|e| format!("Failed to write terminator: {}", e))?;
+        .map_err(|e| P2pError::Io(format!("Failed to write terminator: {}", e)))?;
     send.finish()
-        .map_err(|e| format!("Failed to finish stream: {}", e))?;
+        .map_err(|e| P2pError::Io(format!("Failed to finish stream: {}", e)))?;
 
     let total_ms = t0.elapsed().as_millis() as u64;
     let quic_write_ms = quic_write_nanos / 1_000_000;
@@ -1174,20 +2535,48 @@ async fn stream_file_to_quic(
 }
 
 /// Handle incoming streams from a peer connection
-async fn handle_connection(
-    conn: Connection,
-    peer_id: String,
-    event_tx: mpsc::Sender<Event>,
-    shared_state: Arc<Mutex<SharedState>>,
-) {
+async fn handle_connection(peer: PeerConnection, peer_id: String, ctx: ConnectionContext) {
+    let ConnectionContext {
+        max_message_size,
+        event_tx,
+        shared_state,
+        internal_tx,
+        access,
+        request_limiter,
+        rejected_requests,
+        ..
+    } = ctx;
+    let max_pending_responses = request_limiter.limits().max_pending_responses;
+    let protocol = peer.protocol.clone();
+    let count_rejected = |error: &WireError| {
+        tracing::warn!("Rejected request from {}: {}", peer_id, error.message);
+        peer.rejected_requests.fetch_add(1, Ordering::Relaxed);
+        rejected_requests.fetch_add(1, Ordering::Relaxed);
+    };
+    // Answer a refused request with `error` and count it
+    let reject = |send: SendStream, error: WireError, hls: bool| {
+        count_rejected(&error);
+        tokio::spawn(reject_request(send, error, protocol.clone(), hls));
+    };
     loop {
-        match conn.accept_bi().await {
+        match peer.conn.accept_bi().await {
             Ok((send, mut recv)) => {
                 let request_id = uuid::Uuid::new_v4().to_string();
                 let t0 = std::time::Instant::now();
 
-                // Read the request
-                let data = match recv.read_to_end(64 * 1024).await {
+                // Enforce the request limits before reading the request
+                let open_streams = peer.open_streams.load(Ordering::Relaxed);
+                if let Err(error) = request_limiter.check(&peer_id, open_streams) {
+                    count_rejected(&error);
+                    tokio::spawn(reject_unread_request(send, recv, error, protocol.clone()));
+                    continue;
+                }
+
+                // Read the request, held to the unpaired size limit if it applies
+                let max_len = access
+                    .max_message_size(&peer_id)
+                    .map_or(max_message_size, |limit| limit.min(max_message_size));
+                let data = match protocol::read_message(&mut recv, max_len, &protocol).await {
                     Ok(data) => data,
                     Err(e) => {
                         tracing::warn!("Failed to read request from {}: {}", peer_id, e);
@@ -1207,12 +2596,17 @@ async fn handle_connection(
 
                 tracing::debug!("Received request from {}: {:?}", peer_id, request);
 
+                if let Err(error) = access.check(&peer_id, &request, open_streams) {
+                    reject(send, error, matches!(request, MydiaRequest::HlsStream(_)));
+                    continue;
+                }
+
                 // For Ping requests, respond immediately
                 if matches!(request, MydiaRequest::Ping) {
                     let mut send = send;
                     let response = MydiaResponse::Pong;
                     if let Ok(response_data) = serde_cbor::to_vec(&response) {
-                        let _ = send.write_all(&response_data).await;
+                        let _ = protocol::write_message(&mut send, &response_data, &protocol).await;
                         let _ = send.finish();
                     }
                     continue;
@@ -1221,17 +2615,34 @@ async fn handle_connection(
                 // For HLS streaming requests, store the send stream and emit event
                 if let MydiaRequest::HlsStream(hls_request) = request {
                     let stream_id = request_id.clone();
+                    let stopped = send.stopped();
 
                     // Store the send stream for later use
                     {
                         let mut state = shared_state.lock().await;
-                        state.hls_streams.insert(stream_id.clone(), send);
+                        if state.pending() >= max_pending_responses {
+                            reject(send, server_busy(), true);
+                            continue;
+                        }
+                        state
+                            .hls_streams
+                            .insert(stream_id.clone(), HlsStream::new(send, peer.open_stream()));
                     }
 
+                    // Drop the stream as soon as the client goes away or the
+                    // application stops writing to it
+                    tokio::spawn(watch_hls_stream(
+                        stream_id.clone(),
+                        stopped,
+                        event_tx.clone(),
+                        shared_state.clone(),
+                    ));
+
                     // Emit the HLS stream event
                     let _ = event_tx
                         .send(Event::HlsStreamRequest {
                             peer: peer_id.clone(),
+                            session: peer.session(),
                             request: hls_request.clone(),
                             stream_id,
                         })
@@ -1259,25 +2670,83 @@ async fn handle_connection(
                 // Store the response sender
                 {
                     let mut state = shared_state.lock().await;
+                    if state.pending() >= max_pending_responses {
+                        reject(send, server_busy(), false);
+                        continue;
+                    }
                     state.pending_responses.insert(request_id.clone(), resp_tx);
                 }
 
+                // The application's answer to an Authenticate sets the connection's session
+                let authenticating = matches!(request, MydiaRequest::Authenticate(_));
+
                 // Emit the request event
                 let _ = event_tx
                     .send(Event::RequestReceived {
                         peer: peer_id.clone(),
+                        session: peer.session(),
                         request,
                         request_id: request_id.clone(),
                     })
                     .await;
 
-                // Wait for the response and send it
+                // Wait for the response and send it, unless the client cancels first
                 let request_id_clone = request_id.clone();
+                let protocol = protocol.clone();
+                let event_tx = event_tx.clone();
+                let shared_state = shared_state.clone();
+                let open_stream = peer.open_stream();
+                let session = peer.session.clone();
+                let peer_id = peer_id.clone();
                 tokio::spawn(async move {
-                    match tokio::time::timeout(std::time::Duration::from_secs(30), resp_rx).await {
+                    let _open_stream = open_stream;
+                    let stopped = send.stopped();
+                    let result = tokio::select! {
+                        result = tokio::time::timeout(std::time::Duration::from_secs(30), resp_rx) => result,
+                        Ok(Some(code)) = stopped => {
+                            tracing::debug!(
+                                "Request {} cancelled by peer (code {})",
+                                request_id_clone,
+                                code
+                            );
+                            shared_state.lock().await.pending_responses.remove(&request_id_clone);
+                            let _ = event_tx
+                                .send(Event::RequestCancelled {
+                                    request_id: request_id_clone,
+                                })
+                                .await;
+                            return;
+                        }
+                    };
+
+                    match result {
                         Ok(Ok(response)) => {
+                            if authenticating {
+                                let authenticated = match &response {
+                                    MydiaResponse::Authenticated(session) => Some(session.clone()),
+                                    _ => None,
+                                };
+                                if let Some(session) = &authenticated {
+                                    tracing::info!(
+                                        "{} authenticated as {}",
+                                        peer_id,
+                                        session.principal
+                                    );
+                                }
+                                *session.write().unwrap() = authenticated;
+                            }
+                            let response = response.for_peer(&protocol);
                             if let Ok(response_data) = serde_cbor::to_vec(&response) {
-                                let _ = send.write_all(&response_data).await;
+                                if let Err(e) =
+                                    protocol::write_message(&mut send, &response_data, &protocol)
+                                        .await
+                                {
+                                    tracing::warn!(
+                                        "Failed to send response for request {}: {}",
+                                        request_id_clone,
+                                        e
+                                    );
+                                }
                                 let _ = send.finish();
                             }
                         }
@@ -1289,10 +2758,20 @@ async fn handle_connection(
                         }
                         Err(_) => {
                             tracing::warn!("Response timeout for request {}", request_id_clone);
-                            let error_response =
-                                MydiaResponse::Error("Request timeout".to_string());
+                            shared_state
+                                .lock()
+                                .await
+                                .pending_responses
+                                .remove(&request_id_clone);
+                            let error_response = MydiaResponse::Failure(WireError::new(
+                                WireError::TIMEOUT,
+                                "Request timeout",
+                            ))
+                            .for_peer(&protocol);
                             if let Ok(response_data) = serde_cbor::to_vec(&error_response) {
-                                let _ = send.write_all(&response_data).await;
+                                let _ =
+                                    protocol::write_message(&mut send, &response_data, &protocol)
+                                        .await;
                                 let _ = send.finish();
                             }
                         }
@@ -1301,24 +2780,78 @@ async fn handle_connection(
             }
             Err(e) => {
                 tracing::info!("Connection closed for peer {}: {}", peer_id, e);
-                let _ = event_tx.send(Event::Disconnected(peer_id)).await;
+                // The event loop decides whether to reconnect or report Disconnected
+                let _ = internal_tx.send(ConnectionEvent::Closed {
+                    peer_id,
+                    generation: peer.generation,
+                    reason: DisconnectReason::from_close(&e),
+                });
                 break;
             }
         }
     }
 }
 
-/// Send a request to a connected peer
-async fn handle_send_request(
-    connected_peers: &HashMap<String, Connection>,
-    node_id: &str,
-    request: MydiaRequest,
-) -> Result<MydiaResponse, String> {
+/// Error for requests refused because too many are waiting on the application
+fn server_busy() -> WireError {
+    WireError::new(WireError::UNAVAILABLE, "Server is busy, try again later")
+}
+
+/// Answer a request the connection policy or request limits refused. HLS clients expect
+/// the response where the stream header would go.
+async fn reject_request(mut send: SendStream, error: WireError, protocol: PeerProtocol, hls: bool) {
+    let response = MydiaResponse::Failure(error).for_peer(&protocol);
+    let Ok(data) = serde_cbor::to_vec(&response) else {
+        return;
+    };
+    let written = if hls {
+        match send.write_all(&(data.len() as u32).to_be_bytes()).await {
+            Ok(()) => send.write_all(&data).await.map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        }
+    } else {
+        protocol::write_message(&mut send, &data, &protocol)
+            .await
+            .map_err(|e| e.to_string())
+    };
+    if written.is_ok() {
+        let _ = send.finish();
+    }
+}
+
+/// Most of a request refused by the request limits that is read to answer it
+const REJECTED_REQUEST_MAX_SIZE: usize = 4 * 1024;
+
+/// Answer a request refused before it was read. Only enough of it is read to
+/// tell how the client expects the answer; anything bigger is cancelled.
+async fn reject_unread_request(
+    mut send: SendStream,
+    mut recv: RecvStream,
+    error: WireError,
+    protocol: PeerProtocol,
+) {
+    match protocol::read_message(&mut recv, REJECTED_REQUEST_MAX_SIZE, &protocol).await {
+        Ok(data) => {
+            let hls = matches!(
+                serde_cbor::from_slice(&data),
+                Ok(MydiaRequest::HlsStream(_))
+            );
+            reject_request(send, error, protocol, hls).await;
+        }
+        Err(_) => {
+            let _ = send.reset(protocol::STREAM_CANCELLED.into());
+            let _ = recv.stop(protocol::STREAM_CANCELLED.into());
+        }
+    }
+}
+
+/// Extract the node id from a peer address
+fn resolve_node_id(node_id: &str) -> String {
     // The node_id parameter might be either:
     // 1. A bare node ID string (e.g., "09ecb63dd2...")
     // 2. A full EndpointAddr JSON (e.g., {"id":"09ecb63dd2...", ...})
     // We need to extract the actual node ID in both cases.
-    let actual_node_id = if node_id.starts_with('{') {
+    if node_id.starts_with('{') {
         // Try to parse as EndpointAddr JSON
         match endpoint_addr_from_json(node_id) {
             Ok(addr) => addr.id.to_string(),
@@ -1326,113 +2859,253 @@ async fn handle_send_request(
         }
     } else {
         node_id.to_string()
+    }
+}
+
+/// Close the connection to a peer with `reason`, without redialing it.
+/// A peer being redialed is given up on. Returns whether there was a
+/// connection to close.
+async fn disconnect_peer(
+    node_id: &str,
+    reason: DisconnectReason,
+    connected_peers: &HashMap<String, PeerConnection>,
+    dialed_addrs: &mut HashMap<String, EndpointAddr>,
+    reconnecting: &mut HashMap<String, Reconnect>,
+    event_tx: &mpsc::Sender<Event>,
+) -> bool {
+    dialed_addrs.remove(node_id);
+    if let Some(reconnect) = reconnecting.remove(node_id) {
+        reconnect.abort(P2pError::NotConnected {
+            peer_id: node_id.to_string(),
+        });
+        let _ = event_tx
+            .send(Event::Disconnected(node_id.to_string()))
+            .await;
+    }
+    match connected_peers.get(node_id) {
+        Some(peer) => {
+            tracing::info!("Disconnecting {}: {}", node_id, reason);
+            reason.close(&peer.conn);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Send a client request to its peer, or hold it while the peer reconnects
+fn route_request(
+    connected_peers: &HashMap<String, PeerConnection>,
+    reconnecting: &mut HashMap<String, Reconnect>,
+    policy: Option<&ReconnectPolicy>,
+    node_id: &str,
+    request: ClientRequest,
+    max_message_size: usize,
+) {
+    let node_id = resolve_node_id(node_id);
+    if let Some(peer) = connected_peers.get(&node_id) {
+        request.dispatch(peer.clone(), max_message_size);
+        return;
+    }
+
+    let queue = policy.is_some_and(|p| p.queue_requests);
+    match reconnecting.get_mut(&node_id) {
+        Some(reconnect) if queue => reconnect.queued.push(request),
+        _ => request.fail(P2pError::NotConnected { peer_id: node_id }),
+    }
+}
+
+/// Watch an HLS stream until it finishes, the client stops it, or it sits idle
+/// past the TTL. Abandoned streams are removed from `hls_streams` and reported
+/// with `Event::StreamCancelled`.
+async fn watch_hls_stream(
+    stream_id: String,
+    stopped: impl std::future::Future<Output = Result<Option<VarInt>, StoppedError>>,
+    event_tx: mpsc::Sender<Event>,
+    shared_state: Arc<Mutex<SharedState>>,
+) {
+    tokio::pin!(stopped);
+    let ttl = shared_state.lock().await.hls_stream_ttl;
+    // None once the stream has left hls_streams (finished or handed to StreamFileRange)
+    let mut deadline = Some(tokio::time::Instant::now() + ttl);
+
+    let reason = loop {
+        tokio::select! {
+            result = &mut stopped => {
+                let reason = match result {
+                    // Finished and acknowledged
+                    Ok(None) => return,
+                    Ok(Some(code)) => StreamCancelReason::Stopped(code.into_inner()),
+                    Err(_) => StreamCancelReason::ConnectionLost,
+                };
+                let parked = shared_state.lock().await.hls_streams.remove(&stream_id).is_some();
+                // A lost connection only matters if the stream was still waiting on the application
+                if parked || matches!(reason, StreamCancelReason::Stopped(_)) {
+                    break reason;
+                }
+                return;
+            }
+            _ = tokio::time::sleep_until(deadline.unwrap_or_else(tokio::time::Instant::now)), if deadline.is_some() => {
+                let mut state = shared_state.lock().await;
+                let Some(stream) = state.hls_streams.get(&stream_id) else {
+                    deadline = None;
+                    continue;
+                };
+                let expires_at = stream.last_activity + ttl;
+                if expires_at > tokio::time::Instant::now() {
+                    deadline = Some(expires_at);
+                    continue;
+                }
+                if let Some(mut stream) = state.hls_streams.remove(&stream_id) {
+                    let _ = stream.send.reset(protocol::STREAM_EXPIRED.into());
+                }
+                break StreamCancelReason::Expired;
+            }
+        }
     };
 
-    let conn = connected_peers
-        .get(&actual_node_id)
-        .ok_or_else(|| format!("Not connected to peer: {}", actual_node_id))?;
+    tracing::debug!("HLS stream {} cancelled: {}", stream_id, reason.as_str());
+    let _ = event_tx
+        .send(Event::StreamCancelled { stream_id, reason })
+        .await;
+}
+
+/// Send a request to a connected peer
+async fn handle_send_request(
+    peer: PeerConnection,
+    request: MydiaRequest,
+    max_message_size: usize,
+    cancel_rx: oneshot::Receiver<()>,
+) -> Result<MydiaResponse, P2pError> {
+    if let Some(capability) = request.required_capability() {
+        if !peer.protocol.supports(capability) {
+            return Err(P2pError::Protocol(format!(
+                "Peer does not support {}",
+                capability
+            )));
+        }
+    }
+
+    let _open_stream = peer.open_stream();
 
     // Open a bidirectional stream
-    let (mut send, mut recv) = conn
+    let (mut send, mut recv) = peer
+        .conn
         .open_bi()
         .await
-        .map_err(|e| format!("Failed to open stream: {}", e))?;
+        .map_err(|e| P2pError::Io(format!("Failed to open stream: {}", e)))?;
 
-    // Send the request
-    let request_data =
-        serde_cbor::to_vec(&request).map_err(|e| format!("Failed to encode request: {}", e))?;
+    let exchange = async {
+        // Send the request
+        let request_data = serde_cbor::to_vec(&request)
+            .map_err(|e| P2pError::Protocol(format!("Failed to encode request: {}", e)))?;
 
-    send.write_all(&request_data)
-        .await
-        .map_err(|e| format!("Failed to send request: {}", e))?;
+        protocol::write_message(&mut send, &request_data, &peer.protocol).await?;
 
-    send.finish()
-        .map_err(|e| format!("Failed to finish send: {}", e))?;
+        send.finish()
+            .map_err(|e| P2pError::Io(format!("Failed to finish send: {}", e)))?;
 
-    // Read the response
-    let response_data = recv
-        .read_to_end(64 * 1024)
-        .await
-        .map_err(|e| format!("Failed to read response: {}", e))?;
+        // Read the response
+        let response_data =
+            protocol::read_message(&mut recv, max_message_size, &peer.protocol).await?;
+
+        let response: MydiaResponse = serde_cbor::from_slice(&response_data)
+            .map_err(|e| P2pError::Protocol(format!("Failed to decode response: {}", e)))?;
+
+        response.into_result()
+    };
 
-    let response: MydiaResponse = serde_cbor::from_slice(&response_data)
-        .map_err(|e| format!("Failed to decode response: {}", e))?;
+    let result = tokio::select! {
+        result = exchange => result,
+        _ = cancelled(cancel_rx) => Err(P2pError::Cancelled),
+    };
+
+    if result == Err(P2pError::Cancelled) {
+        // Tell the server to stop working on this request
+        let _ = send.reset(protocol::STREAM_CANCELLED.into());
+        let _ = recv.stop(protocol::STREAM_CANCELLED.into());
+    }
 
-    Ok(response)
+    result
 }
 
 /// Send an HLS streaming request to a connected peer (client-side).
 /// Returns a streaming response with header and channel for chunks.
 async fn handle_send_hls_request(
-    connected_peers: &HashMap<String, Connection>,
-    node_id: &str,
+    peer: PeerConnection,
     request: HlsRequest,
-) -> Result<HlsStreamResponse, String> {
+    cancel_rx: oneshot::Receiver<()>,
+) -> Result<HlsStreamResponse, P2pError> {
     use std::time::Instant;
     let t0 = Instant::now();
 
     let session_id = request.session_id.clone();
     let path = request.path.clone();
 
-    // Handle both bare node ID and full EndpointAddr JSON
-    let actual_node_id = if node_id.starts_with('{') {
-        match endpoint_addr_from_json(node_id) {
-            Ok(addr) => addr.id.to_string(),
-            Err(_) => node_id.to_string(),
-        }
-    } else {
-        node_id.to_string()
-    };
-
-    let conn = connected_peers
-        .get(&actual_node_id)
-        .ok_or_else(|| format!("Not connected to peer: {}", actual_node_id))?;
-
-    let connection_type = PeerConnectionType::from_connection(conn);
+    let connection_type = PeerConnectionType::from_connection(&peer.conn);
+    let open_stream = peer.open_stream();
 
     // Open a bidirectional stream
-    let (mut send, mut recv) = conn
+    let (mut send, mut recv) = peer
+        .conn
         .open_bi()
         .await
-        .map_err(|e| format!("Failed to open stream: {}", e))?;
+        .map_err(|e| P2pError::Io(format!("Failed to open stream: {}", e)))?;
     let open_bi_ms = t0.elapsed().as_millis() as u64;
 
-    // Send the request
-    let request = MydiaRequest::HlsStream(request);
-    let request_data =
-        serde_cbor::to_vec(&request).map_err(|e| format!("Failed to encode request: {}", e))?;
+    // Shared between the header exchange and the chunk reader below
+    let mut cancel = Box::pin(cancelled(cancel_rx));
+    let mut request_sent_ms = 0;
 
-    send.write_all(&request_data)
-        .await
-        .map_err(|e| format!("Failed to send request: {}", e))?;
+    let exchange = async {
+        // Send the request
+        let request = MydiaRequest::HlsStream(request);
+        let request_data = serde_cbor::to_vec(&request)
+            .map_err(|e| P2pError::Protocol(format!("Failed to encode request: {}", e)))?;
 
-    send.finish()
-        .map_err(|e| format!("Failed to finish send: {}", e))?;
-    let request_sent_ms = t0.elapsed().as_millis() as u64;
+        protocol::write_message(&mut send, &request_data, &peer.protocol).await?;
 
-    // Read the header (length-prefixed)
-    let mut len_buf = [0u8; 4];
-    recv.read_exact(&mut len_buf)
-        .await
-        .map_err(|e| format!("Failed to read header length: {}", e))?;
-    let header_len = u32::from_be_bytes(len_buf) as usize;
+        send.finish()
+            .map_err(|e| P2pError::Io(format!("Failed to finish send: {}", e)))?;
+        request_sent_ms = t0.elapsed().as_millis() as u64;
 
-    if header_len == 0 {
-        return Err("Empty header received".to_string());
-    }
+        // Read the header (length-prefixed)
+        let mut len_buf = [0u8; 4];
+        recv.read_exact(&mut len_buf)
+            .await
+            .map_err(|e| P2pError::Io(format!("Failed to read header length: {}", e)))?;
+        let header_len = u32::from_be_bytes(len_buf) as usize;
 
-    let mut header_data = vec![0u8; header_len];
-    recv.read_exact(&mut header_data)
-        .await
-        .map_err(|e| format!("Failed to read header: {}", e))?;
+        if header_len == 0 {
+            return Err(P2pError::Protocol("Empty header received".to_string()));
+        }
+
+        let mut header_data = vec![0u8; header_len];
+        recv.read_exact(&mut header_data)
+            .await
+            .map_err(|e| P2pError::Io(format!("Failed to read header: {}", e)))?;
+
+        let header_response: MydiaResponse = serde_cbor::from_slice(&header_data)
+            .map_err(|e| P2pError::Protocol(format!("Failed to decode header: {}", e)))?;
+
+        match header_response.into_result()? {
+            MydiaResponse::HlsHeader(h) => Ok(h),
+            _ => Err(P2pError::Protocol("Unexpected response type".to_string())),
+        }
+    };
 
-    let header_response: MydiaResponse = serde_cbor::from_slice(&header_data)
-        .map_err(|e| format!("Failed to decode header: {}", e))?;
+    let header = tokio::select! {
+        result = exchange => result,
+        _ = &mut cancel => Err(P2pError::Cancelled),
+    };
 
-    let header = match header_response {
-        MydiaResponse::HlsHeader(h) => h,
-        MydiaResponse::Error(e) => return Err(format!("Server error: {}", e)),
-        _ => return Err("Unexpected response type".to_string()),
+    let header = match header {
+        Ok(header) => header,
+        Err(P2pError::Cancelled) => {
+            let _ = send.reset(protocol::STREAM_CANCELLED.into());
+            let _ = recv.stop(protocol::STREAM_CANCELLED.into());
+            return Err(P2pError::Cancelled);
+        }
+        Err(e) => return Err(e),
     };
     let header_received_ms = t0.elapsed().as_millis() as u64;
 
@@ -1454,32 +3127,49 @@ async fn handle_send_hls_request(
 
     // Spawn a task to read chunks and send them through the channel
     tokio::spawn(async move {
+        let _open_stream = open_stream;
         let mut total_bytes: u64 = 0;
         let mut chunk_count: u32 = 0;
         let transfer_start = Instant::now();
 
+        let mut was_cancelled = false;
+
         loop {
-            // Read chunk length
-            let mut len_buf = [0u8; 4];
-            if let Err(e) = recv.read_exact(&mut len_buf).await {
-                tracing::debug!("HLS chunk read completed or error: {}", e);
-                break;
-            }
-            let chunk_len = u32::from_be_bytes(len_buf) as usize;
+            let read_chunk = async {
+                // Read chunk length
+                let mut len_buf = [0u8; 4];
+                if let Err(e) = recv.read_exact(&mut len_buf).await {
+                    tracing::debug!("HLS chunk read completed or error: {}", e);
+                    return None;
+                }
+                let chunk_len = u32::from_be_bytes(len_buf) as usize;
 
-            // Zero length indicates end of stream
-            if chunk_len == 0 {
-                break;
-            }
+                // Zero length indicates end of stream
+                if chunk_len == 0 {
+                    return None;
+                }
+
+                // Read the chunk
+                let mut chunk_data = vec![0u8; chunk_len];
+                if let Err(e) = recv.read_exact(&mut chunk_data).await {
+                    tracing::error!("Failed to read chunk data: {}", e);
+                    return None;
+                }
+                Some(chunk_data)
+            };
 
-            // Read the chunk
-            let mut chunk_data = vec![0u8; chunk_len];
-            if let Err(e) = recv.read_exact(&mut chunk_data).await {
-                tracing::error!("Failed to read chunk data: {}", e);
+            let chunk = tokio::select! {
+                chunk = read_chunk => chunk,
+                _ = &mut cancel => {
+                    was_cancelled = true;
+                    None
+                }
+            };
+            let Some(chunk_data) = chunk else {
                 break;
-            }
+            };
 
-            total_bytes += chunk_len as u64;
+            total_bytes += chunk_data.len() as u64;
             chunk_count += 1;
 
             // Send chunk through channel
@@ -1489,6 +3179,15 @@ async fn handle_send_hls_request(
             }
         }
 
+        if was_cancelled {
+            tracing::debug!(
+                "HLS stream cancelled by client: session={} path={}",
+                session_id,
+                path
+            );
+            let _ = recv.stop(protocol::STREAM_CANCELLED.into());
+        }
+
         let transfer_ms = transfer_start.elapsed().as_millis() as u64;
         let total_ms = t0.elapsed().as_millis() as u64;
         let throughput_mbps = if transfer_ms > 0 {
@@ -1645,4 +3344,950 @@ mod tests {
         assert_eq!(response, decoded);
     }
 
+    #[test]
+    fn test_failure_response_serialization() {
+        let response = MydiaResponse::Failure(WireError::new(WireError::NOT_FOUND, "No such file"));
+        let data = serde_cbor::to_vec(&response).unwrap();
+        let decoded: MydiaResponse = serde_cbor::from_slice(&data).unwrap();
+        assert_eq!(response, decoded);
+    }
+
+    #[test]
+    fn test_failure_downgraded_for_legacy_peers() {
+        let legacy = PeerProtocol {
+            version: ProtocolVersion::V1_0,
+            capabilities: vec![],
+        };
+        let response =
+            MydiaResponse::Failure(WireError::new(WireError::TIMEOUT, "Request timeout"));
+        assert_eq!(
+            response.for_peer(&legacy),
+            MydiaResponse::Error("Request timeout".to_string())
+        );
+    }
+
+    #[test]
+    fn test_error_responses_become_remote_errors() {
+        let result = MydiaResponse::Failure(WireError::new(WireError::UNAUTHORIZED, "bad token"))
+            .into_result();
+        assert_eq!(result.unwrap_err().code(), Some(WireError::UNAUTHORIZED));
+
+        let result = MydiaResponse::Error("boom".to_string()).into_result();
+        assert_eq!(result.unwrap_err().code(), Some(WireError::INTERNAL));
+
+        assert_eq!(MydiaResponse::Pong.into_result(), Ok(MydiaResponse::Pong));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_handle() {
+        let (handle, cancel_rx) = CancelHandle::new();
+        handle.cancel();
+        tokio::time::timeout(std::time::Duration::from_secs(1), cancelled(cancel_rx))
+            .await
+            .expect("cancel should resolve");
+
+        // Dropping the handle must not cancel the request
+        let (handle, cancel_rx) = CancelHandle::new();
+        drop(handle);
+        let result =
+            tokio::time::timeout(std::time::Duration::from_millis(50), cancelled(cancel_rx)).await;
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_duplicate_connections_resolved_deterministically() {
+        let (a, b) = ("aaaa", "bbbb");
+        // Both sides dialed: each keeps the connection dialed by `a`
+        assert!(!replaces_existing(a, b, Side::Client, Side::Server));
+        assert!(replaces_existing(a, b, Side::Server, Side::Client));
+        assert!(!replaces_existing(b, a, Side::Server, Side::Client));
+        assert!(replaces_existing(b, a, Side::Client, Side::Server));
+
+        // A reconnect from the same side replaces the old connection
+        assert!(replaces_existing(a, b, Side::Client, Side::Client));
+        assert!(replaces_existing(b, a, Side::Server, Side::Server));
+    }
+
+    #[test]
+    fn test_duplicate_connections_leave_one_live_entry() {
+        let config = HostConfig {
+            reconnect: Some(ReconnectPolicy::default()),
+            ..local_config()
+        };
+        let server = Host::start(config.clone()).unwrap();
+        let player = Host::start(config).unwrap();
+        let rt = Runtime::new().unwrap();
+
+        // Dial again while connected, then from the other side too. If the
+        // player keeps its own connection it closes the server's right after
+        // the handshake, which can cut the server's dial short.
+        player.dial(server.get_node_addr()).unwrap();
+        player.dial(server.get_node_addr()).unwrap();
+        let _ = server.dial(player.get_node_addr());
+
+        // The closed duplicates must not look like a lost peer
+        let settle = |host: &Host| {
+            rt.block_on(async {
+                let mut rx = host.event_rx.lock().await;
+                let quiet = tokio::time::sleep(std::time::Duration::from_millis(500));
+                tokio::pin!(quiet);
+                loop {
+                    tokio::select! {
+                        Some(event) = rx.recv() => match event {
+                            Event::Disconnected(_) | Event::Reconnecting { .. } => {
+                                panic!("unexpected {:?}", event)
+                            }
+                            _ => {}
+                        },
+                        _ = &mut quiet => break,
+                    }
+                }
+            })
+        };
+        settle(&server);
+        settle(&player);
+
+        assert_eq!(server.list_peers().len(), 1);
+        assert_eq!(player.list_peers().len(), 1);
+        let server_id = server.node_id().to_string();
+        let pong = rt.block_on(player.send_request(server_id, MydiaRequest::Ping));
+        assert!(matches!(pong, Ok(MydiaResponse::Pong)));
+        let player_id = player.node_id().to_string();
+        let pong = rt.block_on(server.send_request(player_id, MydiaRequest::Ping));
+        assert!(matches!(pong, Ok(MydiaResponse::Pong)));
+
+        player.shutdown(std::time::Duration::ZERO).unwrap();
+        server.shutdown(std::time::Duration::ZERO).unwrap();
+    }
+
+    #[test]
+    fn test_inbound_connection_ends_reconnect() {
+        let server_config = HostConfig {
+            identity: Some(generate_identity()),
+            bind_port: Some(free_udp_port()),
+            ..local_config()
+        };
+        let player = Host::start(HostConfig {
+            reconnect: Some(ReconnectPolicy {
+                initial_backoff: std::time::Duration::from_secs(3),
+                jitter: 0.0,
+                ..Default::default()
+            }),
+            ..local_config()
+        })
+        .unwrap();
+        let rt = Runtime::new().unwrap();
+
+        let server = Host::start(server_config.clone()).unwrap();
+        player.dial(server.get_node_addr()).unwrap();
+        server.shutdown(std::time::Duration::ZERO).unwrap();
+        wait_for_event(&rt, &player, |event| match event {
+            Event::Reconnecting { .. } => Some(()),
+            _ => None,
+        })
+        .expect("player should start redialing");
+
+        // Held until the peer is back
+        let server_id = server.node_id().to_string();
+        let mut ping = Box::pin(player.send_request(server_id, MydiaRequest::Ping));
+        let held = rt.block_on(async {
+            tokio::time::timeout(std::time::Duration::from_millis(200), &mut ping).await
+        });
+        assert!(held.is_err());
+
+        // The server comes back and dials the player before the redial is due
+        let server = Host::start(server_config).unwrap();
+        server.dial(player.get_node_addr()).unwrap();
+        let pong = rt.block_on(async {
+            tokio::time::timeout(std::time::Duration::from_secs(2), ping).await
+        });
+        assert!(matches!(pong, Ok(Ok(MydiaResponse::Pong))));
+
+        // The redial was called off, so nothing replaces the inbound connection
+        rt.block_on(async {
+            let mut rx = player.event_rx.lock().await;
+            let quiet = tokio::time::sleep(std::time::Duration::from_secs(4));
+            tokio::pin!(quiet);
+            loop {
+                tokio::select! {
+                    Some(event) = rx.recv() => {
+                        if let Event::Reconnected { .. } = event {
+                            panic!("unexpected {:?}", event);
+                        }
+                    }
+                    _ = &mut quiet => break,
+                }
+            }
+        });
+        assert_eq!(player.list_peers().len(), 1);
+
+        player.shutdown(std::time::Duration::ZERO).unwrap();
+        server.shutdown(std::time::Duration::ZERO).unwrap();
+    }
+
+    #[test]
+    fn test_parse_dial_addr_accepts_bare_node_id() {
+        let node_id = SecretKey::generate(&mut rand::rng()).public();
+        let addr = parse_dial_addr(&node_id.to_string()).unwrap();
+        assert_eq!(addr.id, node_id);
+        assert!(addr.addrs.is_empty());
+
+        let json = endpoint_addr_to_json(&addr);
+        assert_eq!(parse_dial_addr(&json).unwrap(), addr);
+
+        assert!(matches!(
+            parse_dial_addr("not-a-node-id"),
+            Err(P2pError::InvalidAddress(_))
+        ));
+    }
+
+    /// Wait for the first event matching `pick`
+    fn wait_for_event<T>(
+        rt: &Runtime,
+        host: &Host,
+        mut pick: impl FnMut(Event) -> Option<T>,
+    ) -> Option<T> {
+        rt.block_on(async {
+            let mut rx = host.event_rx.lock().await;
+            tokio::time::timeout(std::time::Duration::from_secs(20), async {
+                while let Some(event) = rx.recv().await {
+                    if let Some(found) = pick(event) {
+                        return Some(found);
+                    }
+                }
+                None
+            })
+            .await
+            .ok()
+            .flatten()
+        })
+    }
+
+    /// Config for a host that only reaches peers on this machine
+    fn local_config() -> HostConfig {
+        HostConfig {
+            relay: RelayPolicy::Disabled,
+            lookup: LookupService::Disabled,
+            ..Default::default()
+        }
+    }
+
+    /// Start a server and a player, and connect the player to the server
+    fn start_pair(server: HostConfig, player: HostConfig) -> (Host, Host, Runtime) {
+        let server = Host::start(server).unwrap();
+        let player = Host::start(player).unwrap();
+        player.dial(server.get_node_addr()).unwrap();
+        (server, player, Runtime::new().unwrap())
+    }
+
+    #[test]
+    fn test_local_discovery_on_loopback() {
+        let config = |local_discovery| HostConfig {
+            relay: RelayPolicy::Disabled,
+            local_discovery,
+            ..Default::default()
+        };
+        let server = Host::start(config(LocalDiscovery::AnnounceAndDiscover)).unwrap();
+        let player = Host::start(config(LocalDiscovery::Discover)).unwrap();
+        let rt = Runtime::new().unwrap();
+
+        let server_id = server.node_id().to_string();
+        let addrs = wait_for_event(&rt, &player, |event| match event {
+            Event::PeerDiscovered { node_id, addrs } if node_id == server_id => Some(addrs),
+            _ => None,
+        })
+        .expect("server should be discovered");
+        assert!(!addrs.is_empty());
+
+        player.dial(server_id.clone()).unwrap();
+        let connected = wait_for_event(&rt, &server, |event| match event {
+            Event::Connected { peer_id, .. } => Some(peer_id),
+            _ => None,
+        });
+        assert_eq!(connected.as_deref(), Some(player.node_id()));
+
+        player.shutdown(std::time::Duration::ZERO).unwrap();
+        server.shutdown(std::time::Duration::ZERO).unwrap();
+    }
+
+    #[test]
+    fn test_selected_path_is_reported() {
+        let (server, player, rt) = start_pair(local_config(), local_config());
+        let server_addrs: Vec<String> = endpoint_addr_from_json(&server.get_node_addr())
+            .unwrap()
+            .addrs
+            .iter()
+            .map(transport_addr_to_string)
+            .collect();
+
+        let server_id = server.node_id().to_string();
+        let selected = wait_for_event(&rt, &player, |event| match event {
+            Event::SelectedPathChanged {
+                peer_id,
+                remote_addr,
+            } if peer_id == server_id => Some(remote_addr),
+            _ => None,
+        })
+        .expect("the initial path should be reported");
+        assert!(
+            server_addrs.contains(&selected),
+            "{} not in {:?}",
+            selected,
+            server_addrs
+        );
+
+        let peers = player.list_peers();
+        assert_eq!(peers[0].connection_type, PeerConnectionType::Direct);
+        assert!(peers[0].remote_addrs.contains(&selected));
+
+        player.shutdown(std::time::Duration::ZERO).unwrap();
+        server.shutdown(std::time::Duration::ZERO).unwrap();
+    }
+
+    /// A UDP port nothing is bound to right now
+    fn free_udp_port() -> u16 {
+        std::net::UdpSocket::bind("0.0.0.0:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port()
+    }
+
+    #[test]
+    fn test_port_is_free_again_after_shutdown() {
+        let config = HostConfig {
+            bind_port: Some(free_udp_port()),
+            ..local_config()
+        };
+        let host = Host::start(config.clone()).unwrap();
+        host.shutdown(std::time::Duration::ZERO).unwrap();
+
+        let host = Host::start(config).unwrap();
+        host.shutdown(std::time::Duration::ZERO).unwrap();
+    }
+
+    #[test]
+    fn test_logs_reach_a_restarted_host() {
+        let rt = Runtime::new().unwrap();
+        let host = Host::start(local_config()).unwrap();
+        host.shutdown(std::time::Duration::ZERO).unwrap();
+
+        // Nothing keeps the stopped host's event channel open, logging included
+        let closed = rt.block_on(async {
+            let mut rx = host.event_rx.lock().await;
+            tokio::time::timeout(std::time::Duration::from_secs(5), async {
+                while rx.recv().await.is_some() {}
+            })
+            .await
+        });
+        assert!(closed.is_ok(), "event channel should close after shutdown");
+
+        let host = Host::start(local_config()).unwrap();
+        let logged = wait_for_event(&rt, &host, |event| match event {
+            Event::Log { .. } => Some(()),
+            _ => None,
+        });
+        assert!(logged.is_some(), "second host should receive logs");
+        host.shutdown(std::time::Duration::ZERO).unwrap();
+    }
+
+    #[test]
+    fn test_start_reports_bind_errors() {
+        let socket = std::net::UdpSocket::bind("0.0.0.0:0").unwrap();
+        let port = socket.local_addr().unwrap().port();
+        let result = Host::start(HostConfig {
+            bind_port: Some(port),
+            ..local_config()
+        });
+        assert!(matches!(result, Err(P2pError::Bind(_))));
+
+        let result = Host::start(HostConfig {
+            bind_interface: Some("no-such-interface0".to_string()),
+            ..local_config()
+        });
+        assert!(matches!(result, Err(P2pError::InvalidConfig(_))));
+    }
+
+    #[test]
+    fn test_direct_connections_are_served_before_the_relay_is_online() {
+        // Nothing listens on the discard port, so the relay never comes up
+        let server = Host::start(HostConfig {
+            relay: RelayPolicy::Custom(vec!["http://127.0.0.1:9".to_string()]),
+            lookup: LookupService::Disabled,
+            online_timeout: Some(std::time::Duration::from_secs(60)),
+            ..Default::default()
+        })
+        .unwrap();
+        let player = Host::start(local_config()).unwrap();
+        let rt = Runtime::new().unwrap();
+
+        player.dial(server.get_node_addr()).unwrap();
+        let connected = wait_for_event(&rt, &server, |event| match event {
+            Event::Ready { .. } | Event::RelayConnected => {
+                panic!("the relay should not be online, got {:?}", event)
+            }
+            Event::Connected { peer_id, .. } => Some(peer_id),
+            _ => None,
+        });
+        assert_eq!(connected.as_deref(), Some(player.node_id()));
+        let server_id = server.node_id().to_string();
+        let pong = rt.block_on(player.send_request(server_id, MydiaRequest::Ping));
+        assert!(matches!(pong, Ok(MydiaResponse::Pong)));
+
+        // The startup probe says why the relay is down
+        let relay = &server.relay_health()[0];
+        assert!(!relay.reachable);
+        let error = relay.last_error.as_deref().unwrap_or_default();
+        assert!(error.contains("refused"), "unexpected error: {:?}", error);
+
+        player.shutdown(std::time::Duration::ZERO).unwrap();
+        server.shutdown(std::time::Duration::ZERO).unwrap();
+    }
+
+    #[test]
+    fn test_dial_by_node_id_from_address_book() {
+        let server = Host::start(local_config()).unwrap();
+        let player = Host::start(local_config()).unwrap();
+        let rt = Runtime::new().unwrap();
+
+        // Nothing to look the node id up in yet
+        assert!(matches!(
+            player.dial(server.node_id().to_string()),
+            Err(P2pError::Connect(_))
+        ));
+
+        player.add_peer_addr(server.get_node_addr()).unwrap();
+        player.dial(server.node_id().to_string()).unwrap();
+        let connected = wait_for_event(&rt, &server, |event| match event {
+            Event::Connected { peer_id, .. } => Some(peer_id),
+            _ => None,
+        });
+        assert_eq!(connected.as_deref(), Some(player.node_id()));
+
+        player.shutdown(std::time::Duration::ZERO).unwrap();
+        server.shutdown(std::time::Duration::ZERO).unwrap();
+    }
+
+    #[test]
+    fn test_stalled_handshake_does_not_hold_up_other_peers() {
+        let server = Host::start(local_config()).unwrap();
+        let player = Host::start(local_config()).unwrap();
+        let rt = Runtime::new().unwrap();
+
+        // Connect without ever sending the handshake
+        let server_addr = endpoint_addr_from_json(&server.get_node_addr()).unwrap();
+        let _stalled = rt.block_on(async {
+            let endpoint = Endpoint::empty_builder(iroh::RelayMode::Disabled)
+                .bind()
+                .await
+                .unwrap();
+            let conn = endpoint
+                .connect(server_addr, &ProtocolVersion::CURRENT.alpn())
+                .await
+                .unwrap();
+            (endpoint, conn)
+        });
+
+        let started = std::time::Instant::now();
+        player.dial(server.get_node_addr()).unwrap();
+        let connected = wait_for_event(&rt, &server, |event| match event {
+            Event::Connected { peer_id, .. } => Some(peer_id),
+            _ => None,
+        });
+        assert_eq!(connected.as_deref(), Some(player.node_id()));
+        assert!(started.elapsed() < std::time::Duration::from_secs(5));
+
+        player.shutdown(std::time::Duration::ZERO).unwrap();
+        server.shutdown(std::time::Duration::ZERO).unwrap();
+    }
+
+    #[test]
+    fn test_address_book_survives_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("address_book.json");
+        let player_config = HostConfig {
+            address_book_path: Some(path.to_string_lossy().into_owned()),
+            ..local_config()
+        };
+        let server = Host::start(local_config()).unwrap();
+        let rt = Runtime::new().unwrap();
+
+        let player = Host::start(player_config.clone()).unwrap();
+        player.dial(server.get_node_addr()).unwrap();
+        wait_for_event(&rt, &server, |event| match event {
+            Event::Connected { .. } => Some(()),
+            _ => None,
+        })
+        .expect("player should connect");
+        player.shutdown(std::time::Duration::ZERO).unwrap();
+
+        // A fresh host only knows the server's node id from the saved book
+        let player = Host::start(player_config).unwrap();
+        player.dial(server.node_id().to_string()).unwrap();
+
+        player.shutdown(std::time::Duration::ZERO).unwrap();
+        server.shutdown(std::time::Duration::ZERO).unwrap();
+    }
+
+    #[test]
+    fn test_allowlist_rejects_unpaired_peers() {
+        let (server, player, rt) = start_pair(
+            HostConfig {
+                connection_policy: ConnectionPolicy::Allowlist {
+                    paired: Vec::new(),
+                    limits: UnpairedLimits::default(),
+                },
+                ..local_config()
+            },
+            local_config(),
+        );
+
+        let server_id = server.node_id().to_string();
+        let graphql = || {
+            MydiaRequest::GraphQL(GraphQLRequest {
+                query: "{ me { id } }".to_string(),
+                variables: None,
+                operation_name: None,
+                auth_token: None,
+            })
+        };
+        let result = rt.block_on(player.send_request(server_id.clone(), graphql()));
+        assert!(matches!(
+            result,
+            Err(P2pError::Remote {
+                code: WireError::FORBIDDEN,
+                ..
+            })
+        ));
+        let pong = rt.block_on(player.send_request(server_id.clone(), MydiaRequest::Ping));
+        assert!(matches!(pong, Ok(MydiaResponse::Pong)));
+
+        // Requests bigger than a pairing request aren't read
+        let oversized = MydiaRequest::Pairing(PairingRequest {
+            claim_code: "ABC123".to_string(),
+            device_name: "x".repeat(8 * 1024),
+            device_type: "mobile".to_string(),
+            device_os: None,
+        });
+        let result = rt.block_on(player.send_request(server_id.clone(), oversized));
+        assert!(result.is_err());
+
+        // Once paired the request reaches the server
+        server.allow_peer(player.node_id().to_string()).unwrap();
+        let (_cancel, response) = player.send_request_cancellable(server_id, graphql());
+        let _pending = rt.spawn(response);
+        let peer = wait_for_event(&rt, &server, |event| match event {
+            Event::RequestReceived {
+                request: MydiaRequest::Pairing(_),
+                ..
+            } => panic!("the oversized pairing request should not be read"),
+            Event::RequestReceived { peer, .. } => Some(peer),
+            _ => None,
+        });
+        assert_eq!(peer.as_deref(), Some(player.node_id()));
+
+        player.shutdown(std::time::Duration::ZERO).unwrap();
+        server.shutdown(std::time::Duration::ZERO).unwrap();
+    }
+
+    #[test]
+    fn test_request_events_name_the_peer() {
+        let (server, player, rt) = start_pair(local_config(), local_config());
+        let server_id = server.node_id().to_string();
+
+        let (_cancel, response) =
+            player.send_request_cancellable(server_id.clone(), MydiaRequest::Custom(vec![1]));
+        let _pending = rt.spawn(response);
+        let peer = wait_for_event(&rt, &server, |event| match event {
+            Event::RequestReceived { peer, .. } => Some(peer),
+            _ => None,
+        });
+        assert_eq!(peer.as_deref(), Some(player.node_id()));
+
+        let hls = HlsRequest {
+            session_id: "session-1".to_string(),
+            path: "index.m3u8".to_string(),
+            range_start: None,
+            range_end: None,
+            auth_token: None,
+        };
+        let (_cancel, response) = player.send_hls_request_cancellable(server_id, hls);
+        let _pending = rt.spawn(response);
+        let peer = wait_for_event(&rt, &server, |event| match event {
+            Event::HlsStreamRequest { peer, .. } => Some(peer),
+            _ => None,
+        });
+        assert_eq!(peer.as_deref(), Some(player.node_id()));
+
+        player.shutdown(std::time::Duration::ZERO).unwrap();
+        server.shutdown(std::time::Duration::ZERO).unwrap();
+    }
+
+    #[test]
+    fn test_cancelled_request_is_reported_and_forgotten() {
+        // Only room for one pending request, so a leaked entry would refuse the next
+        let (server, player, rt) = start_pair(
+            HostConfig {
+                request_limits: RequestLimits {
+                    max_pending_responses: 1,
+                    ..Default::default()
+                },
+                ..local_config()
+            },
+            local_config(),
+        );
+        let server_id = server.node_id().to_string();
+
+        let (cancel, response) =
+            player.send_request_cancellable(server_id.clone(), MydiaRequest::Custom(vec![1]));
+        let response = rt.spawn(response);
+        let request_id = wait_for_event(&rt, &server, |event| match event {
+            Event::RequestReceived { request_id, .. } => Some(request_id),
+            _ => None,
+        })
+        .expect("request should arrive");
+
+        cancel.cancel();
+        assert!(matches!(
+            rt.block_on(response).unwrap(),
+            Err(P2pError::Cancelled)
+        ));
+        let cancelled = wait_for_event(&rt, &server, |event| match event {
+            Event::RequestCancelled { request_id } => Some(request_id),
+            _ => None,
+        });
+        assert_eq!(cancelled, Some(request_id.clone()));
+
+        // The pending entry is gone: the next request gets through
+        let (_cancel, response) =
+            player.send_request_cancellable(server_id, MydiaRequest::Custom(vec![2]));
+        let _pending = rt.spawn(response);
+        let next = wait_for_event(&rt, &server, |event| match event {
+            Event::RequestReceived { request_id, .. } => Some(request_id),
+            _ => None,
+        });
+        assert!(next.is_some_and(|next| next != request_id));
+
+        player.shutdown(std::time::Duration::ZERO).unwrap();
+        server.shutdown(std::time::Duration::ZERO).unwrap();
+    }
+
+    #[test]
+    fn test_abandoned_hls_streams_are_cancelled_and_dropped() {
+        let (server, player, rt) = start_pair(
+            HostConfig {
+                hls_stream_ttl: Some(std::time::Duration::from_millis(200)),
+                ..local_config()
+            },
+            local_config(),
+        );
+        let server_id = server.node_id().to_string();
+        let hls = || HlsRequest {
+            session_id: "session-1".to_string(),
+            path: "index.m3u8".to_string(),
+            range_start: None,
+            range_end: None,
+            auth_token: None,
+        };
+        let stream_requested = |rt: &Runtime| {
+            wait_for_event(rt, &server, |event| match event {
+                Event::HlsStreamRequest { stream_id, .. } => Some(stream_id),
+                _ => None,
+            })
+            .expect("HLS request should arrive")
+        };
+        let stream_cancelled = |rt: &Runtime| {
+            wait_for_event(rt, &server, |event| match event {
+                Event::StreamCancelled { stream_id, reason } => Some((stream_id, reason)),
+                _ => None,
+            })
+        };
+
+        // The client stops reading before the header
+        let (cancel, response) = player.send_hls_request_cancellable(server_id.clone(), hls());
+        let response = rt.spawn(response);
+        let stream_id = stream_requested(&rt);
+        cancel.cancel();
+        assert!(matches!(
+            rt.block_on(response).unwrap(),
+            Err(P2pError::Cancelled)
+        ));
+        let reason = StreamCancelReason::Stopped(protocol::STREAM_CANCELLED.into());
+        assert_eq!(stream_cancelled(&rt), Some((stream_id.clone(), reason)));
+        assert!(matches!(
+            server.send_hls_chunk(stream_id, vec![1]),
+            Err(P2pError::StreamNotFound(_))
+        ));
+
+        // The application never answers
+        let (_cancel, response) = player.send_hls_request_cancellable(server_id, hls());
+        let response = rt.spawn(response);
+        let stream_id = stream_requested(&rt);
+        let cancelled = stream_cancelled(&rt);
+        assert_eq!(
+            cancelled,
+            Some((stream_id.clone(), StreamCancelReason::Expired))
+        );
+        assert!(matches!(
+            server.send_hls_chunk(stream_id, vec![1]),
+            Err(P2pError::StreamNotFound(_))
+        ));
+        assert!(rt.block_on(response).unwrap().is_err());
+
+        player.shutdown(std::time::Duration::ZERO).unwrap();
+        server.shutdown(std::time::Duration::ZERO).unwrap();
+    }
+
+    #[test]
+    fn test_authenticate_sets_session_for_later_requests() {
+        let (server, player, rt) = start_pair(local_config(), local_config());
+        let server_id = server.node_id().to_string();
+        let player_id = player.node_id().to_string();
+
+        let authenticate = MydiaRequest::Authenticate(AuthenticateRequest {
+            token: "device-token".to_string(),
+        });
+        let (_cancel, response) = player.send_request_cancellable(server_id.clone(), authenticate);
+        let response = rt.spawn(response);
+        let (request_id, token) = wait_for_event(&rt, &server, |event| match event {
+            Event::RequestReceived {
+                session: None,
+                request: MydiaRequest::Authenticate(req),
+                request_id,
+                ..
+            } => Some((request_id, req.token)),
+            _ => None,
+        })
+        .expect("authenticate request should arrive");
+        assert_eq!(token, "device-token");
+
+        let session = Session {
+            principal: "user-1".to_string(),
+            scopes: vec!["graphql".to_string()],
+        };
+        server
+            .send_response(request_id, MydiaResponse::Authenticated(session.clone()))
+            .unwrap();
+        let response = rt.block_on(response).unwrap();
+        assert_eq!(response, Ok(MydiaResponse::Authenticated(session.clone())));
+
+        // Later requests on the connection carry the session
+        let (_cancel, response) =
+            player.send_request_cancellable(server_id, MydiaRequest::Custom(vec![1]));
+        let _pending = rt.spawn(response);
+        let received = wait_for_event(&rt, &server, |event| match event {
+            Event::RequestReceived { session, .. } => Some(session),
+            _ => None,
+        });
+        assert_eq!(received, Some(Some(session)));
+
+        // Revoking the session closes the connection
+        assert!(server.revoke_session(player_id.clone()).unwrap());
+        let disconnected = wait_for_event(&rt, &server, |event| match event {
+            Event::Disconnected(peer_id) => Some(peer_id),
+            _ => None,
+        });
+        assert_eq!(disconnected, Some(player_id.clone()));
+        assert!(!server.revoke_session(player_id).unwrap());
+
+        player.shutdown(std::time::Duration::ZERO).unwrap();
+        server.shutdown(std::time::Duration::ZERO).unwrap();
+    }
+
+    #[test]
+    fn test_banned_peer_is_told_why_and_not_redialed() {
+        let (server, player, rt) = start_pair(
+            local_config(),
+            HostConfig {
+                reconnect: Some(ReconnectPolicy::default()),
+                ..local_config()
+            },
+        );
+        let player_id = player.node_id().to_string();
+
+        server.ban_peer(player_id.clone(), None).unwrap();
+        let closed = wait_for_event(&rt, &player, |event| match event {
+            Event::PeerClosed { reason, .. } => Some(reason),
+            Event::Reconnecting { .. } => panic!("a banned peer should not be redialed"),
+            _ => None,
+        });
+        assert_eq!(closed, Some(DisconnectReason::Banned));
+
+        // Dialing again is refused until the ban is lifted
+        let result = player.dial(server.get_node_addr());
+        assert!(matches!(
+            result,
+            Err(P2pError::Refused(DisconnectReason::Banned))
+        ));
+        assert!(server.unban_peer(player_id.clone()).unwrap());
+        player.dial(server.get_node_addr()).unwrap();
+
+        assert!(server
+            .disconnect_peer(player_id, DisconnectReason::Revoked)
+            .unwrap());
+        let closed = wait_for_event(&rt, &player, |event| match event {
+            Event::PeerClosed { reason, .. } => Some(reason),
+            _ => None,
+        });
+        assert_eq!(closed, Some(DisconnectReason::Revoked));
+
+        player.shutdown(std::time::Duration::ZERO).unwrap();
+        server.shutdown(std::time::Duration::ZERO).unwrap();
+    }
+
+    #[test]
+    fn test_over_limit_requests_are_rejected_and_counted() {
+        let (server, player, rt) = start_pair(
+            HostConfig {
+                request_limits: RequestLimits {
+                    requests_per_second: 1,
+                    burst: 2,
+                    ..Default::default()
+                },
+                ..local_config()
+            },
+            local_config(),
+        );
+        let server_id = server.node_id().to_string();
+
+        for _ in 0..2 {
+            let pong = rt.block_on(player.send_request(server_id.clone(), MydiaRequest::Ping));
+            assert!(matches!(pong, Ok(MydiaResponse::Pong)));
+        }
+        let result = rt.block_on(player.send_request(server_id.clone(), MydiaRequest::Ping));
+        match result {
+            Err(e) => {
+                assert_eq!(e.code(), Some(WireError::TOO_MANY_REQUESTS));
+                assert!(e.is_retryable());
+            }
+            Ok(response) => panic!("expected the request to be rejected, got {:?}", response),
+        }
+
+        assert_eq!(server.get_network_stats().rejected_requests, 1);
+        let peers = server.list_peers();
+        assert_eq!(peers[0].rejected_requests, 1);
+
+        // Reconnecting doesn't refill the bucket
+        let player_id = player.node_id().to_string();
+        assert!(server
+            .disconnect_peer(player_id, DisconnectReason::Disconnected)
+            .unwrap());
+        wait_for_event(&rt, &player, |event| match event {
+            Event::PeerClosed { .. } => Some(()),
+            _ => None,
+        })
+        .expect("player should be disconnected");
+        player.dial(server.get_node_addr()).unwrap();
+        let result = rt.block_on(player.send_request(server_id, MydiaRequest::Ping));
+        assert!(matches!(
+            result,
+            Err(P2pError::Remote {
+                code: WireError::TOO_MANY_REQUESTS,
+                ..
+            })
+        ));
+        assert_eq!(server.get_network_stats().rejected_requests, 2);
+
+        player.shutdown(std::time::Duration::ZERO).unwrap();
+        server.shutdown(std::time::Duration::ZERO).unwrap();
+    }
+
+    /// In-memory stand-in for a pkarr relay: stores the body of
+    /// `PUT /pkarr/<key>` and serves it back on `GET /pkarr/<key>`
+    async fn run_pkarr_stand_in() -> String {
+        use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/pkarr", listener.local_addr().unwrap());
+        let packets: Arc<Mutex<HashMap<String, Vec<u8>>>> = Default::default();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let packets = packets.clone();
+                tokio::spawn(async move {
+                    let mut stream = BufReader::new(stream);
+                    let mut request_line = String::new();
+                    stream.read_line(&mut request_line).await.unwrap();
+                    let mut content_length = 0;
+                    loop {
+                        let mut header = String::new();
+                        stream.read_line(&mut header).await.unwrap();
+                        if header.trim().is_empty() {
+                            break;
+                        }
+                        if let Some((name, value)) = header.split_once(':') {
+                            if name.eq_ignore_ascii_case("content-length") {
+                                content_length = value.trim().parse().unwrap();
+                            }
+                        }
+                    }
+                    let mut parts = request_line.split_whitespace();
+                    let (method, path) = (parts.next().unwrap(), parts.next().unwrap());
+                    let (status, body) = match method {
+                        "PUT" => {
+                            let mut body = vec![0; content_length];
+                            stream.read_exact(&mut body).await.unwrap();
+                            packets.lock().await.insert(path.to_string(), body);
+                            ("204 No Content", Vec::new())
+                        }
+                        _ => match packets.lock().await.get(path) {
+                            Some(body) => ("200 OK", body.clone()),
+                            None => ("404 Not Found", Vec::new()),
+                        },
+                    };
+                    let head = format!(
+                        "HTTP/1.1 {}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+                        status,
+                        body.len()
+                    );
+                    let stream = stream.get_mut();
+                    stream.write_all(head.as_bytes()).await.unwrap();
+                    stream.write_all(&body).await.unwrap();
+                });
+            }
+        });
+        url
+    }
+
+    #[test]
+    fn test_dial_by_node_id_through_lookup_service() {
+        let rt = Runtime::new().unwrap();
+        let url = rt.block_on(run_pkarr_stand_in());
+        let config = HostConfig {
+            lookup: LookupService::Pkarr(url),
+            ..local_config()
+        };
+        let server = Host::start(config.clone()).unwrap();
+        let player = Host::start(config).unwrap();
+
+        // Publishing happens in the background, retry until the record is there
+        let server_id = server.node_id().to_string();
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+        while player.dial(server_id.clone()).is_err() {
+            assert!(
+                std::time::Instant::now() < deadline,
+                "server never published"
+            );
+            std::thread::sleep(std::time::Duration::from_millis(200));
+        }
+        let connected = wait_for_event(&rt, &server, |event| match event {
+            Event::Connected { peer_id, .. } => Some(peer_id),
+            _ => None,
+        });
+        assert_eq!(connected.as_deref(), Some(player.node_id()));
+
+        player.shutdown(std::time::Duration::ZERO).unwrap();
+        server.shutdown(std::time::Duration::ZERO).unwrap();
+    }
+
+    #[test]
+    fn test_invalid_lookup_service_url_is_rejected() {
+        let config = HostConfig {
+            lookup: LookupService::Pkarr("not a url".to_string()),
+            ..local_config()
+        };
+        assert!(matches!(
+            Host::start(config),
+            Err(P2pError::InvalidConfig(_))
+        ));
+    }
 }
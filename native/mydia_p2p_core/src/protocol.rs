@@ -0,0 +1,529 @@
+//! Protocol version negotiation.
+//!
+//! Every supported protocol version is advertised as its own ALPN
+//! (`/mydia/<major>.<minor>.0`), so QUIC picks a version both sides speak
+//! during the TLS handshake. Peers on 1.1 or newer then exchange a
+//! [`Handshake`] on the first bidirectional stream to confirm the version and
+//! agree on the optional capabilities they share. 1.0 peers predate the
+//! handshake and are served with no optional capabilities.
+//!
+//! With the [`CAP_FRAMED_MESSAGES`] capability, request/response bodies are
+//! sent as a sequence of length-prefixed chunks terminated by a zero-length
+//! frame (the same layout as the HLS data path), so message size is bounded
+//! only by the configured maximum rather than a fixed read buffer.
+
+use crate::error::P2pError;
+use iroh::endpoint::{Connection, ConnectionError};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// How long either side waits for the peer's handshake before giving up
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Upper bound on an encoded handshake message
+const MAX_HANDSHAKE_SIZE: usize = 16 * 1024;
+
+/// Size of each chunk when writing a framed message
+const MESSAGE_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Default upper bound on a single request or response message
+pub const DEFAULT_MAX_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
+
+/// QUIC application error code used to reset or stop a stream when the
+/// client cancels a request
+pub const STREAM_CANCELLED: u32 = 1;
+
+/// QUIC application error code used by the server to reset an HLS stream that
+/// sat idle past its TTL
+pub const STREAM_EXPIRED: u32 = 2;
+
+/// Request/response bodies are chunked with length prefixes instead of
+/// being delimited by the end of the stream
+pub const CAP_FRAMED_MESSAGES: &str = "framed_messages";
+
+/// Errors are sent as `MydiaResponse::Failure` with a numeric code instead of
+/// free-text `MydiaResponse::Error`
+pub const CAP_STRUCTURED_ERRORS: &str = "structured_errors";
+
+/// The connection can be authenticated once with `MydiaRequest::Authenticate`
+/// instead of passing a token with every request
+pub const CAP_SESSION_AUTH: &str = "session_auth";
+
+/// Why a host closed a connection on purpose. Sent to the peer as the QUIC
+/// application close code, so it can tell the user instead of redialing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisconnectReason {
+    /// Dropped by the host without a more specific reason
+    Disconnected,
+    /// The peer's device was revoked
+    Revoked,
+    /// The peer's node id is banned
+    Banned,
+    /// The connection's authenticated session was revoked
+    SessionRevoked,
+}
+
+impl DisconnectReason {
+    /// QUIC application close code. 0 is left for ordinary closes.
+    pub fn code(&self) -> u32 {
+        match self {
+            DisconnectReason::SessionRevoked => 3,
+            DisconnectReason::Disconnected => 4,
+            DisconnectReason::Revoked => 5,
+            DisconnectReason::Banned => 6,
+        }
+    }
+
+    pub fn from_code(code: u64) -> Option<Self> {
+        match code {
+            3 => Some(DisconnectReason::SessionRevoked),
+            4 => Some(DisconnectReason::Disconnected),
+            5 => Some(DisconnectReason::Revoked),
+            6 => Some(DisconnectReason::Banned),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DisconnectReason::Disconnected => "disconnected",
+            DisconnectReason::Revoked => "revoked",
+            DisconnectReason::Banned => "banned",
+            DisconnectReason::SessionRevoked => "session_revoked",
+        }
+    }
+
+    /// The reason the remote side closed a connection with, if it gave one
+    pub(crate) fn from_close(error: &ConnectionError) -> Option<Self> {
+        match error {
+            ConnectionError::ApplicationClosed(close) => {
+                DisconnectReason::from_code(close.error_code.into_inner())
+            }
+            _ => None,
+        }
+    }
+
+    /// Close a connection, telling the peer why
+    pub(crate) fn close(&self, conn: &Connection) {
+        conn.close(self.code().into(), self.as_str().as_bytes());
+    }
+}
+
+impl std::fmt::Display for DisconnectReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A Mydia wire protocol version.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize,
+)]
+pub struct ProtocolVersion {
+    pub major: u16,
+    pub minor: u16,
+}
+
+impl ProtocolVersion {
+    /// Original protocol: raw CBOR request/response, no handshake
+    pub const V1_0: ProtocolVersion = ProtocolVersion { major: 1, minor: 0 };
+    /// Adds the post-connect handshake and capability exchange
+    pub const V1_1: ProtocolVersion = ProtocolVersion { major: 1, minor: 1 };
+    /// The newest version this build speaks
+    pub const CURRENT: ProtocolVersion = ProtocolVersion::V1_1;
+
+    /// ALPN identifier used to advertise this version
+    pub fn alpn(&self) -> Vec<u8> {
+        format!("/mydia/{}.{}.0", self.major, self.minor).into_bytes()
+    }
+
+    /// Parse a version from its ALPN identifier, if it is one we support
+    pub fn from_alpn(alpn: &[u8]) -> Option<Self> {
+        SUPPORTED_VERSIONS
+            .iter()
+            .copied()
+            .find(|version| version.alpn() == alpn)
+    }
+
+    /// Whether peers on this version exchange a [`Handshake`] after connecting
+    pub fn has_handshake(&self) -> bool {
+        *self >= ProtocolVersion::V1_1
+    }
+}
+
+impl std::fmt::Display for ProtocolVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+/// Versions this build accepts, newest first
+pub const SUPPORTED_VERSIONS: &[ProtocolVersion] = &[ProtocolVersion::V1_1, ProtocolVersion::V1_0];
+
+/// Optional capabilities this build offers during the handshake
+pub const LOCAL_CAPABILITIES: &[&str] =
+    &[CAP_FRAMED_MESSAGES, CAP_STRUCTURED_ERRORS, CAP_SESSION_AUTH];
+
+/// ALPN identifiers for every supported version, in preference order
+pub(crate) fn supported_alpns() -> Vec<Vec<u8>> {
+    SUPPORTED_VERSIONS.iter().map(|v| v.alpn()).collect()
+}
+
+/// Handshake message exchanged once per connection on 1.1+ peers.
+///
+/// The dialer sends the version it negotiated via ALPN and the capabilities it
+/// offers; the acceptor replies with the same version and the subset of those
+/// capabilities it also supports.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Handshake {
+    pub version: ProtocolVersion,
+    pub capabilities: Vec<String>,
+}
+
+/// The protocol version and capabilities agreed on with a connected peer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerProtocol {
+    pub version: ProtocolVersion,
+    pub capabilities: Vec<String>,
+}
+
+impl PeerProtocol {
+    /// Whether the peer agreed on the given capability
+    pub fn supports(&self, capability: &str) -> bool {
+        self.capabilities.iter().any(|c| c == capability)
+    }
+}
+
+/// Capabilities offered by the remote side that we also support
+fn shared_capabilities(offered: &[String]) -> Vec<String> {
+    offered
+        .iter()
+        .filter(|c| LOCAL_CAPABILITIES.contains(&c.as_str()))
+        .cloned()
+        .collect()
+}
+
+/// Determine the protocol version negotiated via ALPN for a connection
+fn negotiated_version(conn: &Connection) -> Result<ProtocolVersion, P2pError> {
+    ProtocolVersion::from_alpn(conn.alpn()).ok_or_else(|| {
+        P2pError::Protocol(format!(
+            "Unsupported ALPN: {}",
+            String::from_utf8_lossy(conn.alpn())
+        ))
+    })
+}
+
+/// Write a single length-prefixed frame (4-byte big-endian length, then data)
+pub(crate) async fn write_frame<W: AsyncWrite + Unpin>(
+    send: &mut W,
+    data: &[u8],
+) -> Result<(), P2pError> {
+    let len = data.len() as u32;
+    send.write_all(&len.to_be_bytes())
+        .await
+        .map_err(|e| P2pError::Io(format!("Failed to write frame length: {}", e)))?;
+    send.write_all(data)
+        .await
+        .map_err(|e| P2pError::Io(format!("Failed to write frame: {}", e)))
+}
+
+/// Read a single length-prefixed frame, rejecting frames larger than `max_len`
+pub(crate) async fn read_frame<R: AsyncRead + Unpin>(
+    recv: &mut R,
+    max_len: usize,
+) -> Result<Vec<u8>, P2pError> {
+    let mut len_buf = [0u8; 4];
+    recv.read_exact(&mut len_buf)
+        .await
+        .map_err(|e| P2pError::Io(format!("Failed to read frame length: {}", e)))?;
+    let len = u32::from_be_bytes(len_buf) as usize;
+    if len > max_len {
+        return Err(P2pError::Protocol(format!(
+            "Frame too large: {} > {}",
+            len, max_len
+        )));
+    }
+
+    let mut data = vec![0u8; len];
+    recv.read_exact(&mut data)
+        .await
+        .map_err(|e| P2pError::Io(format!("Failed to read frame: {}", e)))?;
+    Ok(data)
+}
+
+/// Write an encoded request or response using the framing agreed on with the peer.
+/// The caller is responsible for finishing the stream.
+pub(crate) async fn write_message<W: AsyncWrite + Unpin>(
+    send: &mut W,
+    data: &[u8],
+    protocol: &PeerProtocol,
+) -> Result<(), P2pError> {
+    if !protocol.supports(CAP_FRAMED_MESSAGES) {
+        return send
+            .write_all(data)
+            .await
+            .map_err(|e| P2pError::Io(format!("Failed to write message: {}", e)));
+    }
+
+    for chunk in data.chunks(MESSAGE_CHUNK_SIZE) {
+        write_frame(send, chunk).await?;
+    }
+    send.write_all(&0u32.to_be_bytes())
+        .await
+        .map_err(|e| P2pError::Io(format!("Failed to write terminator: {}", e)))
+}
+
+/// Read an encoded request or response using the framing agreed on with the peer,
+/// rejecting messages larger than `max_len`
+pub(crate) async fn read_message<R: AsyncRead + Unpin>(
+    recv: &mut R,
+    max_len: usize,
+    protocol: &PeerProtocol,
+) -> Result<Vec<u8>, P2pError> {
+    let mut data = Vec::new();
+
+    if !protocol.supports(CAP_FRAMED_MESSAGES) {
+        // Legacy peers delimit the message by finishing the stream
+        recv.take(max_len as u64 + 1)
+            .read_to_end(&mut data)
+            .await
+            .map_err(|e| P2pError::Io(format!("Failed to read message: {}", e)))?;
+        if data.len() > max_len {
+            return Err(P2pError::Protocol(format!(
+                "Message too large: exceeds {} bytes",
+                max_len
+            )));
+        }
+        return Ok(data);
+    }
+
+    loop {
+        let mut len_buf = [0u8; 4];
+        recv.read_exact(&mut len_buf)
+            .await
+            .map_err(|e| P2pError::Io(format!("Failed to read chunk length: {}", e)))?;
+        let len = u32::from_be_bytes(len_buf) as usize;
+
+        // Zero length indicates end of message
+        if len == 0 {
+            return Ok(data);
+        }
+        if data.len() + len > max_len {
+            return Err(P2pError::Protocol(format!(
+                "Message too large: exceeds {} bytes",
+                max_len
+            )));
+        }
+
+        let start = data.len();
+        data.resize(start + len, 0);
+        recv.read_exact(&mut data[start..])
+            .await
+            .map_err(|e| P2pError::Io(format!("Failed to read chunk: {}", e)))?;
+    }
+}
+
+/// Run the dialer side of the handshake
+pub(crate) async fn client_handshake(conn: &Connection) -> Result<PeerProtocol, P2pError> {
+    let version = negotiated_version(conn)?;
+    if !version.has_handshake() {
+        return Ok(PeerProtocol {
+            version,
+            capabilities: Vec::new(),
+        });
+    }
+
+    let exchange = async {
+        let (mut send, mut recv) = conn
+            .open_bi()
+            .await
+            .map_err(|e| P2pError::Io(format!("Failed to open handshake stream: {}", e)))?;
+
+        let hello = Handshake {
+            version,
+            capabilities: LOCAL_CAPABILITIES.iter().map(|c| c.to_string()).collect(),
+        };
+        let data = serde_cbor::to_vec(&hello)
+            .map_err(|e| P2pError::Protocol(format!("Failed to encode handshake: {}", e)))?;
+        write_frame(&mut send, &data).await?;
+        send.finish()
+            .map_err(|e| P2pError::Io(format!("Failed to finish handshake: {}", e)))?;
+
+        let data = read_frame(&mut recv, MAX_HANDSHAKE_SIZE).await?;
+        let reply: Handshake = serde_cbor::from_slice(&data)
+            .map_err(|e| P2pError::Protocol(format!("Failed to decode handshake: {}", e)))?;
+
+        if reply.version != version {
+            return Err(P2pError::Protocol(format!(
+                "Handshake version mismatch: negotiated {}, peer replied {}",
+                version, reply.version
+            )));
+        }
+
+        Ok(PeerProtocol {
+            version,
+            capabilities: shared_capabilities(&reply.capabilities),
+        })
+    };
+
+    tokio::time::timeout(HANDSHAKE_TIMEOUT, exchange)
+        .await
+        .map_err(|_| P2pError::Timeout)?
+}
+
+/// Run the acceptor side of the handshake
+pub(crate) async fn server_handshake(conn: &Connection) -> Result<PeerProtocol, P2pError> {
+    let version = negotiated_version(conn)?;
+    if !version.has_handshake() {
+        return Ok(PeerProtocol {
+            version,
+            capabilities: Vec::new(),
+        });
+    }
+
+    let exchange = async {
+        let (mut send, mut recv) = conn
+            .accept_bi()
+            .await
+            .map_err(|e| P2pError::Io(format!("Failed to accept handshake stream: {}", e)))?;
+
+        let data = read_frame(&mut recv, MAX_HANDSHAKE_SIZE).await?;
+        let hello: Handshake = serde_cbor::from_slice(&data)
+            .map_err(|e| P2pError::Protocol(format!("Failed to decode handshake: {}", e)))?;
+
+        if hello.version != version {
+            return Err(P2pError::Protocol(format!(
+                "Handshake version mismatch: negotiated {}, peer sent {}",
+                version, hello.version
+            )));
+        }
+
+        let reply = Handshake {
+            version,
+            capabilities: shared_capabilities(&hello.capabilities),
+        };
+        let data = serde_cbor::to_vec(&reply)
+            .map_err(|e| P2pError::Protocol(format!("Failed to encode handshake: {}", e)))?;
+        write_frame(&mut send, &data).await?;
+        send.finish()
+            .map_err(|e| P2pError::Io(format!("Failed to finish handshake: {}", e)))?;
+
+        Ok(PeerProtocol {
+            version,
+            capabilities: reply.capabilities,
+        })
+    };
+
+    tokio::time::timeout(HANDSHAKE_TIMEOUT, exchange)
+        .await
+        .map_err(|_| P2pError::Timeout)?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_alpn_round_trip() {
+        for version in SUPPORTED_VERSIONS {
+            assert_eq!(ProtocolVersion::from_alpn(&version.alpn()), Some(*version));
+        }
+        assert_eq!(ProtocolVersion::V1_0.alpn(), b"/mydia/1.0.0".to_vec());
+        assert_eq!(ProtocolVersion::from_alpn(b"/mydia/9.9.0"), None);
+    }
+
+    #[test]
+    fn test_handshake_only_from_1_1() {
+        assert!(!ProtocolVersion::V1_0.has_handshake());
+        assert!(ProtocolVersion::V1_1.has_handshake());
+    }
+
+    #[test]
+    fn test_handshake_serialization() {
+        let handshake = Handshake {
+            version: ProtocolVersion::CURRENT,
+            capabilities: vec!["future_feature".to_string()],
+        };
+        let data = serde_cbor::to_vec(&handshake).unwrap();
+        let decoded: Handshake = serde_cbor::from_slice(&data).unwrap();
+        assert_eq!(handshake, decoded);
+    }
+
+    #[test]
+    fn test_unknown_capabilities_are_dropped() {
+        let offered = vec![
+            "from_the_future".to_string(),
+            CAP_FRAMED_MESSAGES.to_string(),
+        ];
+        assert_eq!(
+            shared_capabilities(&offered),
+            vec![CAP_FRAMED_MESSAGES.to_string()]
+        );
+    }
+
+    fn framed() -> PeerProtocol {
+        PeerProtocol {
+            version: ProtocolVersion::CURRENT,
+            capabilities: vec![CAP_FRAMED_MESSAGES.to_string()],
+        }
+    }
+
+    fn legacy() -> PeerProtocol {
+        PeerProtocol {
+            version: ProtocolVersion::V1_0,
+            capabilities: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_framed_message_larger_than_one_chunk() {
+        let message: Vec<u8> = (0..(3 * MESSAGE_CHUNK_SIZE + 17))
+            .map(|i| (i % 251) as u8)
+            .collect();
+        let (mut client, mut server) = tokio::io::duplex(MESSAGE_CHUNK_SIZE);
+
+        let writer = {
+            let message = message.clone();
+            tokio::spawn(async move { write_message(&mut client, &message, &framed()).await })
+        };
+        let read = read_message(&mut server, DEFAULT_MAX_MESSAGE_SIZE, &framed())
+            .await
+            .unwrap();
+        writer.await.unwrap().unwrap();
+
+        assert_eq!(read, message);
+    }
+
+    #[tokio::test]
+    async fn test_framed_message_over_limit_is_rejected() {
+        let message = vec![7u8; 2 * MESSAGE_CHUNK_SIZE];
+        let (mut client, mut server) = tokio::io::duplex(4 * MESSAGE_CHUNK_SIZE);
+
+        write_message(&mut client, &message, &framed())
+            .await
+            .unwrap();
+        let result = read_message(&mut server, MESSAGE_CHUNK_SIZE, &framed()).await;
+
+        assert!(matches!(result, Err(P2pError::Protocol(e)) if e.starts_with("Message too large")));
+    }
+
+    #[tokio::test]
+    async fn test_legacy_message_reads_to_end_of_stream() {
+        let message = b"legacy request".to_vec();
+        let (mut client, mut server) = tokio::io::duplex(1024);
+
+        write_message(&mut client, &message, &legacy())
+            .await
+            .unwrap();
+        drop(client);
+        let read = read_message(&mut server, 1024, &legacy()).await.unwrap();
+
+        assert_eq!(read, message);
+        let (mut client, mut server) = tokio::io::duplex(1024);
+        write_message(&mut client, &message, &legacy())
+            .await
+            .unwrap();
+        drop(client);
+        assert!(read_message(&mut server, 4, &legacy()).await.is_err());
+    }
+}
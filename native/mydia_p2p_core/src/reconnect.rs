@@ -0,0 +1,107 @@
+//! Redialing peers whose connection dropped.
+
+use rand::Rng;
+use std::time::Duration;
+
+/// How a Host redials peers it dialed itself once their connection drops.
+///
+/// Reconnection is off unless a policy is set in `HostConfig::reconnect`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReconnectPolicy {
+    /// Delay before the first redial attempt
+    pub initial_backoff: Duration,
+    /// Upper bound on the delay between attempts
+    pub max_backoff: Duration,
+    /// Random spread applied to each delay, as a fraction of it (0.0 - 1.0)
+    pub jitter: f64,
+    /// Give up after this many failed attempts. None retries forever.
+    pub max_attempts: Option<u32>,
+    /// Hold requests made while reconnecting until the peer is back instead
+    /// of failing them straight away with `P2pError::NotConnected`
+    pub queue_requests: bool,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        ReconnectPolicy {
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            jitter: 0.2,
+            max_attempts: Some(10),
+            queue_requests: true,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// Delay before the given attempt (starting at 1), doubling each time
+    /// up to `max_backoff`
+    fn base_delay(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+
+    /// Delay before the given attempt (starting at 1) with jitter applied
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let base = self.base_delay(attempt);
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        if jitter == 0.0 {
+            return base;
+        }
+        let factor = rand::rng().random_range((1.0 - jitter)..=(1.0 + jitter));
+        base.mul_f64(factor)
+    }
+
+    /// Whether the policy stops after `attempt` failed attempts
+    pub fn gives_up_after(&self, attempt: u32) -> bool {
+        self.max_attempts.is_some_and(|max| attempt >= max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_doubles_up_to_max() {
+        let policy = ReconnectPolicy {
+            jitter: 0.0,
+            ..Default::default()
+        };
+        assert_eq!(policy.backoff(1), Duration::from_millis(500));
+        assert_eq!(policy.backoff(2), Duration::from_secs(1));
+        assert_eq!(policy.backoff(4), Duration::from_secs(4));
+        assert_eq!(policy.backoff(10), Duration::from_secs(30));
+        assert_eq!(policy.backoff(100), Duration::from_secs(30));
+    }
+
+    #[test]
+    fn test_backoff_jitter_bounds() {
+        let policy = ReconnectPolicy {
+            jitter: 0.5,
+            ..Default::default()
+        };
+        for _ in 0..100 {
+            let delay = policy.backoff(3);
+            assert!(delay >= Duration::from_secs(1) && delay <= Duration::from_secs(3));
+        }
+    }
+
+    #[test]
+    fn test_gives_up_after_max_attempts() {
+        let policy = ReconnectPolicy {
+            max_attempts: Some(3),
+            ..Default::default()
+        };
+        assert!(!policy.gives_up_after(2));
+        assert!(policy.gives_up_after(3));
+
+        let forever = ReconnectPolicy {
+            max_attempts: None,
+            ..Default::default()
+        };
+        assert!(!forever.gives_up_after(u32::MAX));
+    }
+}
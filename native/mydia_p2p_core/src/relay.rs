@@ -0,0 +1,326 @@
+//! Choosing which relay servers the endpoint uses.
+
+use crate::P2pError;
+use iroh::{defaults::prod as default_relays, RelayConfig, RelayMap, RelayMode, RelayUrl};
+use iroh_relay::http::RELAY_PROBE_PATH;
+use iroh_relay::RelayQuicConfig;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+/// Give up on a relay probe after this long
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Which relay servers a Host uses for NAT traversal.
+///
+/// Peers can always connect directly; relays only help when they can't.
+/// iroh picks the home relay among them from its own latency measurements;
+/// `Host::relay_health` only reports on them and doesn't change that choice.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum RelayPolicy {
+    /// iroh's production relays
+    #[default]
+    Default,
+    /// Only the given relay URLs
+    Custom(Vec<String>),
+    /// The given relay URLs, falling back to iroh's production relays
+    CustomWithDefaults(Vec<String>),
+    /// No relays at all (direct/LAN connections only)
+    Disabled,
+}
+
+impl RelayPolicy {
+    /// Return a string representation of the policy, without its URLs
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RelayPolicy::Default => "default",
+            RelayPolicy::Custom(_) => "custom",
+            RelayPolicy::CustomWithDefaults(_) => "custom_with_defaults",
+            RelayPolicy::Disabled => "disabled",
+        }
+    }
+
+    /// Whether the endpoint has any relay to connect to
+    pub fn is_enabled(&self) -> bool {
+        !matches!(self, RelayPolicy::Disabled)
+    }
+
+    /// URLs of every relay the endpoint may use under this policy
+    pub(crate) fn relay_urls(&self) -> Vec<String> {
+        self.relay_mode()
+            .map(|mode| {
+                mode.relay_map()
+                    .urls::<Vec<_>>()
+                    .iter()
+                    .map(|url| url.to_string())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Build the iroh relay mode for this policy
+    pub(crate) fn relay_mode(&self) -> Result<RelayMode, P2pError> {
+        match self {
+            RelayPolicy::Default => Ok(RelayMode::Default),
+            RelayPolicy::Disabled => Ok(RelayMode::Disabled),
+            RelayPolicy::Custom(urls) => {
+                Ok(RelayMode::Custom(RelayMap::from_iter(custom_relays(urls)?)))
+            }
+            RelayPolicy::CustomWithDefaults(urls) => {
+                let defaults = [
+                    default_relays::default_na_east_relay(),
+                    default_relays::default_eu_relay(),
+                    default_relays::default_ap_relay(),
+                ];
+                Ok(RelayMode::Custom(RelayMap::from_iter(
+                    custom_relays(urls)?.into_iter().chain(defaults),
+                )))
+            }
+        }
+    }
+}
+
+/// Parse custom relay URLs, with QUIC enabled on each
+fn custom_relays(urls: &[String]) -> Result<Vec<RelayConfig>, P2pError> {
+    if urls.is_empty() {
+        return Err(P2pError::InvalidConfig(
+            "Custom relay policy needs at least one relay URL".to_string(),
+        ));
+    }
+    urls.iter()
+        .map(|url| {
+            let parsed = url.parse::<RelayUrl>().map_err(|e| {
+                P2pError::InvalidConfig(format!("Invalid relay URL {}: {}", url, e))
+            })?;
+            Ok(RelayConfig {
+                url: parsed,
+                quic: Some(RelayQuicConfig::default()),
+            })
+        })
+        .collect()
+}
+
+/// Health of a single relay server, as measured by the host's probes.
+///
+/// Every relay's HTTPS probe endpoint is requested at startup and again every
+/// `HostConfig::relay_probe_interval`. This is a report only: iroh chooses
+/// the home relay by itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelayHealth {
+    pub url: String,
+    /// Whether this is the home relay the endpoint is connected to
+    pub home: bool,
+    /// Whether the relay answered the latest probe
+    pub reachable: bool,
+    /// Round-trip time of the latest probe
+    pub latency_ms: Option<u64>,
+    /// Why the last failed probe failed, kept after the relay recovers.
+    /// None if it never failed.
+    pub last_error: Option<String>,
+}
+
+/// Measures relay latency with an HTTPS request to each relay's probe endpoint
+pub(crate) struct RelayProber {
+    client: reqwest::Client,
+}
+
+impl RelayProber {
+    pub(crate) fn new() -> Result<Self, reqwest::Error> {
+        let client = reqwest::Client::builder()
+            .use_rustls_tls()
+            .redirect(reqwest::redirect::Policy::none())
+            .timeout(PROBE_TIMEOUT)
+            .build()?;
+        Ok(RelayProber { client })
+    }
+
+    /// Probe every relay at once
+    pub(crate) async fn probe_all(
+        &self,
+        urls: &[String],
+    ) -> Vec<(String, Result<Duration, String>)> {
+        let probes = urls
+            .iter()
+            .map(|url| async move { (url.clone(), self.probe(url).await) });
+        futures::future::join_all(probes).await
+    }
+
+    /// Time one request to the relay's probe endpoint, or say why it failed
+    async fn probe(&self, url: &str) -> Result<Duration, String> {
+        let probe_url = url::Url::parse(url)
+            .and_then(|url| url.join(RELAY_PROBE_PATH))
+            .map_err(|e| format!("Invalid relay URL: {}", e))?;
+        let start = Instant::now();
+        let response = self
+            .client
+            .get(probe_url)
+            .send()
+            .await
+            .map_err(|e| format!("{:#}", anyhow::Error::from(e)))?;
+        let latency = start.elapsed();
+        if response.status().is_success() {
+            Ok(latency)
+        } else {
+            Err(format!("Probe answered with HTTP {}", response.status()))
+        }
+    }
+}
+
+/// Per-relay probe results collected by the event loop
+#[derive(Debug, Default)]
+pub(crate) struct RelayHealthTracker {
+    /// Latest latency keyed by relay URL. None if not probed yet or unreachable.
+    latencies: BTreeMap<String, Option<Duration>>,
+    last_errors: BTreeMap<String, String>,
+}
+
+impl RelayHealthTracker {
+    pub(crate) fn new(urls: Vec<String>) -> Self {
+        RelayHealthTracker {
+            latencies: urls.into_iter().map(|url| (url, None)).collect(),
+            last_errors: BTreeMap::new(),
+        }
+    }
+
+    /// Record a round of probes: each relay's latency, or why it failed
+    pub(crate) fn update(
+        &mut self,
+        results: impl IntoIterator<Item = (String, Result<Duration, String>)>,
+    ) {
+        for (url, result) in results {
+            match result {
+                Ok(latency) => {
+                    self.latencies.insert(url, Some(latency));
+                }
+                Err(error) => {
+                    self.latencies.insert(url.clone(), None);
+                    self.last_errors.insert(url, error);
+                }
+            }
+        }
+    }
+
+    /// Health of every relay, lowest latency first
+    pub(crate) fn report(&self, home: Option<&str>) -> Vec<RelayHealth> {
+        let mut report: Vec<RelayHealth> = self
+            .latencies
+            .iter()
+            .map(|(url, latency)| RelayHealth {
+                url: url.clone(),
+                home: home == Some(url.as_str()),
+                reachable: latency.is_some(),
+                latency_ms: latency.map(|l| l.as_millis() as u64),
+                last_error: self.last_errors.get(url).cloned(),
+            })
+            .collect();
+        report.sort_by_key(|relay| relay.latency_ms.unwrap_or(u64::MAX));
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_custom_policy_uses_only_given_relays() {
+        let policy = RelayPolicy::Custom(vec!["https://relay.example.com".to_string()]);
+        let RelayMode::Custom(map) = policy.relay_mode().unwrap() else {
+            panic!("expected a custom relay mode");
+        };
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn test_custom_with_defaults_adds_production_relays() {
+        let policy = RelayPolicy::CustomWithDefaults(vec!["https://relay.example.com".to_string()]);
+        let RelayMode::Custom(map) = policy.relay_mode().unwrap() else {
+            panic!("expected a custom relay mode");
+        };
+        assert_eq!(map.len(), 4);
+    }
+
+    #[test]
+    fn test_invalid_or_missing_custom_relays_are_rejected() {
+        let invalid = RelayPolicy::Custom(vec!["not a url".to_string()]);
+        assert!(matches!(
+            invalid.relay_mode(),
+            Err(P2pError::InvalidConfig(_))
+        ));
+
+        let empty = RelayPolicy::Custom(Vec::new());
+        assert!(matches!(
+            empty.relay_mode(),
+            Err(P2pError::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn test_health_tracks_latest_probe() {
+        let a = "https://a.example.com/".to_string();
+        let b = "https://b.example.com/".to_string();
+        let mut tracker = RelayHealthTracker::new(vec![a.clone(), b.clone()]);
+
+        // Not probed yet
+        let report = tracker.report(None);
+        assert!(report
+            .iter()
+            .all(|r| !r.reachable && r.last_error.is_none()));
+
+        tracker.update([
+            (a.clone(), Ok(Duration::from_millis(40))),
+            (b.clone(), Ok(Duration::from_millis(20))),
+        ]);
+        let report = tracker.report(Some(&b));
+        assert_eq!(report[0].url, b);
+        assert!(report[0].home);
+        assert_eq!(report[1].latency_ms, Some(40));
+
+        // b stops answering, then recovers
+        tracker.update([
+            (a.clone(), Ok(Duration::from_millis(40))),
+            (b.clone(), Err("connection refused".to_string())),
+        ]);
+        let report = tracker.report(Some(&a));
+        assert_eq!(report[1].url, b);
+        assert!(!report[1].reachable);
+        assert_eq!(report[1].last_error.as_deref(), Some("connection refused"));
+
+        tracker.update([(b.clone(), Ok(Duration::from_millis(20)))]);
+        let b_health = tracker.report(None).remove(0);
+        assert!(b_health.reachable);
+        assert_eq!(b_health.last_error.as_deref(), Some("connection refused"));
+    }
+
+    #[tokio::test]
+    async fn test_probe_reports_latency_or_the_error() {
+        // A relay that answers its probe endpoint
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let up = format!("http://{}/", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            use std::io::{Read, Write};
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0u8; 1024];
+            let _ = stream.read(&mut request);
+            let _ = stream.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n");
+        });
+        // Nothing listens on the discard port
+        let down = "http://127.0.0.1:9/".to_string();
+
+        let prober = RelayProber::new().unwrap();
+        let results = prober.probe_all(&[up.clone(), down.clone()]).await;
+        assert_eq!(results[0].0, up);
+        assert!(results[0].1.is_ok());
+        assert_eq!(results[1].0, down);
+        let error = results[1].1.as_ref().unwrap_err();
+        assert!(error.contains("refused"), "unexpected error: {}", error);
+    }
+
+    #[test]
+    fn test_disabled_policy() {
+        assert!(!RelayPolicy::Disabled.is_enabled());
+        assert!(matches!(
+            RelayPolicy::Disabled.relay_mode(),
+            Ok(RelayMode::Disabled)
+        ));
+    }
+}
@@ -3,6 +3,7 @@ import 'dart:convert';
 import 'dart:io';
 import 'package:flutter/foundation.dart';
 import 'package:flutter_riverpod/flutter_riverpod.dart';
+import 'package:path_provider/path_provider.dart';
 import 'package:player/native/lib.dart';
 
 /// Default iroh relay URL (our own relay).
@@ -130,6 +131,25 @@ class P2pStatus {
   }
 }
 
+/// A peer closed the connection on purpose, e.g. because this device was
+/// revoked. The connection is not redialed.
+class P2pPeerClosed {
+  final String peerId;
+
+  /// One of `disconnected`, `revoked`, `banned` or `session_revoked`
+  final String reason;
+
+  const P2pPeerClosed({required this.peerId, required this.reason});
+
+  /// Explanation to show the user
+  String get message => switch (reason) {
+        'revoked' => 'This device was removed from the server.',
+        'banned' => 'This device is blocked by the server.',
+        'session_revoked' => 'Your session ended. Please sign in again.',
+        _ => 'The server closed the connection.',
+      };
+}
+
 /// Max auto-reconnect attempts before giving up (reset on successful connect).
 const _maxAutoReconnectAttempts = 3;
 
@@ -153,6 +173,8 @@ class P2pService {
   String? _lastDialedEndpointAddr;
   int _autoReconnectAttempts = 0;
   Timer? _autoReconnectTimer;
+  // Peers that closed the connection on purpose, not to be redialed
+  final Set<String> _closedPeers = {};
 
   // Stream of P2P status updates
   final _statusController = StreamController<P2pStatus>.broadcast();
@@ -162,6 +184,10 @@ class P2pService {
   final _peerConnectedController = StreamController<String>.broadcast();
   Stream<String> get onPeerConnected => _peerConnectedController.stream;
 
+  // Stream of peers closing the connection on purpose
+  final _peerClosedController = StreamController<P2pPeerClosed>.broadcast();
+  Stream<P2pPeerClosed> get onPeerClosed => _peerClosedController.stream;
+
   /// Returns true if the P2P host is initialized
   bool get isInitialized => _isInitialized;
 
@@ -254,8 +280,18 @@ class P2pService {
       debugPrint(
           '[P2P] Initializing iroh-based P2P Host with relay: $effectiveRelayUrl');
 
-      // Initialize Host via FRB - returns (P2PHost, String)
-      final (host, nodeId) = P2PHost.init(relayUrl: effectiveRelayUrl);
+      // Remember the server's last known addresses across app restarts
+      final supportDir = await getApplicationSupportDirectory();
+      final addressBookPath = '${supportDir.path}/p2p_address_book.json';
+      // Keep the same node id across launches so the server recognizes us
+      final keypairPath = '${supportDir.path}/p2p_keypair.bin';
+
+      // Initialize Host via FRB - returns (P2PHost, String) once it is bound,
+      // or throws if the keypair can't be read or the port is taken
+      final (host, nodeId) = await P2PHost.init(
+          relayUrl: effectiveRelayUrl,
+          addressBookPath: addressBookPath,
+          keypairPath: keypairPath);
       _host = host;
       _nodeId = nodeId;
 
@@ -266,7 +302,7 @@ class P2pService {
         debugPrint('[P2P] Event: $event');
 
         if (event.startsWith('connected:')) {
-          // Format: "connected:<peer_id>:<connection_type>"
+          // Format: "connected:<peer_id>:<connection_type>:<protocol_version>"
           final parts = event.substring('connected:'.length).split(':');
           final peerId = parts.first;
           final connectionType = parts.length > 1 ? parts[1] : 'unknown';
@@ -286,6 +322,39 @@ class P2pService {
               '[P2P] Connection type changed: $peerId -> $connectionType');
           _currentConnectionType = _parseConnectionType(connectionType);
           _emitStatus();
+        } else if (event.startsWith('path_changed:')) {
+          // Format: "path_changed:<peer_id>:<remote_addr>" (addr may contain ':')
+          final rest = event.substring('path_changed:'.length);
+          final sep = rest.indexOf(':');
+          if (sep > 0) {
+            debugPrint(
+                '[P2P] Selected path for ${rest.substring(0, sep)}: ${rest.substring(sep + 1)}');
+          }
+        } else if (event.startsWith('reconnecting:')) {
+          // Format: "reconnecting:<peer_id>:<attempt>". The host redials on
+          // its own; "disconnected" only follows if it gives up.
+          final parts = event.substring('reconnecting:'.length).split(':');
+          debugPrint(
+              '[P2P] Reconnecting to ${parts.first} (attempt ${parts.length > 1 ? parts[1] : '?'})');
+        } else if (event.startsWith('reconnected:')) {
+          // Format: "reconnected:<peer_id>:<attempts>", sent after "connected:"
+          final parts = event.substring('reconnected:'.length).split(':');
+          debugPrint('[P2P] Reconnected to ${parts.first}');
+        } else if (event.startsWith('closed:')) {
+          // Format: "closed:<peer_id>:<reason>", sent before "disconnected:"
+          final parts = event.substring('closed:'.length).split(':');
+          final closed = P2pPeerClosed(
+              peerId: parts.first,
+              reason: parts.length > 1 ? parts[1] : 'disconnected');
+          debugPrint(
+              '[P2P] ${closed.peerId} closed the connection: ${closed.reason}');
+          _closedPeers.add(closed.peerId);
+          _peerClosedController.add(closed);
+        } else if (event.startsWith('discovered:')) {
+          // Format: "discovered:<node_id>:<addr>,<addr>" (addrs may contain ':')
+          final rest = event.substring('discovered:'.length);
+          final nodeId = rest.split(':').first;
+          debugPrint('[P2P] Discovered $nodeId on the local network');
         } else if (event.startsWith('disconnected:')) {
           final peerId = event.substring('disconnected:'.length);
           debugPrint('[P2P] Peer disconnected: $peerId');
@@ -294,11 +363,17 @@ class P2pService {
             _currentConnectionType = P2pConnectionType.none;
           }
           _emitStatus();
-          _scheduleAutoReconnect();
+          if (!_closedPeers.remove(peerId)) {
+            _scheduleAutoReconnect();
+          }
         } else if (event == 'relay_connected') {
           debugPrint('[P2P] Connected to relay');
           _isRelayConnected = true;
           _emitStatus();
+        } else if (event == 'relay_disconnected') {
+          debugPrint('[P2P] Disconnected from relay');
+          _isRelayConnected = false;
+          _emitStatus();
         } else if (event.startsWith('ready:')) {
           final nodeAddrJson = event.substring('ready:'.length);
           debugPrint('[P2P] Node ready with addr: $nodeAddrJson');
@@ -365,6 +440,8 @@ class P2pService {
 
   /// Dial a peer using their EndpointAddr JSON.
   /// This is the primary way to connect to a peer in iroh.
+  /// A bare node id also works once the server has published its address
+  /// or been found on the local network.
   Future<void> dial(String endpointAddrJson) async {
     if (_host == null) throw Exception("P2P host not initialized");
     debugPrint('[P2P] Dialing endpoint: $endpointAddrJson');
@@ -671,5 +748,6 @@ class P2pService {
     _isInitialized = false;
     await _statusController.close();
     await _peerConnectedController.close();
+    await _peerClosedController.close();
   }
 }
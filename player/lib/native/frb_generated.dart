@@ -81,17 +81,35 @@ class RustLib extends BaseEntrypoint<RustLibApi, RustLibApiImpl, RustLibWire> {
 }
 
 abstract class RustLibApi extends BaseApi {
+  Future<FlutterSession> crateP2PHostAuthenticate(
+      {required P2PHost that, required String peer, required String token});
+
   Future<void> crateP2PHostDial(
       {required P2PHost that, required String endpointAddrJson});
 
   Stream<String> crateP2PHostEventStream({required P2PHost that});
 
+  String crateP2PHostExportIdentity({required String keypairPath});
+
   Future<FlutterNetworkStats> crateP2PHostGetNetworkStats(
       {required P2PHost that});
 
   Future<String> crateP2PHostGetNodeAddr({required P2PHost that});
 
-  (P2PHost, String) crateP2PHostInit({String? relayUrl});
+  String crateP2PHostImportIdentity(
+      {required String keypairPath, required String identity});
+
+  Future<(P2PHost, String)> crateP2PHostInit(
+      {String? relayUrl,
+      String? relayMode,
+      String? addressBookPath,
+      String? keypairPath,
+      String? identity});
+
+  Future<List<FlutterPeerInfo>> crateP2PHostListPeers({required P2PHost that});
+
+  Future<List<FlutterRelayHealth>> crateP2PHostRelayHealth(
+      {required P2PHost that});
 
   Future<FlutterGraphQLResponse> crateP2PHostSendGraphqlRequest(
       {required P2PHost that,
@@ -113,6 +131,9 @@ abstract class RustLibApi extends BaseApi {
       required String peer,
       required FlutterPairingRequest req});
 
+  Future<void> crateP2PHostShutdown(
+      {required P2PHost that, required BigInt graceMs});
+
   Future<void> crateInitApp();
 
   RustArcIncrementStrongCountFnType get rust_arc_increment_strong_count_P2PHost;
@@ -130,6 +151,34 @@ class RustLibApiImpl extends RustLibApiImplPlatform implements RustLibApi {
     required super.portManager,
   });
 
+  @override
+  Future<FlutterSession> crateP2PHostAuthenticate(
+      {required P2PHost that, required String peer, required String token}) {
+    return handler.executeNormal(NormalTask(
+      callFfi: (port_) {
+        final serializer = SseSerializer(generalizedFrbRustBinding);
+        sse_encode_Auto_Ref_RustOpaque_flutter_rust_bridgefor_generatedRustAutoOpaqueInnerP2pHost(
+            that, serializer);
+        sse_encode_String(peer, serializer);
+        sse_encode_String(token, serializer);
+        pdeCallFfi(generalizedFrbRustBinding, serializer,
+            funcId: 1, port: port_);
+      },
+      codec: SseCodec(
+        decodeSuccessData: sse_decode_flutter_session,
+        decodeErrorData: sse_decode_AnyhowException,
+      ),
+      constMeta: kCrateP2PHostAuthenticateConstMeta,
+      argValues: [that, peer, token],
+      apiImpl: this,
+    ));
+  }
+
+  TaskConstMeta get kCrateP2PHostAuthenticateConstMeta => const TaskConstMeta(
+        debugName: "P2PHost_authenticate",
+        argNames: ["that", "peer", "token"],
+      );
+
   @override
   Future<void> crateP2PHostDial(
       {required P2PHost that, required String endpointAddrJson}) {
@@ -140,7 +189,7 @@ class RustLibApiImpl extends RustLibApiImplPlatform implements RustLibApi {
             that, serializer);
         sse_encode_String(endpointAddrJson, serializer);
         pdeCallFfi(generalizedFrbRustBinding, serializer,
-            funcId: 1, port: port_);
+            funcId: 2, port: port_);
       },
       codec: SseCodec(
         decodeSuccessData: sse_decode_unit,
@@ -167,7 +216,7 @@ class RustLibApiImpl extends RustLibApiImplPlatform implements RustLibApi {
             that, serializer);
         sse_encode_StreamSink_String_Sse(sink, serializer);
         pdeCallFfi(generalizedFrbRustBinding, serializer,
-            funcId: 2, port: port_);
+            funcId: 3, port: port_);
       },
       codec: SseCodec(
         decodeSuccessData: sse_decode_unit,
@@ -185,6 +234,29 @@ class RustLibApiImpl extends RustLibApiImplPlatform implements RustLibApi {
         argNames: ["that", "sink"],
       );
 
+  @override
+  String crateP2PHostExportIdentity({required String keypairPath}) {
+    return handler.executeSync(SyncTask(
+      callFfi: () {
+        final serializer = SseSerializer(generalizedFrbRustBinding);
+        sse_encode_String(keypairPath, serializer);
+        return pdeCallFfi(generalizedFrbRustBinding, serializer, funcId: 4)!;
+      },
+      codec: SseCodec(
+        decodeSuccessData: sse_decode_String,
+        decodeErrorData: sse_decode_AnyhowException,
+      ),
+      constMeta: kCrateP2PHostExportIdentityConstMeta,
+      argValues: [keypairPath],
+      apiImpl: this,
+    ));
+  }
+
+  TaskConstMeta get kCrateP2PHostExportIdentityConstMeta => const TaskConstMeta(
+        debugName: "P2PHost_export_identity",
+        argNames: ["keypairPath"],
+      );
+
   @override
   Future<FlutterNetworkStats> crateP2PHostGetNetworkStats(
       {required P2PHost that}) {
@@ -194,7 +266,7 @@ class RustLibApiImpl extends RustLibApiImplPlatform implements RustLibApi {
         sse_encode_Auto_Ref_RustOpaque_flutter_rust_bridgefor_generatedRustAutoOpaqueInnerP2pHost(
             that, serializer);
         pdeCallFfi(generalizedFrbRustBinding, serializer,
-            funcId: 3, port: port_);
+            funcId: 5, port: port_);
       },
       codec: SseCodec(
         decodeSuccessData: sse_decode_flutter_network_stats,
@@ -220,7 +292,7 @@ class RustLibApiImpl extends RustLibApiImplPlatform implements RustLibApi {
         sse_encode_Auto_Ref_RustOpaque_flutter_rust_bridgefor_generatedRustAutoOpaqueInnerP2pHost(
             that, serializer);
         pdeCallFfi(generalizedFrbRustBinding, serializer,
-            funcId: 4, port: port_);
+            funcId: 6, port: port_);
       },
       codec: SseCodec(
         decodeSuccessData: sse_decode_String,
@@ -238,27 +310,119 @@ class RustLibApiImpl extends RustLibApiImplPlatform implements RustLibApi {
       );
 
   @override
-  (P2PHost, String) crateP2PHostInit({String? relayUrl}) {
+  String crateP2PHostImportIdentity(
+      {required String keypairPath, required String identity}) {
     return handler.executeSync(SyncTask(
       callFfi: () {
+        final serializer = SseSerializer(generalizedFrbRustBinding);
+        sse_encode_String(keypairPath, serializer);
+        sse_encode_String(identity, serializer);
+        return pdeCallFfi(generalizedFrbRustBinding, serializer, funcId: 7)!;
+      },
+      codec: SseCodec(
+        decodeSuccessData: sse_decode_String,
+        decodeErrorData: sse_decode_AnyhowException,
+      ),
+      constMeta: kCrateP2PHostImportIdentityConstMeta,
+      argValues: [keypairPath, identity],
+      apiImpl: this,
+    ));
+  }
+
+  TaskConstMeta get kCrateP2PHostImportIdentityConstMeta => const TaskConstMeta(
+        debugName: "P2PHost_import_identity",
+        argNames: ["keypairPath", "identity"],
+      );
+
+  @override
+  Future<(P2PHost, String)> crateP2PHostInit(
+      {String? relayUrl,
+      String? relayMode,
+      String? addressBookPath,
+      String? keypairPath,
+      String? identity}) {
+    return handler.executeNormal(NormalTask(
+      callFfi: (port_) {
         final serializer = SseSerializer(generalizedFrbRustBinding);
         sse_encode_opt_String(relayUrl, serializer);
-        return pdeCallFfi(generalizedFrbRustBinding, serializer, funcId: 5)!;
+        sse_encode_opt_String(relayMode, serializer);
+        sse_encode_opt_String(addressBookPath, serializer);
+        sse_encode_opt_String(keypairPath, serializer);
+        sse_encode_opt_String(identity, serializer);
+        pdeCallFfi(generalizedFrbRustBinding, serializer,
+            funcId: 8, port: port_);
       },
       codec: SseCodec(
         decodeSuccessData:
             sse_decode_record_auto_owned_rust_opaque_flutter_rust_bridgefor_generated_rust_auto_opaque_inner_p_2_p_host_string,
-        decodeErrorData: null,
+        decodeErrorData: sse_decode_AnyhowException,
       ),
       constMeta: kCrateP2PHostInitConstMeta,
-      argValues: [relayUrl],
+      argValues: [relayUrl, relayMode, addressBookPath, keypairPath, identity],
       apiImpl: this,
     ));
   }
 
   TaskConstMeta get kCrateP2PHostInitConstMeta => const TaskConstMeta(
         debugName: "P2PHost_init",
-        argNames: ["relayUrl"],
+        argNames: [
+          "relayUrl",
+          "relayMode",
+          "addressBookPath",
+          "keypairPath",
+          "identity"
+        ],
+      );
+
+  @override
+  Future<List<FlutterPeerInfo>> crateP2PHostListPeers({required P2PHost that}) {
+    return handler.executeNormal(NormalTask(
+      callFfi: (port_) {
+        final serializer = SseSerializer(generalizedFrbRustBinding);
+        sse_encode_Auto_Ref_RustOpaque_flutter_rust_bridgefor_generatedRustAutoOpaqueInnerP2pHost(
+            that, serializer);
+        pdeCallFfi(generalizedFrbRustBinding, serializer,
+            funcId: 9, port: port_);
+      },
+      codec: SseCodec(
+        decodeSuccessData: sse_decode_list_flutter_peer_info,
+        decodeErrorData: null,
+      ),
+      constMeta: kCrateP2PHostListPeersConstMeta,
+      argValues: [that],
+      apiImpl: this,
+    ));
+  }
+
+  TaskConstMeta get kCrateP2PHostListPeersConstMeta => const TaskConstMeta(
+        debugName: "P2PHost_list_peers",
+        argNames: ["that"],
+      );
+
+  @override
+  Future<List<FlutterRelayHealth>> crateP2PHostRelayHealth(
+      {required P2PHost that}) {
+    return handler.executeNormal(NormalTask(
+      callFfi: (port_) {
+        final serializer = SseSerializer(generalizedFrbRustBinding);
+        sse_encode_Auto_Ref_RustOpaque_flutter_rust_bridgefor_generatedRustAutoOpaqueInnerP2pHost(
+            that, serializer);
+        pdeCallFfi(generalizedFrbRustBinding, serializer,
+            funcId: 10, port: port_);
+      },
+      codec: SseCodec(
+        decodeSuccessData: sse_decode_list_flutter_relay_health,
+        decodeErrorData: null,
+      ),
+      constMeta: kCrateP2PHostRelayHealthConstMeta,
+      argValues: [that],
+      apiImpl: this,
+    ));
+  }
+
+  TaskConstMeta get kCrateP2PHostRelayHealthConstMeta => const TaskConstMeta(
+        debugName: "P2PHost_relay_health",
+        argNames: ["that"],
       );
 
   @override
@@ -274,7 +438,7 @@ class RustLibApiImpl extends RustLibApiImplPlatform implements RustLibApi {
         sse_encode_String(peer, serializer);
         sse_encode_box_autoadd_flutter_graph_ql_request(req, serializer);
         pdeCallFfi(generalizedFrbRustBinding, serializer,
-            funcId: 6, port: port_);
+            funcId: 11, port: port_);
       },
       codec: SseCodec(
         decodeSuccessData: sse_decode_flutter_graph_ql_response,
@@ -305,7 +469,7 @@ class RustLibApiImpl extends RustLibApiImplPlatform implements RustLibApi {
         sse_encode_String(peer, serializer);
         sse_encode_box_autoadd_flutter_hls_request(req, serializer);
         pdeCallFfi(generalizedFrbRustBinding, serializer,
-            funcId: 7, port: port_);
+            funcId: 12, port: port_);
       },
       codec: SseCodec(
         decodeSuccessData: sse_decode_flutter_hls_response,
@@ -337,7 +501,7 @@ class RustLibApiImpl extends RustLibApiImplPlatform implements RustLibApi {
         sse_encode_box_autoadd_flutter_hls_request(req, serializer);
         sse_encode_StreamSink_flutter_hls_stream_event_Sse(sink, serializer);
         pdeCallFfi(generalizedFrbRustBinding, serializer,
-            funcId: 8, port: port_);
+            funcId: 13, port: port_);
       },
       codec: SseCodec(
         decodeSuccessData: sse_decode_unit,
@@ -369,7 +533,7 @@ class RustLibApiImpl extends RustLibApiImplPlatform implements RustLibApi {
         sse_encode_String(peer, serializer);
         sse_encode_box_autoadd_flutter_pairing_request(req, serializer);
         pdeCallFfi(generalizedFrbRustBinding, serializer,
-            funcId: 9, port: port_);
+            funcId: 14, port: port_);
       },
       codec: SseCodec(
         decodeSuccessData: sse_decode_flutter_pairing_response,
@@ -387,13 +551,40 @@ class RustLibApiImpl extends RustLibApiImplPlatform implements RustLibApi {
         argNames: ["that", "peer", "req"],
       );
 
+  @override
+  Future<void> crateP2PHostShutdown(
+      {required P2PHost that, required BigInt graceMs}) {
+    return handler.executeNormal(NormalTask(
+      callFfi: (port_) {
+        final serializer = SseSerializer(generalizedFrbRustBinding);
+        sse_encode_Auto_Ref_RustOpaque_flutter_rust_bridgefor_generatedRustAutoOpaqueInnerP2pHost(
+            that, serializer);
+        sse_encode_u_64(graceMs, serializer);
+        pdeCallFfi(generalizedFrbRustBinding, serializer,
+            funcId: 15, port: port_);
+      },
+      codec: SseCodec(
+        decodeSuccessData: sse_decode_unit,
+        decodeErrorData: sse_decode_AnyhowException,
+      ),
+      constMeta: kCrateP2PHostShutdownConstMeta,
+      argValues: [that, graceMs],
+      apiImpl: this,
+    ));
+  }
+
+  TaskConstMeta get kCrateP2PHostShutdownConstMeta => const TaskConstMeta(
+        debugName: "P2PHost_shutdown",
+        argNames: ["that", "graceMs"],
+      );
+
   @override
   Future<void> crateInitApp() {
     return handler.executeNormal(NormalTask(
       callFfi: (port_) {
         final serializer = SseSerializer(generalizedFrbRustBinding);
         pdeCallFfi(generalizedFrbRustBinding, serializer,
-            funcId: 10, port: port_);
+            funcId: 16, port: port_);
       },
       codec: SseCodec(
         decodeSuccessData: sse_decode_unit,
@@ -647,6 +838,51 @@ class RustLibApiImpl extends RustLibApiImplPlatform implements RustLibApi {
     );
   }
 
+  @protected
+  FlutterPeerInfo dco_decode_flutter_peer_info(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    final arr = raw as List<dynamic>;
+    if (arr.length != 8)
+      throw Exception('unexpected arr length: expect 8 but see ${arr.length}');
+    return FlutterPeerInfo(
+      nodeId: dco_decode_String(arr[0]),
+      connectionType: dco_decode_flutter_connection_type(arr[1]),
+      remoteAddrs: dco_decode_list_String(arr[2]),
+      rttMs: dco_decode_opt_box_autoadd_u_64(arr[3]),
+      bytesSent: dco_decode_u_64(arr[4]),
+      bytesReceived: dco_decode_u_64(arr[5]),
+      connectedSince: dco_decode_u_64(arr[6]),
+      openStreams: dco_decode_usize(arr[7]),
+    );
+  }
+
+  @protected
+  FlutterRelayHealth dco_decode_flutter_relay_health(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    final arr = raw as List<dynamic>;
+    if (arr.length != 5)
+      throw Exception('unexpected arr length: expect 5 but see ${arr.length}');
+    return FlutterRelayHealth(
+      url: dco_decode_String(arr[0]),
+      home: dco_decode_bool(arr[1]),
+      reachable: dco_decode_bool(arr[2]),
+      latencyMs: dco_decode_opt_box_autoadd_u_64(arr[3]),
+      lastError: dco_decode_opt_String(arr[4]),
+    );
+  }
+
+  @protected
+  FlutterSession dco_decode_flutter_session(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    final arr = raw as List<dynamic>;
+    if (arr.length != 2)
+      throw Exception('unexpected arr length: expect 2 but see ${arr.length}');
+    return FlutterSession(
+      principal: dco_decode_String(arr[0]),
+      scopes: dco_decode_list_String(arr[1]),
+    );
+  }
+
   @protected
   int dco_decode_i_32(dynamic raw) {
     // Codec=Dco (DartCObject based), see doc to use other codecs
@@ -659,6 +895,18 @@ class RustLibApiImpl extends RustLibApiImplPlatform implements RustLibApi {
     return (raw as List<dynamic>).map(dco_decode_String).toList();
   }
 
+  @protected
+  List<FlutterPeerInfo> dco_decode_list_flutter_peer_info(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    return (raw as List<dynamic>).map(dco_decode_flutter_peer_info).toList();
+  }
+
+  @protected
+  List<FlutterRelayHealth> dco_decode_list_flutter_relay_health(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    return (raw as List<dynamic>).map(dco_decode_flutter_relay_health).toList();
+  }
+
   @protected
   Uint8List dco_decode_list_prim_u_8_strict(dynamic raw) {
     // Codec=Dco (DartCObject based), see doc to use other codecs
@@ -970,6 +1218,53 @@ class RustLibApiImpl extends RustLibApiImplPlatform implements RustLibApi {
         directUrls: var_directUrls);
   }
 
+  @protected
+  FlutterPeerInfo sse_decode_flutter_peer_info(SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    var var_nodeId = sse_decode_String(deserializer);
+    var var_connectionType = sse_decode_flutter_connection_type(deserializer);
+    var var_remoteAddrs = sse_decode_list_String(deserializer);
+    var var_rttMs = sse_decode_opt_box_autoadd_u_64(deserializer);
+    var var_bytesSent = sse_decode_u_64(deserializer);
+    var var_bytesReceived = sse_decode_u_64(deserializer);
+    var var_connectedSince = sse_decode_u_64(deserializer);
+    var var_openStreams = sse_decode_usize(deserializer);
+    return FlutterPeerInfo(
+        nodeId: var_nodeId,
+        connectionType: var_connectionType,
+        remoteAddrs: var_remoteAddrs,
+        rttMs: var_rttMs,
+        bytesSent: var_bytesSent,
+        bytesReceived: var_bytesReceived,
+        connectedSince: var_connectedSince,
+        openStreams: var_openStreams);
+  }
+
+  @protected
+  FlutterRelayHealth sse_decode_flutter_relay_health(
+      SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    var var_url = sse_decode_String(deserializer);
+    var var_home = sse_decode_bool(deserializer);
+    var var_reachable = sse_decode_bool(deserializer);
+    var var_latencyMs = sse_decode_opt_box_autoadd_u_64(deserializer);
+    var var_lastError = sse_decode_opt_String(deserializer);
+    return FlutterRelayHealth(
+        url: var_url,
+        home: var_home,
+        reachable: var_reachable,
+        latencyMs: var_latencyMs,
+        lastError: var_lastError);
+  }
+
+  @protected
+  FlutterSession sse_decode_flutter_session(SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    var var_principal = sse_decode_String(deserializer);
+    var var_scopes = sse_decode_list_String(deserializer);
+    return FlutterSession(principal: var_principal, scopes: var_scopes);
+  }
+
   @protected
   int sse_decode_i_32(SseDeserializer deserializer) {
     // Codec=Sse (Serialization based), see doc to use other codecs
@@ -988,6 +1283,32 @@ class RustLibApiImpl extends RustLibApiImplPlatform implements RustLibApi {
     return ans_;
   }
 
+  @protected
+  List<FlutterPeerInfo> sse_decode_list_flutter_peer_info(
+      SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+
+    var len_ = sse_decode_i_32(deserializer);
+    var ans_ = <FlutterPeerInfo>[];
+    for (var idx_ = 0; idx_ < len_; ++idx_) {
+      ans_.add(sse_decode_flutter_peer_info(deserializer));
+    }
+    return ans_;
+  }
+
+  @protected
+  List<FlutterRelayHealth> sse_decode_list_flutter_relay_health(
+      SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+
+    var len_ = sse_decode_i_32(deserializer);
+    var ans_ = <FlutterRelayHealth>[];
+    for (var idx_ = 0; idx_ < len_; ++idx_) {
+      ans_.add(sse_decode_flutter_relay_health(deserializer));
+    }
+    return ans_;
+  }
+
   @protected
   Uint8List sse_decode_list_prim_u_8_strict(SseDeserializer deserializer) {
     // Codec=Sse (Serialization based), see doc to use other codecs
@@ -1272,6 +1593,39 @@ class RustLibApiImpl extends RustLibApiImplPlatform implements RustLibApi {
     sse_encode_list_String(self.directUrls, serializer);
   }
 
+  @protected
+  void sse_encode_flutter_peer_info(
+      FlutterPeerInfo self, SseSerializer serializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    sse_encode_String(self.nodeId, serializer);
+    sse_encode_flutter_connection_type(self.connectionType, serializer);
+    sse_encode_list_String(self.remoteAddrs, serializer);
+    sse_encode_opt_box_autoadd_u_64(self.rttMs, serializer);
+    sse_encode_u_64(self.bytesSent, serializer);
+    sse_encode_u_64(self.bytesReceived, serializer);
+    sse_encode_u_64(self.connectedSince, serializer);
+    sse_encode_usize(self.openStreams, serializer);
+  }
+
+  @protected
+  void sse_encode_flutter_relay_health(
+      FlutterRelayHealth self, SseSerializer serializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    sse_encode_String(self.url, serializer);
+    sse_encode_bool(self.home, serializer);
+    sse_encode_bool(self.reachable, serializer);
+    sse_encode_opt_box_autoadd_u_64(self.latencyMs, serializer);
+    sse_encode_opt_String(self.lastError, serializer);
+  }
+
+  @protected
+  void sse_encode_flutter_session(
+      FlutterSession self, SseSerializer serializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    sse_encode_String(self.principal, serializer);
+    sse_encode_list_String(self.scopes, serializer);
+  }
+
   @protected
   void sse_encode_i_32(int self, SseSerializer serializer) {
     // Codec=Sse (Serialization based), see doc to use other codecs
@@ -1287,6 +1641,26 @@ class RustLibApiImpl extends RustLibApiImplPlatform implements RustLibApi {
     }
   }
 
+  @protected
+  void sse_encode_list_flutter_peer_info(
+      List<FlutterPeerInfo> self, SseSerializer serializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    sse_encode_i_32(self.length, serializer);
+    for (final item in self) {
+      sse_encode_flutter_peer_info(item, serializer);
+    }
+  }
+
+  @protected
+  void sse_encode_list_flutter_relay_health(
+      List<FlutterRelayHealth> self, SseSerializer serializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    sse_encode_i_32(self.length, serializer);
+    for (final item in self) {
+      sse_encode_flutter_relay_health(item, serializer);
+    }
+  }
+
   @protected
   void sse_encode_list_prim_u_8_strict(
       Uint8List self, SseSerializer serializer) {
@@ -1374,7 +1748,16 @@ class P2PHostImpl extends RustOpaque implements P2PHost {
         RustLib.instance.api.rust_arc_decrement_strong_count_P2PHostPtr,
   );
 
-  /// Dial a peer using their EndpointAddr JSON.
+  /// Authenticate the connection to a peer once, so later GraphQL and HLS
+  /// requests to it can leave their auth_token out. Needs to be repeated
+  /// after a reconnect, as a new connection starts unauthenticated.
+  Future<FlutterSession> authenticate(
+          {required String peer, required String token}) =>
+      RustLib.instance.api
+          .crateP2PHostAuthenticate(that: this, peer: peer, token: token);
+
+  /// Dial a peer using their EndpointAddr JSON, or a bare node id
+  /// reported by a "discovered:" event.
   Future<void> dial({required String endpointAddrJson}) => RustLib.instance.api
       .crateP2PHostDial(that: this, endpointAddrJson: endpointAddrJson);
 
@@ -1394,6 +1777,18 @@ class P2PHostImpl extends RustOpaque implements P2PHost {
         that: this,
       );
 
+  /// List every connected peer.
+  Future<List<FlutterPeerInfo>> listPeers() =>
+      RustLib.instance.api.crateP2PHostListPeers(
+        that: this,
+      );
+
+  /// Get the latest probe results for every relay, lowest latency first.
+  Future<List<FlutterRelayHealth>> relayHealth() =>
+      RustLib.instance.api.crateP2PHostRelayHealth(
+        that: this,
+      );
+
   /// Send a GraphQL request to a specific peer.
   Future<FlutterGraphQLResponse> sendGraphqlRequest(
           {required String peer, required FlutterGraphQLRequest req}) =>
@@ -1413,7 +1808,7 @@ class P2PHostImpl extends RustOpaque implements P2PHost {
   ///
   /// Sends Header, Chunk, and End events via a StreamSink. The stream is
   /// cancelled automatically when the Dart subscription is dropped (sink.add
-  /// returns an error).
+  /// returns an error), which also tells the server to stop sending.
   Stream<FlutterHlsStreamEvent> sendHlsRequestStreaming(
           {required String peer, required FlutterHlsRequest req}) =>
       RustLib.instance.api.crateP2PHostSendHlsRequestStreaming(
@@ -1424,4 +1819,9 @@ class P2PHostImpl extends RustOpaque implements P2PHost {
           {required String peer, required FlutterPairingRequest req}) =>
       RustLib.instance.api
           .crateP2PHostSendPairingRequest(that: this, peer: peer, req: req);
+
+  /// Stop the host, giving in-flight requests up to grace_ms to finish,
+  /// and release its UDP port. Dropping the P2pHost does the same with no grace.
+  Future<void> shutdown({required BigInt graceMs}) =>
+      RustLib.instance.api.crateP2PHostShutdown(that: this, graceMs: graceMs);
 }
@@ -102,12 +102,27 @@ abstract class RustLibApiImplPlatform extends BaseApiImpl<RustLibWire> {
   @protected
   FlutterPairingResponse dco_decode_flutter_pairing_response(dynamic raw);
 
+  @protected
+  FlutterPeerInfo dco_decode_flutter_peer_info(dynamic raw);
+
+  @protected
+  FlutterRelayHealth dco_decode_flutter_relay_health(dynamic raw);
+
+  @protected
+  FlutterSession dco_decode_flutter_session(dynamic raw);
+
   @protected
   int dco_decode_i_32(dynamic raw);
 
   @protected
   List<String> dco_decode_list_String(dynamic raw);
 
+  @protected
+  List<FlutterPeerInfo> dco_decode_list_flutter_peer_info(dynamic raw);
+
+  @protected
+  List<FlutterRelayHealth> dco_decode_list_flutter_relay_health(dynamic raw);
+
   @protected
   Uint8List dco_decode_list_prim_u_8_strict(dynamic raw);
 
@@ -231,12 +246,30 @@ abstract class RustLibApiImplPlatform extends BaseApiImpl<RustLibWire> {
   FlutterPairingResponse sse_decode_flutter_pairing_response(
       SseDeserializer deserializer);
 
+  @protected
+  FlutterPeerInfo sse_decode_flutter_peer_info(SseDeserializer deserializer);
+
+  @protected
+  FlutterRelayHealth sse_decode_flutter_relay_health(
+      SseDeserializer deserializer);
+
+  @protected
+  FlutterSession sse_decode_flutter_session(SseDeserializer deserializer);
+
   @protected
   int sse_decode_i_32(SseDeserializer deserializer);
 
   @protected
   List<String> sse_decode_list_String(SseDeserializer deserializer);
 
+  @protected
+  List<FlutterPeerInfo> sse_decode_list_flutter_peer_info(
+      SseDeserializer deserializer);
+
+  @protected
+  List<FlutterRelayHealth> sse_decode_list_flutter_relay_health(
+      SseDeserializer deserializer);
+
   @protected
   Uint8List sse_decode_list_prim_u_8_strict(SseDeserializer deserializer);
 
@@ -360,12 +393,32 @@ abstract class RustLibApiImplPlatform extends BaseApiImpl<RustLibWire> {
   void sse_encode_flutter_pairing_response(
       FlutterPairingResponse self, SseSerializer serializer);
 
+  @protected
+  void sse_encode_flutter_peer_info(
+      FlutterPeerInfo self, SseSerializer serializer);
+
+  @protected
+  void sse_encode_flutter_relay_health(
+      FlutterRelayHealth self, SseSerializer serializer);
+
+  @protected
+  void sse_encode_flutter_session(
+      FlutterSession self, SseSerializer serializer);
+
   @protected
   void sse_encode_i_32(int self, SseSerializer serializer);
 
   @protected
   void sse_encode_list_String(List<String> self, SseSerializer serializer);
 
+  @protected
+  void sse_encode_list_flutter_peer_info(
+      List<FlutterPeerInfo> self, SseSerializer serializer);
+
+  @protected
+  void sse_encode_list_flutter_relay_health(
+      List<FlutterRelayHealth> self, SseSerializer serializer);
+
   @protected
   void sse_encode_list_prim_u_8_strict(
       Uint8List self, SseSerializer serializer);
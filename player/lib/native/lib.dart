@@ -12,21 +12,64 @@ part 'lib.freezed.dart';
 
 // Rust type: RustOpaqueMoi<flutter_rust_bridge::for_generated::RustAutoOpaqueInner<P2pHost>>
 abstract class P2PHost implements RustOpaqueInterface {
-  /// Dial a peer using their EndpointAddr JSON.
+  /// Authenticate the connection to a peer once, so later GraphQL and HLS
+  /// requests to it can leave their auth_token out. Needs to be repeated
+  /// after a reconnect, as a new connection starts unauthenticated.
+  Future<FlutterSession> authenticate(
+      {required String peer, required String token});
+
+  /// Dial a peer using their EndpointAddr JSON, or a bare node id
+  /// reported by a "discovered:" event.
   Future<void> dial({required String endpointAddrJson});
 
   /// Start streaming events to Flutter.
   Stream<String> eventStream();
 
+  /// Read the device identity stored at keypair_path, e.g. to back it up.
+  static String exportIdentity({required String keypairPath}) =>
+      RustLib.instance.api.crateP2PHostExportIdentity(keypairPath: keypairPath);
+
   /// Get network statistics.
   Future<FlutterNetworkStats> getNetworkStats();
 
   /// Get this node's EndpointAddr as JSON for sharing.
   Future<String> getNodeAddr();
 
+  /// Store an exported identity at keypair_path, so the next init() runs
+  /// as that device. Returns the node id it gives.
+  static String importIdentity(
+          {required String keypairPath, required String identity}) =>
+      RustLib.instance.api.crateP2PHostImportIdentity(
+          keypairPath: keypairPath, identity: identity);
+
   /// Initialize a new P2P host with optional custom relay URL.
-  static (P2PHost, String) init({String? relayUrl}) =>
-      RustLib.instance.api.crateP2PHostInit(relayUrl: relayUrl);
+  /// relay_mode is "default", "custom", "custom_with_defaults" or "disabled".
+  /// If None, the relay URL (if any) is used alongside iroh's default relays.
+  /// address_book_path is where to remember peers' last known addresses
+  /// across restarts; in memory only if None.
+  /// keypair_path is where the device identity is kept, so the node id stays
+  /// the same across launches. identity is an exported identity to run with
+  /// instead, for callers that keep it in secure storage; only one of the two
+  /// may be given. A new identity is used every launch if both are None.
+  /// Fails if the identity can't be read or the host can't bind.
+  static Future<(P2PHost, String)> init(
+          {String? relayUrl,
+          String? relayMode,
+          String? addressBookPath,
+          String? keypairPath,
+          String? identity}) =>
+      RustLib.instance.api.crateP2PHostInit(
+          relayUrl: relayUrl,
+          relayMode: relayMode,
+          addressBookPath: addressBookPath,
+          keypairPath: keypairPath,
+          identity: identity);
+
+  /// List every connected peer.
+  Future<List<FlutterPeerInfo>> listPeers();
+
+  /// Get the latest probe results for every relay, lowest latency first.
+  Future<List<FlutterRelayHealth>> relayHealth();
 
   /// Send a GraphQL request to a specific peer.
   Future<FlutterGraphQLResponse> sendGraphqlRequest(
@@ -43,13 +86,17 @@ abstract class P2PHost implements RustOpaqueInterface {
   ///
   /// Sends Header, Chunk, and End events via a StreamSink. The stream is
   /// cancelled automatically when the Dart subscription is dropped (sink.add
-  /// returns an error).
+  /// returns an error), which also tells the server to stop sending.
   Stream<FlutterHlsStreamEvent> sendHlsRequestStreaming(
       {required String peer, required FlutterHlsRequest req});
 
   /// Send a pairing request to a specific peer.
   Future<FlutterPairingResponse> sendPairingRequest(
       {required String peer, required FlutterPairingRequest req});
+
+  /// Stop the host, giving in-flight requests up to grace_ms to finish,
+  /// and release its UDP port. Dropping the P2pHost does the same with no grace.
+  Future<void> shutdown({required BigInt graceMs});
 }
 
 /// Connection type for a peer (relay vs direct) for display in Flutter UI
@@ -337,3 +384,117 @@ class FlutterPairingResponse {
           error == other.error &&
           directUrls == other.directUrls;
 }
+
+/// Details about a single peer connection
+class FlutterPeerInfo {
+  final String nodeId;
+  final FlutterConnectionType connectionType;
+
+  /// Socket addresses of direct paths and URLs of relay paths
+  final List<String> remoteAddrs;
+  final BigInt? rttMs;
+  final BigInt bytesSent;
+  final BigInt bytesReceived;
+
+  /// Unix timestamp (seconds) when the connection was established
+  final BigInt connectedSince;
+  final BigInt openStreams;
+
+  const FlutterPeerInfo({
+    required this.nodeId,
+    required this.connectionType,
+    required this.remoteAddrs,
+    this.rttMs,
+    required this.bytesSent,
+    required this.bytesReceived,
+    required this.connectedSince,
+    required this.openStreams,
+  });
+
+  @override
+  int get hashCode =>
+      nodeId.hashCode ^
+      connectionType.hashCode ^
+      remoteAddrs.hashCode ^
+      rttMs.hashCode ^
+      bytesSent.hashCode ^
+      bytesReceived.hashCode ^
+      connectedSince.hashCode ^
+      openStreams.hashCode;
+
+  @override
+  bool operator ==(Object other) =>
+      identical(this, other) ||
+      other is FlutterPeerInfo &&
+          runtimeType == other.runtimeType &&
+          nodeId == other.nodeId &&
+          connectionType == other.connectionType &&
+          remoteAddrs == other.remoteAddrs &&
+          rttMs == other.rttMs &&
+          bytesSent == other.bytesSent &&
+          bytesReceived == other.bytesReceived &&
+          connectedSince == other.connectedSince &&
+          openStreams == other.openStreams;
+}
+
+/// Latest probe results for a relay server
+class FlutterRelayHealth {
+  final String url;
+
+  /// Whether this is the relay currently in use
+  final bool home;
+  final bool reachable;
+  final BigInt? latencyMs;
+
+  /// Why the relay was last unreachable, kept after it recovers
+  final String? lastError;
+
+  const FlutterRelayHealth({
+    required this.url,
+    required this.home,
+    required this.reachable,
+    this.latencyMs,
+    this.lastError,
+  });
+
+  @override
+  int get hashCode =>
+      url.hashCode ^
+      home.hashCode ^
+      reachable.hashCode ^
+      latencyMs.hashCode ^
+      lastError.hashCode;
+
+  @override
+  bool operator ==(Object other) =>
+      identical(this, other) ||
+      other is FlutterRelayHealth &&
+          runtimeType == other.runtimeType &&
+          url == other.url &&
+          home == other.home &&
+          reachable == other.reachable &&
+          latencyMs == other.latencyMs &&
+          lastError == other.lastError;
+}
+
+/// Who the connection to a server authenticated as
+class FlutterSession {
+  final String principal;
+  final List<String> scopes;
+
+  const FlutterSession({
+    required this.principal,
+    required this.scopes,
+  });
+
+  @override
+  int get hashCode => principal.hashCode ^ scopes.hashCode;
+
+  @override
+  bool operator ==(Object other) =>
+      identical(this, other) ||
+      other is FlutterSession &&
+          runtimeType == other.runtimeType &&
+          principal == other.principal &&
+          scopes == other.scopes;
+}
@@ -1,7 +1,11 @@
 mod frb_generated; /* AUTO INJECTED BY flutter_rust_bridge. This line may not be accurate, and you can change it according to your needs. */
-use mydia_p2p_core::{Host, Event, MydiaRequest, MydiaResponse, PairingRequest, GraphQLRequest, HlsRequest, HlsRequester, HostConfig, PeerConnectionType};
-use flutter_rust_bridge::frb;
 use crate::frb_generated::StreamSink;
+use flutter_rust_bridge::frb;
+use mydia_p2p_core::{
+    AuthenticateRequest, Event, GraphQLRequest, HlsRequest, HlsRequester, Host, HostConfig,
+    LocalDiscovery, MydiaRequest, MydiaResponse, P2pError, PairingRequest, PeerConnectionType,
+    ReconnectPolicy, RelayPolicy,
+};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
 
 #[frb(init)]
@@ -22,8 +26,9 @@ pub fn init_app() {
 
     // Initialize tracing for mydia_p2p_core and iroh (which use tracing:: macros)
     // This must be done BEFORE Host::new() is called to capture iroh's startup logs
-    let filter = EnvFilter::try_from_default_env()
-        .unwrap_or_else(|_| EnvFilter::new("info,mydia_p2p_core=debug,iroh=info,quinn=warn,rustls=warn"));
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| {
+        EnvFilter::new("info,mydia_p2p_core=debug,iroh=info,quinn=warn,rustls=warn")
+    });
 
     #[cfg(target_os = "android")]
     {
@@ -46,6 +51,14 @@ pub fn init_app() {
     log::info!("mydia_player_p2p initialized");
 }
 
+/// Convert a core error into an error for Dart.
+///
+/// The message is prefixed with the stable error kind (e.g. `timeout: ...`,
+/// `remote: Remote error 401: ...`) so Dart can branch on it.
+fn p2p_error(e: P2pError) -> anyhow::Error {
+    anyhow::anyhow!("{}: {}", e.kind(), e)
+}
+
 pub struct P2pHost {
     inner: Host,
     hls_requester: HlsRequester,
@@ -101,6 +114,31 @@ pub struct FlutterNetworkStats {
     pub peer_connection_type: FlutterConnectionType,
 }
 
+/// Details about a single peer connection
+pub struct FlutterPeerInfo {
+    pub node_id: String,
+    pub connection_type: FlutterConnectionType,
+    /// Socket addresses of direct paths and URLs of relay paths
+    pub remote_addrs: Vec<String>,
+    pub rtt_ms: Option<u64>,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    /// Unix timestamp (seconds) when the connection was established
+    pub connected_since: u64,
+    pub open_streams: usize,
+}
+
+/// Latest probe results for a relay server
+pub struct FlutterRelayHealth {
+    pub url: String,
+    /// Whether this is the relay currently in use
+    pub home: bool,
+    pub reachable: bool,
+    pub latency_ms: Option<u64>,
+    /// Why the relay was last unreachable, kept after it recovers
+    pub last_error: Option<String>,
+}
+
 /// GraphQL request to send over P2P
 pub struct FlutterGraphQLRequest {
     pub query: String,
@@ -115,6 +153,12 @@ pub struct FlutterGraphQLResponse {
     pub errors: Option<String>,
 }
 
+/// Who the connection to a server authenticated as
+pub struct FlutterSession {
+    pub principal: String,
+    pub scopes: Vec<String>,
+}
+
 /// HLS request to send over P2P
 pub struct FlutterHlsRequest {
     pub session_id: String,
@@ -148,20 +192,87 @@ pub struct FlutterHlsResponse {
     pub data: Vec<u8>,
 }
 
+/// Build the relay policy from the relay URL and mode passed to `P2pHost::init`
+fn relay_policy(relay_url: Option<String>, relay_mode: Option<&str>) -> RelayPolicy {
+    let urls: Vec<String> = relay_url.into_iter().collect();
+    match relay_mode {
+        Some("default") => RelayPolicy::Default,
+        Some("custom") => RelayPolicy::Custom(urls),
+        Some("disabled") => RelayPolicy::Disabled,
+        Some("custom_with_defaults") | None if !urls.is_empty() => {
+            RelayPolicy::CustomWithDefaults(urls)
+        }
+        Some("custom_with_defaults") | None => RelayPolicy::Default,
+        Some(other) => {
+            log::warn!("Unknown relay mode {:?}, using default relays", other);
+            RelayPolicy::Default
+        }
+    }
+}
+
 impl P2pHost {
     /// Initialize a new P2P host with optional custom relay URL.
-    #[frb(sync)]
-    pub fn init(relay_url: Option<String>) -> (Self, String) {
-        log::info!("P2pHost::init() called with relay_url: {:?}", relay_url);
-        let config = HostConfig {
+    /// relay_mode is "default", "custom", "custom_with_defaults" or "disabled".
+    /// If None, the relay URL (if any) is used alongside iroh's default relays.
+    /// address_book_path is where to remember peers' last known addresses
+    /// across restarts; in memory only if None.
+    /// keypair_path is where the device identity is kept, so the node id stays
+    /// the same across launches. identity is an exported identity to run with
+    /// instead, for callers that keep it in secure storage; only one of the two
+    /// may be given. A new identity is used every launch if both are None.
+    /// Fails if the identity can't be read or the host can't bind.
+    pub fn init(
+        relay_url: Option<String>,
+        relay_mode: Option<String>,
+        address_book_path: Option<String>,
+        keypair_path: Option<String>,
+        identity: Option<String>,
+    ) -> anyhow::Result<(Self, String)> {
+        log::info!(
+            "P2pHost::init() called with relay_url: {:?}, relay_mode: {:?}",
             relay_url,
+            relay_mode
+        );
+        let config = HostConfig {
+            relay: relay_policy(relay_url, relay_mode.as_deref()),
             bind_port: None,
-            keypair_path: None,
+            keypair_path,
+            identity,
+            // Redial the server when the connection drops (e.g. Wi-Fi -> LTE)
+            reconnect: Some(ReconnectPolicy::default()),
+            // Find servers on the same LAN without announcing the player
+            local_discovery: LocalDiscovery::Discover,
+            // Reconnect to the server over its last known path after a restart
+            address_book_path,
+            ..Default::default()
         };
-        let (host, node_id) = Host::new(config);
+        let host = Host::start(config).map_err(|e| {
+            log::error!("P2pHost::init() failed: {}", e);
+            p2p_error(e)
+        })?;
+        let node_id = host.node_id().to_string();
         let hls_requester = host.hls_requester();
         log::info!("P2pHost created with node_id: {}", node_id);
-        (P2pHost { inner: host, hls_requester }, node_id)
+        Ok((
+            P2pHost {
+                inner: host,
+                hls_requester,
+            },
+            node_id,
+        ))
+    }
+
+    /// Read the device identity stored at keypair_path, e.g. to back it up.
+    #[frb(sync)]
+    pub fn export_identity(keypair_path: String) -> anyhow::Result<String> {
+        mydia_p2p_core::export_identity(&keypair_path).map_err(p2p_error)
+    }
+
+    /// Store an exported identity at keypair_path, so the next init() runs
+    /// as that device. Returns the node id it gives.
+    #[frb(sync)]
+    pub fn import_identity(keypair_path: String, identity: String) -> anyhow::Result<String> {
+        mydia_p2p_core::import_identity(&keypair_path, &identity).map_err(p2p_error)
     }
 
     /// Get this node's EndpointAddr as JSON for sharing.
@@ -169,7 +280,8 @@ impl P2pHost {
         self.inner.get_node_addr()
     }
 
-    /// Dial a peer using their EndpointAddr JSON.
+    /// Dial a peer using their EndpointAddr JSON, or a bare node id
+    /// reported by a "discovered:" event.
     pub fn dial(&self, endpoint_addr_json: String) -> anyhow::Result<()> {
         log::info!("P2pHost::dial() called");
         match self.inner.dial(endpoint_addr_json) {
@@ -179,7 +291,7 @@ impl P2pHost {
             }
             Err(e) => {
                 log::error!("dial() failed: {}", e);
-                Err(anyhow::anyhow!("dial failed: {}", e))
+                Err(p2p_error(e))
             }
         }
     }
@@ -203,20 +315,57 @@ impl P2pHost {
                 log::info!("event_stream listening for events");
                 while let Some(event) = rx.recv().await {
                     let msg = match event {
-                        Event::Connected { peer_id, connection_type } => format!("connected:{}:{}", peer_id, connection_type.as_str()),
+                        Event::Connected {
+                            peer_id,
+                            connection_type,
+                            protocol,
+                        } => format!(
+                            "connected:{}:{}:{}",
+                            peer_id,
+                            connection_type.as_str(),
+                            protocol.version
+                        ),
                         Event::Disconnected(peer_id) => format!("disconnected:{}", peer_id),
                         Event::RelayConnected => "relay_connected".to_string(),
+                        Event::RelayDisconnected => "relay_disconnected".to_string(),
                         Event::Ready { node_addr } => format!("ready:{}", node_addr),
                         Event::RequestReceived { .. } => {
                             // Client doesn't handle incoming requests
                             continue;
                         }
-                        Event::HlsStreamRequest { .. } => {
+                        Event::HlsStreamRequest { .. }
+                        | Event::RequestCancelled { .. }
+                        | Event::StreamCancelled { .. } => {
                             // Client doesn't handle incoming HLS requests
                             continue;
                         }
-                        Event::ConnectionTypeChanged { peer_id, connection_type } => {
-                            format!("connection_type_changed:{}:{}", peer_id, connection_type.as_str())
+                        Event::ConnectionTypeChanged {
+                            peer_id,
+                            connection_type,
+                        } => {
+                            format!(
+                                "connection_type_changed:{}:{}",
+                                peer_id,
+                                connection_type.as_str()
+                            )
+                        }
+                        Event::SelectedPathChanged {
+                            peer_id,
+                            remote_addr,
+                        } => {
+                            format!("path_changed:{}:{}", peer_id, remote_addr)
+                        }
+                        Event::Reconnecting { peer_id, attempt } => {
+                            format!("reconnecting:{}:{}", peer_id, attempt)
+                        }
+                        Event::Reconnected { peer_id, attempts } => {
+                            format!("reconnected:{}:{}", peer_id, attempts)
+                        }
+                        Event::PeerClosed { peer_id, reason } => {
+                            format!("closed:{}:{}", peer_id, reason.as_str())
+                        }
+                        Event::PeerDiscovered { node_id, addrs } => {
+                            format!("discovered:{}:{}", node_id, addrs.join(","))
                         }
                         Event::Log { .. } => {
                             // Logs are handled separately via android_logger/tracing
@@ -236,9 +385,16 @@ impl P2pHost {
     }
 
     /// Send a pairing request to a specific peer.
-    pub async fn send_pairing_request(&self, peer: String, req: FlutterPairingRequest) -> anyhow::Result<FlutterPairingResponse> {
-        log::info!("P2pHost::send_pairing_request() called for peer: {}, claim_code: {}",
-            peer, req.claim_code);
+    pub async fn send_pairing_request(
+        &self,
+        peer: String,
+        req: FlutterPairingRequest,
+    ) -> anyhow::Result<FlutterPairingResponse> {
+        log::info!(
+            "P2pHost::send_pairing_request() called for peer: {}, claim_code: {}",
+            peer,
+            req.claim_code
+        );
         let core_req = PairingRequest {
             claim_code: req.claim_code,
             device_name: req.device_name,
@@ -246,7 +402,11 @@ impl P2pHost {
             device_os: req.device_os,
         };
 
-        match self.inner.send_request(peer.clone(), MydiaRequest::Pairing(core_req)).await {
+        match self
+            .inner
+            .send_request(peer.clone(), MydiaRequest::Pairing(core_req))
+            .await
+        {
             Ok(MydiaResponse::Pairing(res)) => {
                 log::info!("send_pairing_request() succeeded: success={}", res.success);
                 Ok(FlutterPairingResponse {
@@ -258,23 +418,26 @@ impl P2pHost {
                     direct_urls: res.direct_urls,
                 })
             }
-            Ok(MydiaResponse::Error(e)) => {
-                log::error!("send_pairing_request() server error: {}", e);
-                Err(anyhow::anyhow!("Server error: {}", e))
-            }
             Ok(other) => {
-                log::error!("send_pairing_request() unexpected response type: {:?}", other);
+                log::error!(
+                    "send_pairing_request() unexpected response type: {:?}",
+                    other
+                );
                 Err(anyhow::anyhow!("Unexpected response type"))
             }
             Err(e) => {
                 log::error!("send_pairing_request() failed for peer {}: {}", peer, e);
-                Err(anyhow::anyhow!("send_pairing_request failed: {}", e))
+                Err(p2p_error(e))
             }
         }
     }
 
     /// Send a GraphQL request to a specific peer.
-    pub async fn send_graphql_request(&self, peer: String, req: FlutterGraphQLRequest) -> anyhow::Result<FlutterGraphQLResponse> {
+    pub async fn send_graphql_request(
+        &self,
+        peer: String,
+        req: FlutterGraphQLRequest,
+    ) -> anyhow::Result<FlutterGraphQLResponse> {
         log::info!("P2pHost::send_graphql_request() called for peer: {}", peer);
         let core_req = GraphQLRequest {
             query: req.query,
@@ -283,7 +446,11 @@ impl P2pHost {
             auth_token: req.auth_token,
         };
 
-        match self.inner.send_request(peer.clone(), MydiaRequest::GraphQL(core_req)).await {
+        match self
+            .inner
+            .send_request(peer.clone(), MydiaRequest::GraphQL(core_req))
+            .await
+        {
             Ok(MydiaResponse::GraphQL(res)) => {
                 log::info!("send_graphql_request() succeeded");
                 Ok(FlutterGraphQLResponse {
@@ -291,21 +458,56 @@ impl P2pHost {
                     errors: res.errors,
                 })
             }
-            Ok(MydiaResponse::Error(e)) => {
-                log::error!("send_graphql_request() server error: {}", e);
-                Err(anyhow::anyhow!("Server error: {}", e))
-            }
             Ok(other) => {
-                log::error!("send_graphql_request() unexpected response type: {:?}", other);
+                log::error!(
+                    "send_graphql_request() unexpected response type: {:?}",
+                    other
+                );
                 Err(anyhow::anyhow!("Unexpected response type"))
             }
             Err(e) => {
                 log::error!("send_graphql_request() failed for peer {}: {}", peer, e);
-                Err(anyhow::anyhow!("send_graphql_request failed: {}", e))
+                Err(p2p_error(e))
+            }
+        }
+    }
+
+    /// Authenticate the connection to a peer once, so later GraphQL and HLS
+    /// requests to it can leave their auth_token out. Needs to be repeated
+    /// after a reconnect, as a new connection starts unauthenticated.
+    pub async fn authenticate(
+        &self,
+        peer: String,
+        token: String,
+    ) -> anyhow::Result<FlutterSession> {
+        log::info!("P2pHost::authenticate() called for peer: {}", peer);
+        let request = MydiaRequest::Authenticate(AuthenticateRequest { token });
+
+        match self.inner.send_request(peer.clone(), request).await {
+            Ok(MydiaResponse::Authenticated(session)) => Ok(FlutterSession {
+                principal: session.principal,
+                scopes: session.scopes,
+            }),
+            Ok(other) => {
+                log::error!("authenticate() unexpected response type: {:?}", other);
+                Err(anyhow::anyhow!("Unexpected response type"))
+            }
+            Err(e) => {
+                log::error!("authenticate() failed for peer {}: {}", peer, e);
+                Err(p2p_error(e))
             }
         }
     }
 
+    /// Stop the host, giving in-flight requests up to grace_ms to finish,
+    /// and release its UDP port. Dropping the P2pHost does the same with no grace.
+    pub fn shutdown(&self, grace_ms: u64) -> anyhow::Result<()> {
+        log::info!("P2pHost::shutdown() called with grace_ms: {}", grace_ms);
+        self.inner
+            .shutdown(std::time::Duration::from_millis(grace_ms))
+            .map_err(p2p_error)
+    }
+
     /// Get network statistics.
     pub fn get_network_stats(&self) -> FlutterNetworkStats {
         let stats = self.inner.get_network_stats();
@@ -319,11 +521,44 @@ impl P2pHost {
         }
     }
 
+    /// List every connected peer.
+    pub fn list_peers(&self) -> Vec<FlutterPeerInfo> {
+        self.inner
+            .list_peers()
+            .into_iter()
+            .map(|peer| FlutterPeerInfo {
+                node_id: peer.node_id,
+                connection_type: peer.connection_type.into(),
+                remote_addrs: peer.remote_addrs,
+                rtt_ms: peer.rtt_ms,
+                bytes_sent: peer.bytes_sent,
+                bytes_received: peer.bytes_received,
+                connected_since: peer.connected_since,
+                open_streams: peer.open_streams,
+            })
+            .collect()
+    }
+
+    /// Get the latest probe results for every relay, lowest latency first.
+    pub fn relay_health(&self) -> Vec<FlutterRelayHealth> {
+        self.inner
+            .relay_health()
+            .into_iter()
+            .map(|relay| FlutterRelayHealth {
+                url: relay.url,
+                home: relay.home,
+                reachable: relay.reachable,
+                latency_ms: relay.latency_ms,
+                last_error: relay.last_error,
+            })
+            .collect()
+    }
+
     /// Send an HLS request to a specific peer and stream the response.
     ///
     /// Sends Header, Chunk, and End events via a StreamSink. The stream is
     /// cancelled automatically when the Dart subscription is dropped (sink.add
-    /// returns an error).
+    /// returns an error), which also tells the server to stop sending.
     pub fn send_hls_request_streaming(
         &self,
         peer: String,
@@ -332,7 +567,9 @@ impl P2pHost {
     ) -> anyhow::Result<()> {
         log::info!(
             "P2pHost::send_hls_request_streaming() called for peer: {}, session: {}, path: {}",
-            peer, req.session_id, req.path
+            peer,
+            req.session_id,
+            req.path
         );
 
         let core_req = HlsRequest {
@@ -350,13 +587,18 @@ impl P2pHost {
                 Ok(rt) => rt,
                 Err(e) => {
                     log::error!("Failed to create Tokio runtime for streaming HLS: {}", e);
-                    let _ = sink.add(FlutterHlsStreamEvent::Error(format!("Runtime error: {}", e)));
+                    let _ = sink.add(FlutterHlsStreamEvent::Error(format!(
+                        "Runtime error: {}",
+                        e
+                    )));
                     return;
                 }
             };
 
             rt.block_on(async move {
-                match requester.send_hls_request(peer.clone(), core_req).await {
+                let (cancel, response) =
+                    requester.send_hls_request_cancellable(peer.clone(), core_req);
+                match response.await {
                     Ok(stream_response) => {
                         // Send header event
                         let header = FlutterHlsResponseHeader {
@@ -368,6 +610,7 @@ impl P2pHost {
                         };
                         if sink.add(FlutterHlsStreamEvent::Header(header)).is_err() {
                             log::debug!("HLS stream sink closed on header");
+                            cancel.cancel();
                             return;
                         }
 
@@ -376,6 +619,7 @@ impl P2pHost {
                         while let Some(chunk) = chunk_rx.recv().await {
                             if sink.add(FlutterHlsStreamEvent::Chunk(chunk)).is_err() {
                                 log::debug!("HLS stream sink closed, stopping chunk read");
+                                cancel.cancel();
                                 return;
                             }
                         }
@@ -385,7 +629,7 @@ impl P2pHost {
                     }
                     Err(e) => {
                         log::error!("HLS streaming request failed for peer {}: {}", peer, e);
-                        let _ = sink.add(FlutterHlsStreamEvent::Error(format!("HLS request failed: {}", e)));
+                        let _ = sink.add(FlutterHlsStreamEvent::Error(p2p_error(e).to_string()));
                     }
                 }
             });
@@ -398,9 +642,17 @@ impl P2pHost {
     ///
     /// This is a non-streaming version that collects all chunks into a single buffer.
     /// For large files, consider using the local proxy service instead.
-    pub async fn send_hls_request(&self, peer: String, req: FlutterHlsRequest) -> anyhow::Result<FlutterHlsResponse> {
-        log::info!("P2pHost::send_hls_request() called for peer: {}, session: {}, path: {}",
-            peer, req.session_id, req.path);
+    pub async fn send_hls_request(
+        &self,
+        peer: String,
+        req: FlutterHlsRequest,
+    ) -> anyhow::Result<FlutterHlsResponse> {
+        log::info!(
+            "P2pHost::send_hls_request() called for peer: {}, session: {}, path: {}",
+            peer,
+            req.session_id,
+            req.path
+        );
 
         let core_req = HlsRequest {
             session_id: req.session_id,
@@ -428,7 +680,11 @@ impl P2pHost {
                     data.extend_from_slice(&chunk);
                 }
 
-                log::info!("HLS request completed for peer: {}, received {} bytes", peer, data.len());
+                log::info!(
+                    "HLS request completed for peer: {}, received {} bytes",
+                    peer,
+                    data.len()
+                );
                 Ok(FlutterHlsResponse {
                     header: flutter_header,
                     data,
@@ -436,9 +692,8 @@ impl P2pHost {
             }
             Err(e) => {
                 log::error!("send_hls_request failed for peer {}: {}", peer, e);
-                Err(anyhow::anyhow!("HLS request failed: {}", e))
+                Err(p2p_error(e))
             }
         }
     }
-
 }
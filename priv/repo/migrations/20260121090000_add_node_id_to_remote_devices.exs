@@ -0,0 +1,11 @@
+defmodule Mydia.Repo.Migrations.AddNodeIdToRemoteDevices do
+  use Ecto.Migration
+
+  def change do
+    alter table(:remote_devices) do
+      add :node_id, :string
+    end
+
+    create index(:remote_devices, [:node_id])
+  end
+end
@@ -0,0 +1,32 @@
+defmodule Mydia.Auth.GuardianTest do
+  use Mydia.DataCase
+
+  alias Mydia.AccountsFixtures
+  alias Mydia.Auth.Guardian
+
+  describe "verify_token_for_node/2" do
+    setup do
+      %{user: AccountsFixtures.user_fixture()}
+    end
+
+    test "accepts a bound token from the node it was issued to", %{user: user} do
+      {:ok, token, _claims} = Guardian.encode_and_sign(user, %{"node_id" => "node-a"})
+
+      assert {:ok, verified} = Guardian.verify_token_for_node(token, "node-a")
+      assert verified.id == user.id
+    end
+
+    test "rejects a bound token from another node", %{user: user} do
+      {:ok, token, _claims} = Guardian.encode_and_sign(user, %{"node_id" => "node-a"})
+
+      assert {:error, :node_mismatch} = Guardian.verify_token_for_node(token, "node-b")
+    end
+
+    test "accepts an unbound token from any node", %{user: user} do
+      {:ok, token, _claims} = Guardian.encode_and_sign(user, %{})
+
+      assert {:ok, verified} = Guardian.verify_token_for_node(token, "node-b")
+      assert verified.id == user.id
+    end
+  end
+end
@@ -0,0 +1,66 @@
+defmodule Mydia.P2p.ServerTest do
+  use ExUnit.Case, async: true
+
+  alias Mydia.P2p
+  alias Mydia.P2p.Server
+
+  defp state(graphql_tasks) do
+    %{
+      resource: nil,
+      node_id: "server-node",
+      node_addr: nil,
+      relay_connected: false,
+      connected_peers: %{},
+      hls_tasks: %{},
+      graphql_tasks: graphql_tasks
+    }
+  end
+
+  describe "GraphQL requests" do
+    test "run in a tracked task" do
+      req = %P2p.GraphQLRequest{query: "{ __typename }"}
+
+      assert {:noreply, state} =
+               Server.handle_info(
+                 {:ok, "request_received", "graphql", "req-1", "player-node", nil, req},
+                 state(%{})
+               )
+
+      assert %{"req-1" => pid} = state.graphql_tasks
+      assert is_pid(pid)
+    end
+
+    test "are killed when the client cancels them" do
+      pid = spawn(fn -> Process.sleep(:infinity) end)
+      ref = Process.monitor(pid)
+
+      assert {:noreply, state} =
+               Server.handle_info(
+                 {:ok, "request_cancelled", "req-1"},
+                 state(%{"req-1" => pid})
+               )
+
+      assert_receive {:DOWN, ^ref, :process, ^pid, :killed}
+      assert state.graphql_tasks == %{}
+    end
+
+    test "that already finished ignore a late cancellation" do
+      assert {:noreply, state} =
+               Server.handle_info({:ok, "request_cancelled", "req-1"}, state(%{}))
+
+      assert state.graphql_tasks == %{}
+    end
+
+    test "are forgotten once their task exits" do
+      pid = spawn(fn -> :ok end)
+
+      assert {:noreply, state} =
+               Server.handle_info(
+                 {:DOWN, make_ref(), :process, pid, :normal},
+                 state(%{"req-1" => pid})
+               )
+
+      assert state.graphql_tasks == %{}
+    end
+  end
+end
@@ -0,0 +1,54 @@
+defmodule Mydia.RemoteAccess.PairingTest do
+  use Mydia.DataCase
+
+  alias Mydia.AccountsFixtures
+  alias Mydia.Auth.Guardian
+  alias Mydia.RemoteAccess.Pairing
+  alias Mydia.RemoteAccess.PairingClaim
+
+  setup do
+    user = AccountsFixtures.user_fixture()
+
+    expires_at =
+      DateTime.utc_now()
+      |> DateTime.add(300, :second)
+      |> DateTime.truncate(:second)
+
+    {:ok, claim} =
+      %PairingClaim{}
+      |> PairingClaim.changeset_with_code(%{
+        user_id: user.id,
+        code: "ABCD2345",
+        expires_at: expires_at
+      })
+      |> Repo.insert()
+
+    %{user: user, claim: claim}
+  end
+
+  describe "complete_pairing/2" do
+    test "binds the access token to the node id the device paired from", %{claim: claim} do
+      attrs = %{device_name: "Phone", platform: "mobile", node_id: "node-a"}
+
+      assert {:ok, device, _media_token, access_token, _device_token} =
+               Pairing.complete_pairing(claim.code, attrs)
+
+      assert device.node_id == "node-a"
+      assert {:ok, claims} = Guardian.decode_and_verify(access_token)
+      assert claims["node_id"] == "node-a"
+      assert {:ok, _user} = Guardian.verify_token_for_node(access_token, "node-a")
+      assert {:error, :node_mismatch} = Guardian.verify_token_for_node(access_token, "node-b")
+    end
+
+    test "leaves the access token unbound without a node id", %{claim: claim} do
+      attrs = %{device_name: "Phone", platform: "mobile"}
+
+      assert {:ok, device, _media_token, access_token, _device_token} =
+               Pairing.complete_pairing(claim.code, attrs)
+
+      assert device.node_id == nil
+      assert {:ok, claims} = Guardian.decode_and_verify(access_token)
+      refute Map.has_key?(claims, "node_id")
+    end
+  end
+end